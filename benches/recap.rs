@@ -0,0 +1,85 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use iri_string::types::UriString;
+use serde_json::Value;
+use siwe::Message;
+use siwe_recap::{AbilityStr, Capability};
+
+fn capability() -> Capability<Value> {
+    let mut capability = Capability::new();
+    for target in ["public", "dapp-space", "private", "shared"] {
+        capability
+            .with_actions_convert(
+                format!("kepler:ens:example.eth://default/kv/{target}"),
+                [
+                    ("kv/list", []),
+                    ("kv/get", []),
+                    ("kv/metadata", []),
+                    ("kv/put", []),
+                    ("kv/delete", []),
+                ],
+            )
+            .unwrap();
+    }
+    capability
+        .with_action_convert("urn:credential:type:type1", "credential/present", [])
+        .unwrap();
+    capability
+}
+
+fn message() -> Message {
+    Message {
+        domain: "example.com".parse().unwrap(),
+        address: Default::default(),
+        statement: Some("Sign in to example.com.".into()),
+        uri: "did:key:example".parse().unwrap(),
+        version: siwe::Version::V1,
+        chain_id: 1,
+        nonce: "mynonce1".into(),
+        issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+        expiration_time: None,
+        not_before: None,
+        request_id: None,
+        resources: vec![],
+    }
+}
+
+fn benches(c: &mut Criterion) {
+    let capability = capability();
+    let uri = UriString::try_from(&capability.clone()).unwrap();
+    let message = capability.build_message(message()).unwrap();
+
+    // clones don't hold an encoded URI, as it's only cached by the clone encoding it above
+    c.bench_function("encode", |b| {
+        b.iter_batched(
+            || capability.clone(),
+            |capability| UriString::try_from(&capability).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("decode", |b| {
+        b.iter(|| Capability::<Value>::try_from(black_box(&uri)).unwrap())
+    });
+    c.bench_function("to_statement", |b| {
+        b.iter(|| black_box(&capability).to_statement())
+    });
+    let mut storage = Capability::<Value>::new();
+    for n in 0..500 {
+        storage
+            .with_action_convert(format!("https://example.com/storage/{n}"), "kv/get", [])
+            .unwrap();
+    }
+    let get = AbilityStr::parse("kv/get").unwrap();
+    c.bench_function("can_do_hierarchical", |b| {
+        b.iter(|| {
+            storage
+                .can_do_hierarchical(black_box("https://example.com/storage/250/photo.png"), get)
+                .unwrap()
+        })
+    });
+    c.bench_function("extract_and_verify", |b| {
+        b.iter(|| Capability::<Value>::extract_and_verify(black_box(&message)).unwrap())
+    });
+}
+
+criterion_group!(recap, benches);
+criterion_main!(recap);
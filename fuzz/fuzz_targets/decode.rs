@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+use siwe_recap::{Capability, DecodingProfile, RESOURCE_PREFIX};
+
+// Decode arbitrary ReCap URIs with both profiles; a payload accepted strictly must also be
+// accepted leniently.
+fuzz_target!(|encoded: &str| {
+    let Ok(uri) = format!("{RESOURCE_PREFIX}{encoded}").parse() else {
+        return;
+    };
+    let strict = Capability::<Value>::decode_with(&uri, DecodingProfile::Strict);
+    let lenient = Capability::<Value>::decode_with(&uri, DecodingProfile::Lenient);
+    assert!(strict.is_err() || lenient.is_ok());
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+use siwe::Message;
+use siwe_recap::{Capability, DecodingProfile, VerificationOptions};
+
+// Extract capabilities from arbitrary SIWE messages, as a verifier would from untrusted input.
+fuzz_target!(|message: &str| {
+    let Ok(message) = message.parse::<Message>() else {
+        return;
+    };
+    let _ = Capability::<Value>::extract_and_verify(&message);
+    let _ = Capability::<Value>::extract_and_verify_with(
+        &message,
+        &VerificationOptions::new().with_decoding_profile(DecodingProfile::Strict),
+    );
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use siwe_recap::{parse_ability, AbilityStr};
+
+// An accepted ability must also be accepted by borrowed parsing, and must re-parse to itself.
+fuzz_target!(|ability: &str| {
+    if let Ok(parsed) = parse_ability(ability) {
+        assert!(AbilityStr::parse(ability).is_ok());
+        assert_eq!(parse_ability(&parsed.to_string()).unwrap(), parsed);
+    }
+});
@@ -0,0 +1,54 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+use siwe::Message;
+use siwe_recap::{
+    Capability, DecodingError, DecodingLimits, VerificationError, VerificationOptions,
+};
+
+// Capabilities applied to a message must be extracted again, with the same statement.
+fuzz_target!(|json: &[u8]| {
+    let Ok(capability) = serde_json::from_slice::<Capability<Value>>(json) else {
+        return;
+    };
+    let Ok(message) = capability.build_message(message()) else {
+        return;
+    };
+    // serde_json limits nesting to 128 levels, so note-benes which parsed are within these limits
+    let options = VerificationOptions::new().with_decoding_limits(
+        DecodingLimits::new()
+            .with_max_nb_depth(128)
+            .with_max_nb_size(usize::MAX),
+    );
+    let extracted = match Capability::<Value>::extract_and_verify_with(&message, &options) {
+        Ok(Some(extracted)) => extracted,
+        // empty capabilities are not written to the message
+        Ok(None) => return assert!(capability.abilities().is_empty()),
+        // degenerate grants are encoded but rejected when decoding
+        Err(VerificationError::Decoding(DecodingError::DegenerateGrant { .. })) => return,
+        Err(error) => panic!("failed to extract the capabilities again: {error}"),
+    };
+    assert_eq!(extracted.to_statement(), capability.to_statement());
+    assert_eq!(
+        extracted.to_cid().unwrap(),
+        capability.to_cid().unwrap()
+    );
+});
+
+fn message() -> Message {
+    Message {
+        domain: "example.com".parse().unwrap(),
+        address: Default::default(),
+        statement: None,
+        uri: "did:key:example".parse().unwrap(),
+        version: siwe::Version::V1,
+        chain_id: 1,
+        nonce: "mynonce1".into(),
+        issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+        expiration_time: None,
+        not_before: None,
+        request_id: None,
+        resources: vec![],
+    }
+}
@@ -0,0 +1,1265 @@
+use crate::{elide_middle, Capability};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use iri_string::types::UriString;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DeserializeAs, DeserializeFromStr, SerializeAs, SerializeDisplay};
+use ucan_capabilities_object::{
+    Ability, AbilityName, AbilityNameRef, AbilityNamespace, AbilityNamespaceRef, NotaBeneCollection,
+};
+
+/// A borrowed view of an ability string, parsed without allocating.
+///
+/// The ability types of `ucan_capabilities_object` can only be borrowed from an owned [`Ability`],
+/// so this is used on hot verification paths which only need to compare abilities, not store them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AbilityStr<'a> {
+    namespace: &'a str,
+    name: &'a str,
+}
+
+impl<'a> AbilityStr<'a> {
+    /// Parse an ability of the form `namespace/name` with the default [`AbilityPolicy`].
+    pub fn parse(ability: &'a str) -> Result<Self, AbilityError> {
+        Self::parse_with(ability, &AbilityPolicy::default())
+    }
+
+    /// Parse an ability of the form `namespace/name` with the given policy.
+    pub fn parse_with(ability: &'a str, policy: &AbilityPolicy) -> Result<Self, AbilityError> {
+        let (namespace, name) =
+            ability
+                .split_once('/')
+                .ok_or_else(|| AbilityError::MissingSeparator {
+                    input: ability.to_string(),
+                })?;
+        policy.check_segment(ability, 0, namespace, Segment::Namespace)?;
+        policy.check_segment(ability, namespace.len() + 1, name, Segment::Name)?;
+        Ok(Self { namespace, name })
+    }
+
+    /// Construct an ability from segments which are known to be valid, e.g. the constants of
+    /// [`well_known`](crate::well_known).
+    pub(crate) const fn from_valid_parts(namespace: &'a str, name: &'a str) -> Self {
+        Self { namespace, name }
+    }
+
+    /// Convert to an owned [`Ability`].
+    pub fn to_ability(&self) -> Result<Ability, AbilityError> {
+        Ability::try_from(*self)
+    }
+
+    /// The namespace of the ability.
+    pub fn namespace(&self) -> &'a str {
+        self.namespace
+    }
+
+    /// The name of the ability.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+}
+
+impl fmt::Display for AbilityStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.namespace, self.name)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for AbilityStr<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ability = <&'a str>::deserialize(deserializer)?;
+        Self::parse(ability).map_err(serde::de::Error::custom)
+    }
+}
+
+impl PartialEq<Ability> for AbilityStr<'_> {
+    fn eq(&self, other: &Ability) -> bool {
+        displays_as(other.namespace(), self.namespace) && displays_as(other.name(), self.name)
+    }
+}
+
+impl TryFrom<AbilityStr<'_>> for Ability {
+    type Error = AbilityError;
+
+    fn try_from(ability: AbilityStr<'_>) -> Result<Self, Self::Error> {
+        Ability::try_from_parts(ability.namespace, ability.name)
+    }
+}
+
+impl PartialEq<AbilityStr<'_>> for Ability {
+    fn eq(&self, other: &AbilityStr<'_>) -> bool {
+        other == self
+    }
+}
+
+/// A pattern over abilities, where either segment may be the `*` wildcard, e.g. `kv/*`, `*/get` or `*/*`.
+///
+/// A wildcard segment matches any namespace or name, while a literal segment only matches itself.
+/// The bare `*` is equivalent to `*/*`.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct AbilityPattern {
+    namespace: Option<String>,
+    name: Option<String>,
+}
+
+impl AbilityPattern {
+    /// The pattern matching every ability.
+    pub fn any() -> Self {
+        Self {
+            namespace: None,
+            name: None,
+        }
+    }
+
+    /// The namespace matched by the pattern, or `None` for any namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The name matched by the pattern, or `None` for any name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Check whether the pattern matches the given ability.
+    ///
+    /// A namespace pattern ending in `.*`, e.g. `kv.*`, matches any sub-namespace such as `kv.admin`,
+    /// but not `kv` itself.
+    pub fn matches(&self, ability: &Ability) -> bool {
+        self.namespace
+            .as_deref()
+            .is_none_or(|namespace| match namespace.strip_suffix(".*") {
+                Some(parent) => is_sub_namespace(&ability.namespace().to_string(), parent),
+                None => displays_as(ability.namespace(), namespace),
+            })
+            && self
+                .name
+                .as_deref()
+                .is_none_or(|name| displays_as(ability.name(), name))
+    }
+}
+
+impl FromStr for AbilityPattern {
+    type Err = AbilityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(Self::any());
+        }
+        let ability = AbilityStr::parse(s)?;
+        let segment = |segment: &str| (segment != "*").then(|| segment.to_string());
+        Ok(Self {
+            namespace: segment(ability.namespace()),
+            name: segment(ability.name()),
+        })
+    }
+}
+
+impl TryFrom<&str> for AbilityPattern {
+    type Error = AbilityError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<&Ability> for AbilityPattern {
+    /// Convert an ability into a pattern, treating `*` segments as wildcards.
+    fn from(ability: &Ability) -> Self {
+        let segment = |segment: String| (segment != "*").then_some(segment);
+        Self {
+            namespace: segment(ability.namespace().to_string()),
+            name: segment(ability.name().to_string()),
+        }
+    }
+}
+
+/// Extensions to [`Ability`]: wildcard-aware comparisons and construction from its parts.
+///
+/// These are provided as a trait because `Ability` is defined in `ucan_capabilities_object`.
+pub trait AbilityExt: Sized {
+    /// Check whether this ability subsumes another, e.g. `kv/*` covers `kv/get` and `*/*` covers
+    /// everything. A wildcard in `other` is only covered by a wildcard.
+    fn covers(&self, other: &Ability) -> bool;
+
+    /// Borrow the namespace and name of the ability.
+    fn parts(&self) -> (AbilityNamespaceRef<'_>, AbilityNameRef<'_>);
+
+    /// Create an ability from an already-validated namespace and name.
+    fn from_parts(namespace: AbilityNamespace, name: AbilityName) -> Self;
+
+    /// Create an ability from a namespace and name, validating both with the default [`AbilityPolicy`].
+    fn try_from_parts(namespace: &str, name: &str) -> Result<Self, AbilityError>;
+
+    /// Check whether the ability is in the given namespace or one of its dotted sub-namespaces,
+    /// e.g. `kv.admin/rotate` is in `kv`.
+    fn is_in_namespace(&self, namespace: &str) -> bool;
+
+    /// Order abilities by their dotted namespace components, then by name, so that sub-namespaces
+    /// sort directly after their parent.
+    fn cmp_hierarchical(&self, other: &Ability) -> Ordering;
+
+    /// Render the ability in at most `max` characters, eliding the middle if necessary.
+    fn display_truncated(&self, max: usize) -> String;
+}
+
+impl AbilityExt for Ability {
+    fn covers(&self, other: &Ability) -> bool {
+        AbilityPattern::from(self).matches(other)
+    }
+
+    fn parts(&self) -> (AbilityNamespaceRef<'_>, AbilityNameRef<'_>) {
+        (self.namespace(), self.name())
+    }
+
+    fn from_parts(namespace: AbilityNamespace, name: AbilityName) -> Self {
+        format!("{namespace}/{name}")
+            .parse()
+            .expect("a valid namespace and name form a valid ability")
+    }
+
+    fn try_from_parts(namespace: &str, name: &str) -> Result<Self, AbilityError> {
+        let namespace: AbilityNamespace =
+            parse_namespace(namespace)?
+                .parse()
+                .map_err(|_| AbilityError::Rejected {
+                    input: namespace.to_string(),
+                })?;
+        let name: AbilityName = parse_name(name)?
+            .parse()
+            .map_err(|_| AbilityError::Rejected {
+                input: name.to_string(),
+            })?;
+        Ok(Self::from_parts(namespace, name))
+    }
+
+    fn is_in_namespace(&self, namespace: &str) -> bool {
+        let own = self.namespace().to_string();
+        own == namespace || is_sub_namespace(&own, namespace)
+    }
+
+    fn cmp_hierarchical(&self, other: &Ability) -> Ordering {
+        let (namespace, other_namespace) =
+            (self.namespace().to_string(), other.namespace().to_string());
+        namespace
+            .split('.')
+            .cmp(other_namespace.split('.'))
+            .then_with(|| self.name().cmp(&other.name()))
+    }
+
+    fn display_truncated(&self, max: usize) -> String {
+        elide_middle(&self.to_string(), max).into_owned()
+    }
+}
+
+/// Check whether `namespace` is a dotted sub-namespace of `parent`, e.g. `kv.admin` of `kv`.
+pub fn is_sub_namespace(namespace: &str, parent: &str) -> bool {
+    namespace
+        .strip_prefix(parent)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+impl fmt::Display for AbilityPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            self.namespace().unwrap_or("*"),
+            self.name().unwrap_or("*")
+        )
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// List the actions granted for the specified target which match the given ability pattern.
+    pub fn can_matching<'a>(
+        &'a self,
+        target: &UriString,
+        pattern: &'a AbilityPattern,
+    ) -> impl Iterator<Item = (&'a Ability, &'a NotaBeneCollection<NB>)> + 'a {
+        self.abilities()
+            .get(target)
+            .into_iter()
+            .flatten()
+            .filter(move |(ability, _)| pattern.matches(ability))
+    }
+}
+
+/// A `serde_with` adapter serializing an [`Ability`] as `{"namespace": "kv", "name": "get"}`
+/// rather than the `"kv/get"` string form.
+///
+/// Use it as `#[serde_as(as = "StructuredAbility")]`, or wrap an ability in [`Structured`].
+pub struct StructuredAbility;
+
+#[derive(Serialize, Deserialize)]
+struct AbilityFields<N> {
+    namespace: N,
+    name: N,
+}
+
+impl SerializeAs<Ability> for StructuredAbility {
+    fn serialize_as<S>(source: &Ability, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        AbilityFields {
+            namespace: source.namespace().to_string(),
+            name: source.name().to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> DeserializeAs<'de, Ability> for StructuredAbility {
+    fn deserialize_as<D>(deserializer: D) -> Result<Ability, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let fields = AbilityFields::<String>::deserialize(deserializer)?;
+        parse_namespace(&fields.namespace).map_err(serde::de::Error::custom)?;
+        parse_name(&fields.name).map_err(serde::de::Error::custom)?;
+        format!("{}/{}", fields.namespace, fields.name)
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// An [`Ability`] wrapper using the structured serde form of [`StructuredAbility`].
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Structured(#[serde_as(as = "StructuredAbility")] pub Ability);
+
+impl From<Ability> for Structured {
+    fn from(ability: Ability) -> Self {
+        Self(ability)
+    }
+}
+
+/// Namespaces reserved for protocol-level semantics, which applications must explicitly allow.
+pub const RESERVED_NAMESPACES: &[&str] = &["ucan"];
+
+/// Check whether an ability namespace is reserved.
+pub fn is_reserved_namespace(namespace: &str) -> bool {
+    RESERVED_NAMESPACES.contains(&namespace)
+}
+
+impl<NB> Capability<NB> {
+    /// List the grants using a reserved namespace which is not in the allowed set.
+    pub(crate) fn reserved_grants<'a>(
+        &'a self,
+        allowed: &'a BTreeSet<String>,
+    ) -> impl Iterator<Item = (&'a UriString, &'a Ability)> + 'a {
+        self.abilities()
+            .iter()
+            .flat_map(move |(target, abilities)| {
+                abilities
+                    .keys()
+                    .filter(move |ability| {
+                        let namespace = ability.namespace().to_string();
+                        is_reserved_namespace(&namespace) && !allowed.contains(&namespace)
+                    })
+                    .map(move |ability| (target, ability))
+            })
+    }
+}
+
+/// How the case of abilities is normalized, so that e.g. `KV/Get` and `kv/get` from different
+/// SDKs are treated as the same grant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CaseNormalization {
+    /// Keep abilities as written.
+    #[default]
+    Preserve,
+    /// Convert abilities to lowercase.
+    Lowercase,
+}
+
+impl CaseNormalization {
+    /// Parse an ability, applying the normalization.
+    pub fn parse(&self, ability: &str) -> Result<Ability, <Ability as FromStr>::Err> {
+        match self {
+            Self::Preserve => ability.parse(),
+            Self::Lowercase => ability.to_lowercase().parse(),
+        }
+    }
+
+    /// Apply the normalization to an ability, keeping it unchanged if the result is not a valid ability.
+    pub fn normalize(&self, ability: &Ability) -> Ability {
+        match self {
+            Self::Preserve => ability.clone(),
+            Self::Lowercase => self
+                .parse(&ability.to_string())
+                .unwrap_or_else(|_| ability.clone()),
+        }
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: Clone,
+{
+    /// Copy the capabilities with every ability normalized, merging grants which become equal.
+    pub fn normalized(&self, normalization: CaseNormalization) -> Self {
+        if normalization == CaseNormalization::Preserve {
+            return self.clone();
+        }
+        let mut normalized = Self::new().with_proofs(self.proof());
+        for (target, abilities) in self.abilities() {
+            for (ability, nbs) in abilities {
+                let mut grant = Self::new();
+                grant.with_action(
+                    target.clone(),
+                    normalization.normalize(ability),
+                    nbs.iter().cloned(),
+                );
+                normalized.merge_in(grant);
+            }
+        }
+        normalized
+    }
+}
+
+/// Which alphanumeric characters are allowed in ability namespaces and names, in addition to `-_.+*`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CharacterPolicy {
+    /// Only ASCII alphanumerics, preventing look-alike characters from spoofing abilities in statements.
+    #[default]
+    Ascii,
+    /// Any Unicode alphanumeric.
+    Unicode,
+}
+
+impl CharacterPolicy {
+    fn allows(&self, c: char) -> bool {
+        matches!(c, '-' | '_' | '.' | '+' | '*')
+            || match self {
+                Self::Ascii => c.is_ascii_alphanumeric(),
+                Self::Unicode => c.is_alphanumeric(),
+            }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Segment {
+    Namespace,
+    Name,
+}
+
+/// Validation rules for ability namespaces and names, and the targets they are granted on.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AbilityPolicy {
+    characters: CharacterPolicy,
+    max_namespace_length: usize,
+    max_name_length: usize,
+    max_target_length: usize,
+}
+
+impl AbilityPolicy {
+    /// The default maximum length in bytes of an ability namespace.
+    pub const DEFAULT_MAX_NAMESPACE_LENGTH: usize = 64;
+    /// The default maximum length in bytes of an ability name.
+    pub const DEFAULT_MAX_NAME_LENGTH: usize = 64;
+    /// The default maximum length in bytes of a target URI.
+    pub const DEFAULT_MAX_TARGET_LENGTH: usize = 1024;
+
+    /// Create the default policy, which only allows ASCII characters and limits lengths to the
+    /// `DEFAULT_MAX_*` constants.
+    pub fn new() -> Self {
+        Self {
+            characters: CharacterPolicy::default(),
+            max_namespace_length: Self::DEFAULT_MAX_NAMESPACE_LENGTH,
+            max_name_length: Self::DEFAULT_MAX_NAME_LENGTH,
+            max_target_length: Self::DEFAULT_MAX_TARGET_LENGTH,
+        }
+    }
+
+    /// Set the maximum length in bytes of an ability namespace.
+    pub fn with_max_namespace_length(mut self, max: usize) -> Self {
+        self.max_namespace_length = max;
+        self
+    }
+
+    /// Set the maximum length in bytes of an ability name.
+    pub fn with_max_name_length(mut self, max: usize) -> Self {
+        self.max_name_length = max;
+        self
+    }
+
+    /// Set the maximum length in bytes of a target URI.
+    pub fn with_max_target_length(mut self, max: usize) -> Self {
+        self.max_target_length = max;
+        self
+    }
+
+    /// Check a target URI against the policy.
+    pub fn check_target(&self, target: &str) -> Result<(), AbilityError> {
+        match target.len() > self.max_target_length {
+            true => Err(AbilityError::TargetTooLong {
+                length: target.len(),
+                max: self.max_target_length,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// Set which alphanumeric characters are allowed.
+    pub fn with_characters(mut self, characters: CharacterPolicy) -> Self {
+        self.characters = characters;
+        self
+    }
+
+    /// Check an ability against the policy.
+    pub fn check(&self, ability: &Ability) -> Result<(), AbilityError> {
+        AbilityStr::parse_with(&ability.to_string(), self).map(|_| ())
+    }
+
+    /// Check a segment of `input` starting at byte `offset`, reporting errors relative to `input`.
+    fn check_segment(
+        &self,
+        input: &str,
+        offset: usize,
+        segment: &str,
+        kind: Segment,
+    ) -> Result<(), AbilityError> {
+        if segment.is_empty() {
+            return Err(AbilityError::EmptySegment {
+                input: input.to_string(),
+                position: offset,
+            });
+        }
+        let length = segment.len();
+        match kind {
+            Segment::Namespace if length > self.max_namespace_length => {
+                return Err(AbilityError::NamespaceTooLong {
+                    length,
+                    max: self.max_namespace_length,
+                })
+            }
+            Segment::Name if length > self.max_name_length => {
+                return Err(AbilityError::NameTooLong {
+                    length,
+                    max: self.max_name_length,
+                })
+            }
+            _ => {}
+        }
+        if let Some((i, ch)) = segment
+            .char_indices()
+            .find(|(_, c)| !self.characters.allows(*c))
+        {
+            return Err(AbilityError::InvalidCharacter {
+                input: input.to_string(),
+                position: offset + i,
+                ch,
+            });
+        }
+        match kind {
+            Segment::Namespace => Self::check_sub_namespaces(input, offset, segment),
+            Segment::Name => Ok(()),
+        }
+    }
+
+    /// Check the dotted components of a namespace: none may be empty, and `*` may only appear as
+    /// the whole of the last component.
+    fn check_sub_namespaces(
+        input: &str,
+        offset: usize,
+        namespace: &str,
+    ) -> Result<(), AbilityError> {
+        let mut position = offset;
+        let mut components = namespace.split('.').peekable();
+        while let Some(component) = components.next() {
+            if component.is_empty() {
+                return Err(AbilityError::EmptySegment {
+                    input: input.to_string(),
+                    position,
+                });
+            }
+            if let Some(i) = component.find('*') {
+                if component != "*" || components.peek().is_some() {
+                    return Err(AbilityError::InvalidCharacter {
+                        input: input.to_string(),
+                        position: position + i,
+                        ch: '*',
+                    });
+                }
+            }
+            position += component.len() + 1;
+        }
+        Ok(())
+    }
+}
+
+impl Default for AbilityPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// List the grants whose targets or abilities violate the policy.
+    pub(crate) fn policy_violations(
+        &self,
+        policy: &AbilityPolicy,
+    ) -> Vec<(UriString, AbilityError)> {
+        self.abilities()
+            .iter()
+            .flat_map(|(target, abilities)| {
+                let target_error = policy
+                    .check_target(target.as_str())
+                    .err()
+                    .map(|e| (target.clone(), e));
+                target_error
+                    .into_iter()
+                    .chain(abilities.keys().filter_map(move |ability| {
+                        policy.check(ability).err().map(|e| (target.clone(), e))
+                    }))
+            })
+            .collect()
+    }
+}
+
+/// Parse an owned ability with the default [`AbilityPolicy`], reporting the position of any
+/// invalid character.
+pub fn parse_ability(ability: &str) -> Result<Ability, AbilityError> {
+    parse_ability_with(ability, &AbilityPolicy::default())
+}
+
+/// Parse an owned ability with the given policy, reporting the position of any invalid character.
+pub fn parse_ability_with(ability: &str, policy: &AbilityPolicy) -> Result<Ability, AbilityError> {
+    AbilityStr::parse_with(ability, policy)?;
+    ability.parse().map_err(|_| AbilityError::Rejected {
+        input: ability.to_string(),
+    })
+}
+
+/// Parse an ability namespace with the default [`AbilityPolicy`], returning it unchanged if valid.
+pub fn parse_namespace(namespace: &str) -> Result<&str, AbilityError> {
+    AbilityPolicy::default()
+        .check_segment(namespace, 0, namespace, Segment::Namespace)
+        .map(|_| namespace)
+}
+
+/// Parse an ability name with the default [`AbilityPolicy`], returning it unchanged if valid.
+pub fn parse_name(name: &str) -> Result<&str, AbilityError> {
+    AbilityPolicy::default()
+        .check_segment(name, 0, name, Segment::Name)
+        .map(|_| name)
+}
+
+/// Check at compile time whether a string is an ability of the form `namespace/name`.
+///
+/// Only ASCII segments are accepted, so that the check agrees with runtime parsing.
+pub const fn is_valid_ability(ability: &str) -> bool {
+    let bytes = ability.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() && bytes[i] != b'/' {
+        i += 1;
+    }
+    i < bytes.len()
+        && is_valid_const_segment(bytes, 0, i)
+        && is_valid_const_segment(bytes, i + 1, bytes.len())
+}
+
+/// Split an ability checked with [`is_valid_ability`] at compile time, for the
+/// [`ability!`](crate::ability) macro.
+pub const fn split_valid_ability(ability: &str) -> AbilityStr<'_> {
+    assert!(is_valid_ability(ability), "invalid ability");
+    let mut i = 0;
+    while ability.as_bytes()[i] != b'/' {
+        i += 1;
+    }
+    let (namespace, name) = ability.split_at(i);
+    AbilityStr::from_valid_parts(namespace, name.split_at(1).1)
+}
+
+/// Check at compile time whether a string is an ability namespace.
+pub const fn is_valid_namespace(namespace: &str) -> bool {
+    let bytes = namespace.as_bytes();
+    is_valid_const_segment(bytes, 0, bytes.len())
+}
+
+/// Check at compile time whether a string is a target URI, i.e. matches the URI grammar of
+/// RFC 3986 with a non-empty part after the scheme, so that parsing it as a [`UriString`] cannot
+/// fail.
+pub const fn is_valid_target(target: &str) -> bool {
+    let bytes = target.as_bytes();
+    if bytes.is_empty() || !bytes[0].is_ascii_alphabetic() {
+        return false;
+    }
+    let mut i = 1;
+    while i < bytes.len() && bytes[i] != b':' {
+        if !(bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'+' | b'-' | b'.')) {
+            return false;
+        }
+        i += 1;
+    }
+    if i + 1 >= bytes.len() {
+        return false;
+    }
+    i += 1;
+    if i + 1 < bytes.len() && bytes[i] == b'/' && bytes[i + 1] == b'/' {
+        let end = find_any(bytes, i + 2, b"/?#");
+        if !is_valid_authority(bytes, i + 2, end) {
+            return false;
+        }
+        i = end;
+    }
+    let end = find_any(bytes, i, b"?#");
+    if !is_uri_run(bytes, i, end, b":@/") {
+        return false;
+    }
+    i = end;
+    if i < bytes.len() && bytes[i] == b'?' {
+        let end = find_any(bytes, i + 1, b"#");
+        if !is_uri_run(bytes, i + 1, end, b":@/?") {
+            return false;
+        }
+        i = end;
+    }
+    i == bytes.len() || is_uri_run(bytes, i + 1, bytes.len(), b":@/?")
+}
+
+/// The index of the first of the `stops` from `start`, or the length of the bytes.
+const fn find_any(bytes: &[u8], start: usize, stops: &[u8]) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        let mut j = 0;
+        while j < stops.len() {
+            if bytes[i] == stops[j] {
+                return i;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// The length of the URI character at `i`, which is 3 for a percent-encoded byte, or 0 if it is
+/// neither unreserved, a sub-delimiter, nor in `extra`.
+const fn uri_char_len(bytes: &[u8], i: usize, extra: &[u8]) -> usize {
+    let c = bytes[i];
+    if c == b'%' {
+        return if i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            3
+        } else {
+            0
+        };
+    }
+    if c.is_ascii_alphanumeric()
+        || matches!(
+            c,
+            b'-' | b'.'
+                | b'_'
+                | b'~'
+                | b'!'
+                | b'$'
+                | b'&'
+                | b'\''
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+        )
+    {
+        return 1;
+    }
+    if find_any(extra, 0, &[c]) < extra.len() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Whether the bytes from `start` to `end` only have URI characters.
+const fn is_uri_run(bytes: &[u8], start: usize, end: usize, extra: &[u8]) -> bool {
+    let mut i = start;
+    while i < end {
+        match uri_char_len(bytes, i, extra) {
+            0 => return false,
+            len => i += len,
+        }
+    }
+    i == end
+}
+
+/// `[ userinfo "@" ] host [ ":" port ]`
+const fn is_valid_authority(bytes: &[u8], start: usize, end: usize) -> bool {
+    let mut host = start;
+    let at = find_any(bytes, start, b"@");
+    if at < end {
+        if !is_uri_run(bytes, start, at, b":") {
+            return false;
+        }
+        host = at + 1;
+    }
+    let mut port = end;
+    if host < end && bytes[host] == b'[' {
+        let close = find_any(bytes, host + 1, b"]");
+        if close >= end || !is_valid_ip_literal(bytes, host + 1, close) {
+            return false;
+        }
+        if close + 1 < end {
+            if bytes[close + 1] != b':' {
+                return false;
+            }
+            port = close + 1;
+        }
+    } else {
+        port = find_any(bytes, host, b":");
+        if port > end {
+            port = end;
+        }
+        if !is_uri_run(bytes, host, port, b"") {
+            return false;
+        }
+    }
+    let mut i = port + 1;
+    while i < end {
+        if !bytes[i].is_ascii_digit() {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// `IPv6address / IPvFuture`, between the brackets.
+const fn is_valid_ip_literal(bytes: &[u8], start: usize, end: usize) -> bool {
+    if start < end && (bytes[start] == b'v' || bytes[start] == b'V') {
+        let mut i = start + 1;
+        while i < end && bytes[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        return i > start + 1
+            && i + 1 < end
+            && bytes[i] == b'.'
+            // percent-encoding is not allowed in IPvFuture
+            && find_any(bytes, i + 1, b"%") >= end
+            && is_uri_run(bytes, i + 1, end, b":");
+    }
+    let mut i = start;
+    let mut groups = 0;
+    let mut elided = false;
+    if i + 1 < end && bytes[i] == b':' && bytes[i + 1] == b':' {
+        elided = true;
+        i += 2;
+    }
+    while i < end {
+        let mut j = i;
+        while j < end && j - i < 5 && bytes[j].is_ascii_hexdigit() {
+            j += 1;
+        }
+        if j < end && bytes[j] == b'.' {
+            if !is_valid_ipv4(bytes, i, end) {
+                return false;
+            }
+            groups += 2;
+            break;
+        }
+        if j == i || j - i > 4 {
+            return false;
+        }
+        groups += 1;
+        i = j;
+        if i == end {
+            break;
+        }
+        if bytes[i] != b':' || i + 1 == end {
+            return false;
+        }
+        i += 1;
+        if bytes[i] == b':' {
+            if elided {
+                return false;
+            }
+            elided = true;
+            i += 1;
+        }
+    }
+    if elided {
+        groups <= 7
+    } else {
+        groups == 8
+    }
+}
+
+/// Four decimal octets without leading zeros, separated by dots.
+const fn is_valid_ipv4(bytes: &[u8], start: usize, end: usize) -> bool {
+    let mut i = start;
+    let mut octets = 0;
+    while octets < 4 {
+        let mut value = 0u32;
+        let first = i;
+        while i < end && bytes[i].is_ascii_digit() && i - first < 3 {
+            value = value * 10 + (bytes[i] - b'0') as u32;
+            i += 1;
+        }
+        if i == first || value > 255 || (i - first > 1 && bytes[first] == b'0') {
+            return false;
+        }
+        octets += 1;
+        if octets < 4 {
+            if i >= end || bytes[i] != b'.' {
+                return false;
+            }
+            i += 1;
+        }
+    }
+    i == end
+}
+
+const fn is_valid_const_segment(bytes: &[u8], start: usize, end: usize) -> bool {
+    if start >= end {
+        return false;
+    }
+    let mut i = start;
+    while i < end {
+        if !(bytes[i].is_ascii_alphanumeric()
+            || matches!(bytes[i], b'-' | b'_' | b'.' | b'+' | b'*'))
+        {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Compare the `Display` output of a value against a string without allocating.
+fn displays_as(value: impl fmt::Display, expected: &str) -> bool {
+    struct Matcher<'a>(&'a str);
+
+    impl Write for Matcher<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+            Ok(())
+        }
+    }
+
+    let mut matcher = Matcher(expected);
+    write!(matcher, "{value}").is_ok() && matcher.0.is_empty()
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AbilityError {
+    #[error("ability {input:?} must be of the form namespace/name")]
+    MissingSeparator { input: String },
+    #[error("empty segment at byte {position} of {input:?}")]
+    EmptySegment { input: String, position: usize },
+    #[error("invalid character {ch:?} at byte {position} of {input:?}")]
+    InvalidCharacter {
+        input: String,
+        position: usize,
+        ch: char,
+    },
+    #[error("ability {input:?} was rejected")]
+    Rejected { input: String },
+    #[error("ability namespace is {length} bytes long, exceeding the maximum of {max}")]
+    NamespaceTooLong { length: usize, max: usize },
+    #[error("ability name is {length} bytes long, exceeding the maximum of {max}")]
+    NameTooLong { length: usize, max: usize },
+    #[error("target is {length} bytes long, exceeding the maximum of {max}")]
+    TargetTooLong { length: usize, max: usize },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn borrowed_parse() {
+        let ability = AbilityStr::parse("kv/get").unwrap();
+        assert_eq!(ability.namespace(), "kv");
+        assert_eq!(ability.name(), "get");
+        assert_eq!(ability, "kv/get".parse::<Ability>().unwrap());
+        assert_ne!(ability, "kv/getx".parse::<Ability>().unwrap());
+        assert_ne!(ability, "k/vget".parse::<Ability>().unwrap());
+
+        assert!(matches!(
+            AbilityStr::parse("kv"),
+            Err(AbilityError::MissingSeparator { .. })
+        ));
+        assert!(matches!(
+            AbilityStr::parse("/get"),
+            Err(AbilityError::EmptySegment { position: 0, .. })
+        ));
+        assert!(matches!(
+            AbilityStr::parse("kv/"),
+            Err(AbilityError::EmptySegment { position: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn character_policy() {
+        // Cyrillic 'е' in place of the Latin 'e'
+        let spoofed = "kv/g\u{435}t";
+        assert!(matches!(
+            parse_ability(spoofed),
+            Err(AbilityError::InvalidCharacter {
+                position: 4,
+                ch: '\u{435}',
+                ..
+            })
+        ));
+        let unicode = AbilityPolicy::new().with_characters(CharacterPolicy::Unicode);
+        assert!(parse_ability_with(spoofed, &unicode).is_ok());
+        assert!(unicode.check(&spoofed.parse().unwrap()).is_ok());
+        assert!(AbilityPolicy::new()
+            .check(&spoofed.parse().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn length_limits() {
+        let long = "a".repeat(AbilityPolicy::DEFAULT_MAX_NAME_LENGTH + 1);
+        assert!(matches!(
+            parse_ability(&format!("kv/{long}")),
+            Err(AbilityError::NameTooLong { max: 64, .. })
+        ));
+        assert!(matches!(
+            parse_namespace(&long),
+            Err(AbilityError::NamespaceTooLong { .. })
+        ));
+        let short = AbilityPolicy::new()
+            .with_max_namespace_length(2)
+            .with_max_target_length(24);
+        assert!(AbilityStr::parse_with("kv/get", &short).is_ok());
+        assert!(matches!(
+            AbilityStr::parse_with("credential/present", &short),
+            Err(AbilityError::NamespaceTooLong { length: 10, max: 2 })
+        ));
+
+        let mut capability = Capability::<serde_json::Value>::default();
+        capability
+            .with_action_convert("https://example.com/a/long/path", "kv/get", [])
+            .unwrap();
+        assert!(matches!(
+            capability.policy_violations(&short).as_slice(),
+            [(
+                _,
+                AbilityError::TargetTooLong {
+                    length: 31,
+                    max: 24
+                }
+            )]
+        ));
+    }
+
+    #[test]
+    fn parse_error_position() {
+        assert_eq!(
+            parse_ability("credential/pre sent"),
+            Err(AbilityError::InvalidCharacter {
+                input: "credential/pre sent".into(),
+                position: 14,
+                ch: ' ',
+            })
+        );
+        assert_eq!(
+            parse_ability("kv/get").unwrap(),
+            "kv/get".parse::<Ability>().unwrap()
+        );
+        assert!(matches!(
+            parse_name("g?t"),
+            Err(AbilityError::InvalidCharacter {
+                position: 1,
+                ch: '?',
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn case_normalization() {
+        let lowercase = CaseNormalization::Lowercase;
+        assert_eq!(
+            lowercase.parse("KV/Get").unwrap(),
+            "kv/get".parse::<Ability>().unwrap()
+        );
+        assert_eq!(
+            CaseNormalization::Preserve
+                .parse("KV/Get")
+                .unwrap()
+                .to_string(),
+            "KV/Get"
+        );
+
+        let mut capability = Capability::<serde_json::Value>::default();
+        capability
+            .with_actions_convert(
+                "kepler:ens:example.eth://default/kv",
+                [("KV/Get", []), ("kv/get", [])],
+            )
+            .unwrap();
+        let normalized = capability.normalized(lowercase);
+        let abilities = normalized
+            .abilities_for("kepler:ens:example.eth://default/kv")
+            .unwrap()
+            .unwrap();
+        assert_eq!(abilities.len(), 1);
+        assert!(normalized
+            .can("kepler:ens:example.eth://default/kv", "kv/get")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn structured_serde() {
+        #[serde_as]
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Grants {
+            #[serde_as(as = "Vec<StructuredAbility>")]
+            abilities: Vec<Ability>,
+        }
+
+        let grants = Grants {
+            abilities: vec!["kv/get".parse().unwrap()],
+        };
+        let json = serde_json::to_string(&grants).unwrap();
+        assert_eq!(json, r#"{"abilities":[{"namespace":"kv","name":"get"}]}"#);
+        assert_eq!(serde_json::from_str::<Grants>(&json).unwrap(), grants);
+
+        let structured = Structured("kv/get".parse().unwrap());
+        let json = serde_json::to_value(&structured).unwrap();
+        assert_eq!(json, serde_json::json!({"namespace": "kv", "name": "get"}));
+        assert!(serde_json::from_value::<Structured>(
+            serde_json::json!({"namespace": "kv/x", "name": "get"})
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parts() {
+        let ability = Ability::try_from_parts("kv", "get").unwrap();
+        assert_eq!(ability, "kv/get".parse::<Ability>().unwrap());
+        let (namespace, name) = ability.parts();
+        assert_eq!(
+            (namespace.to_string(), name.to_string()),
+            ("kv".into(), "get".into())
+        );
+        assert_eq!(
+            Ability::from_parts("kv".parse().unwrap(), "put".parse().unwrap()).to_string(),
+            "kv/put"
+        );
+        assert!(matches!(
+            Ability::try_from_parts("kv/x", "get"),
+            Err(AbilityError::InvalidCharacter { ch: '/', .. })
+        ));
+    }
+
+    #[test]
+    fn sub_namespaces() {
+        let ability = |s: &str| s.parse::<Ability>().unwrap();
+        assert!(parse_ability("kv.admin/rotate").is_ok());
+        assert!(parse_ability("kv.*/get").is_ok());
+        assert!(matches!(
+            parse_ability("kv..admin/rotate"),
+            Err(AbilityError::EmptySegment { position: 3, .. })
+        ));
+        assert!(matches!(
+            parse_ability("kv./get"),
+            Err(AbilityError::EmptySegment { position: 3, .. })
+        ));
+        assert!(matches!(
+            parse_ability("*.kv/get"),
+            Err(AbilityError::InvalidCharacter { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse_ability("kv.ad*/get"),
+            Err(AbilityError::InvalidCharacter { position: 5, .. })
+        ));
+
+        assert!(ability("kv.*/*").covers(&ability("kv.admin/rotate")));
+        assert!(ability("kv.*/*").covers(&ability("kv.admin.keys/rotate")));
+        assert!(ability("kv.*/*").covers(&ability("kv.admin.*/rotate")));
+        assert!(!ability("kv.*/*").covers(&ability("kv/get")));
+        assert!(!ability("kv.*/*").covers(&ability("kvx.admin/get")));
+        assert!(!ability("kv.admin.*/*").covers(&ability("kv.*/get")));
+
+        assert_eq!(
+            ability("kv.admin/rotate").display_truncated(10),
+            "kv.ad…tate"
+        );
+        assert!(ability("kv.admin/rotate").is_in_namespace("kv"));
+        assert!(ability("kv/get").is_in_namespace("kv"));
+        assert!(!ability("kv-x/get").is_in_namespace("kv"));
+
+        let mut abilities = vec![
+            ability("kv.admin/rotate"),
+            ability("kv-x/get"),
+            ability("kv/get"),
+        ];
+        abilities.sort_by(|a, b| a.cmp_hierarchical(b));
+        assert_eq!(
+            abilities,
+            [
+                ability("kv/get"),
+                ability("kv.admin/rotate"),
+                ability("kv-x/get")
+            ]
+        );
+    }
+
+    #[test]
+    fn covers() {
+        let ability = |s: &str| s.parse::<Ability>().unwrap();
+        assert!(ability("kv/*").covers(&ability("kv/get")));
+        assert!(ability("kv/get").covers(&ability("kv/get")));
+        assert!(ability("*/*").covers(&ability("credential/present")));
+        assert!(ability("*/get").covers(&ability("kv/get")));
+        assert!(!ability("kv/get").covers(&ability("kv/*")));
+        assert!(!ability("kv/*").covers(&ability("*/*")));
+        assert!(!ability("kv/*").covers(&ability("credential/present")));
+    }
+
+    #[test]
+    fn pattern_matching() {
+        let get: Ability = "kv/get".parse().unwrap();
+        let put: Ability = "kv/put".parse().unwrap();
+        let present: Ability = "credential/present".parse().unwrap();
+
+        let kv: AbilityPattern = "kv/*".parse().unwrap();
+        assert!(kv.matches(&get) && kv.matches(&put) && !kv.matches(&present));
+        let any_get: AbilityPattern = "*/get".parse().unwrap();
+        assert!(any_get.matches(&get) && !any_get.matches(&put));
+        let any: AbilityPattern = "*".parse().unwrap();
+        assert_eq!(any.to_string(), "*/*");
+        assert!(any.matches(&get) && any.matches(&present));
+        assert!(AbilityPattern::from(&get).matches(&get));
+        assert!(!AbilityPattern::from(&get).matches(&put));
+        assert!("kv".parse::<AbilityPattern>().is_err());
+        assert_eq!(
+            AbilityPattern::from(&"kv/*".parse::<Ability>().unwrap()),
+            kv
+        );
+
+        let mut capability = Capability::<serde_json::Value>::default();
+        capability
+            .with_actions_convert(
+                "kepler:ens:example.eth://default/kv",
+                [("kv/get", []), ("kv/put", []), ("kv/list", [])],
+            )
+            .unwrap();
+        let matching: Vec<_> = capability
+            .can_matching(
+                &"kepler:ens:example.eth://default/kv".parse().unwrap(),
+                &any_get,
+            )
+            .map(|(ability, _)| ability)
+            .collect();
+        assert_eq!(matching, [&get]);
+    }
+}
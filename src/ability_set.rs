@@ -0,0 +1,225 @@
+use crate::{AbilityExt, Capability};
+use std::collections::{btree_set, BTreeMap, BTreeSet};
+
+use iri_string::types::UriString;
+use serde::{Deserialize, Serialize};
+use ucan_capabilities_object::{Ability, AbilityNameRef, AbilityNamespaceRef};
+
+/// An ordered set of abilities with wildcard-aware queries.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AbilitySet(BTreeSet<Ability>);
+
+impl AbilitySet {
+    /// Create a new empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an ability, returning whether it was newly inserted.
+    pub fn insert(&mut self, ability: Ability) -> bool {
+        self.0.insert(ability)
+    }
+
+    /// Remove an ability, returning whether it was present.
+    pub fn remove(&mut self, ability: &Ability) -> bool {
+        self.0.remove(ability)
+    }
+
+    /// Check whether the ability is covered by any ability in the set, e.g. `kv/get` is contained
+    /// in a set holding `kv/*`.
+    pub fn contains(&self, ability: &Ability) -> bool {
+        self.0.contains(ability) || self.0.iter().any(|member| member.covers(ability))
+    }
+
+    /// Check whether exactly this ability is in the set, ignoring wildcards.
+    pub fn contains_exact(&self, ability: &Ability) -> bool {
+        self.0.contains(ability)
+    }
+
+    /// The number of abilities in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the abilities in order.
+    pub fn iter(&self) -> btree_set::Iter<'_, Ability> {
+        self.0.iter()
+    }
+
+    /// Group the abilities by namespace.
+    pub fn by_namespace(&self) -> BTreeMap<AbilityNamespaceRef<'_>, Vec<AbilityNameRef<'_>>> {
+        self.0.iter().fold(BTreeMap::new(), |mut map, ability| {
+            map.entry(ability.namespace())
+                .or_insert_with(Vec::new)
+                .push(ability.name());
+            map
+        })
+    }
+
+    /// Combine the abilities of both sets.
+    pub fn union(&self, other: &AbilitySet) -> AbilitySet {
+        self.0.union(&other.0).cloned().collect()
+    }
+
+    /// Keep the abilities granted by both sets, e.g. the intersection of `kv/*` and `kv/get` is
+    /// `kv/get`.
+    pub fn intersection(&self, other: &AbilitySet) -> AbilitySet {
+        self.0
+            .iter()
+            .filter(|ability| other.contains(ability))
+            .chain(other.0.iter().filter(|ability| self.contains(ability)))
+            .cloned()
+            .collect()
+    }
+}
+
+impl FromIterator<Ability> for AbilitySet {
+    fn from_iter<I: IntoIterator<Item = Ability>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Ability> for AbilitySet {
+    fn extend<I: IntoIterator<Item = Ability>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for AbilitySet {
+    type Item = Ability;
+    type IntoIter = btree_set::IntoIter<Ability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AbilitySet {
+    type Item = &'a Ability;
+    type IntoIter = btree_set::Iter<'a, Ability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// Collect the abilities granted for the specified target.
+    pub fn ability_set(&self, target: &UriString) -> AbilitySet {
+        self.abilities()
+            .get(target)
+            .into_iter()
+            .flat_map(|abilities| abilities.keys().cloned())
+            .collect()
+    }
+
+    /// List the grants whose ability is not contained in the allowed set.
+    pub(crate) fn disallowed_grants<'a>(
+        &'a self,
+        allowed: &'a AbilitySet,
+    ) -> impl Iterator<Item = (&'a UriString, &'a Ability)> + 'a {
+        self.abilities()
+            .iter()
+            .flat_map(move |(target, abilities)| {
+                abilities
+                    .keys()
+                    .filter(move |ability| !allowed.contains(ability))
+                    .map(move |ability| (target, ability))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::message;
+    use crate::{BuildError, Builder, VerificationError, VerificationOptions};
+    use serde_json::Value;
+
+    fn set(abilities: &[&str]) -> AbilitySet {
+        abilities.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ability_set() {
+        let kv = set(&["kv/*", "credential/present"]);
+        assert!(kv.contains(&"kv/get".parse().unwrap()));
+        assert!(!kv.contains_exact(&"kv/get".parse().unwrap()));
+        assert!(!kv.contains(&"credential/revoke".parse().unwrap()));
+
+        assert_eq!(
+            kv.intersection(&set(&["kv/get", "kv/put", "credential/revoke"])),
+            set(&["kv/get", "kv/put"])
+        );
+        assert_eq!(
+            kv.union(&set(&["kv/get"])),
+            set(&["kv/*", "kv/get", "credential/present"])
+        );
+
+        let grouped: Vec<(String, Vec<String>)> = set(&["kv/get", "kv/put", "credential/present"])
+            .by_namespace()
+            .into_iter()
+            .map(|(ns, names)| {
+                (
+                    ns.to_string(),
+                    names.iter().map(|n| n.to_string()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            [
+                ("credential".into(), vec!["present".into()]),
+                ("kv".into(), vec!["get".into(), "put".into()])
+            ]
+        );
+
+        assert_eq!(
+            serde_json::to_string(&set(&["kv/get", "kv/put"])).unwrap(),
+            r#"["kv/get","kv/put"]"#
+        );
+    }
+
+    #[test]
+    fn allowed_abilities() {
+        let target: UriString = "https://example.com/kv".parse().unwrap();
+        let builder = Builder::<Value>::new()
+            .with_abilities(target.clone(), set(&["kv/get", "kv/put"]))
+            .unwrap();
+        assert_eq!(
+            builder.capability().ability_set(&target),
+            set(&["kv/get", "kv/put"])
+        );
+
+        let allowed = set(&["kv/get", "credential/*"]);
+        assert!(matches!(
+            builder
+                .clone()
+                .with_allowed_abilities(allowed.clone())
+                .validate()
+                .unwrap_err()
+                .errors(),
+            [BuildError::DisallowedAbility { ability, .. }] if ability.to_string() == "kv/put"
+        ));
+
+        let message = builder.build(message()).unwrap();
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify_with(
+                &message,
+                &VerificationOptions::new().with_allowed_abilities(allowed)
+            ),
+            Err(VerificationError::DisallowedAbility { ability, .. }) if ability.to_string() == "kv/put"
+        ));
+        assert!(Capability::<Value>::extract_and_verify_with(
+            &message,
+            &VerificationOptions::new().with_allowed_abilities(set(&["kv/*"]))
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::session::header_str;
+use crate::{
+    RequiredCapabilities, SessionError, SessionHeader, VerificationOptions, VerifiedSession,
+    SESSION_SCHEME,
+};
+use std::future::{ready, Future};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpMessage, HttpResponse, ResponseError};
+use serde::Deserialize;
+
+/// An actix-web middleware verifying the session in the `Authorization` header, and attaching the
+/// [`VerifiedSession`] to the request extensions for handlers to read with
+/// `web::ReqData<VerifiedSession<NB>>`.
+///
+/// Requests are rejected with a 401 response if the session cannot be authenticated, or a 403
+/// response if its capabilities are rejected or do not satisfy the requirements of the route.
+///
+/// ```
+/// # use actix_web::web;
+/// # use siwe_recap::{RecapSession, RequiredCapabilities, VerificationOptions};
+/// let kv = web::resource("/kv").wrap(
+///     RecapSession::<serde_json::Value>::new(VerificationOptions::new()).require(
+///         RequiredCapabilities::new().with_action(
+///             "https://example.com/kv".parse().unwrap(),
+///             "kv/get".parse().unwrap(),
+///         ),
+///     ),
+/// );
+/// ```
+pub struct RecapSession<NB> {
+    options: Arc<VerificationOptions>,
+    required: Arc<RequiredCapabilities>,
+    _nota_bene: PhantomData<fn() -> NB>,
+}
+
+impl<NB> RecapSession<NB> {
+    /// Create a middleware verifying sessions with the given options.
+    pub fn new(options: impl Into<Arc<VerificationOptions>>) -> Self {
+        Self {
+            options: options.into(),
+            required: Default::default(),
+            _nota_bene: PhantomData,
+        }
+    }
+
+    /// Require verified sessions to have been delegated the given capabilities.
+    pub fn require(mut self, required: RequiredCapabilities) -> Self {
+        self.required = Arc::new(required);
+        self
+    }
+}
+
+impl<NB> Clone for RecapSession<NB> {
+    fn clone(&self) -> Self {
+        Self {
+            options: self.options.clone(),
+            required: self.required.clone(),
+            _nota_bene: PhantomData,
+        }
+    }
+}
+
+impl<S, B, NB> Transform<S, ServiceRequest> for RecapSession<NB>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+    NB: for<'a> Deserialize<'a> + Clone + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RecapSessionMiddleware<S, NB>;
+    type InitError = ();
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RecapSessionMiddleware {
+            service,
+            session: self.clone(),
+        }))
+    }
+}
+
+/// The service produced by the [`RecapSession`] middleware.
+pub struct RecapSessionMiddleware<S, NB> {
+    service: S,
+    session: RecapSession<NB>,
+}
+
+impl<S, NB> RecapSessionMiddleware<S, NB>
+where
+    NB: for<'a> Deserialize<'a> + Clone,
+{
+    fn verify(&self, request: &ServiceRequest) -> Result<VerifiedSession<NB>, SessionError> {
+        let header = header_str(
+            request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .map(|value| value.as_bytes()),
+        )?;
+        let session = SessionHeader::decode_and_verify(header, &self.session.options)?;
+        session.require(&self.session.required)?;
+        Ok(session)
+    }
+}
+
+impl<S, B, NB> Service<ServiceRequest> for RecapSessionMiddleware<S, NB>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+    NB: for<'a> Deserialize<'a> + Clone + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        match self.verify(&request) {
+            Ok(session) => {
+                request.extensions_mut().insert(session);
+                let response = self.service.call(request);
+                Box::pin(async move { response.await.map(ServiceResponse::map_into_left_body) })
+            }
+            Err(e) => {
+                let response = request.error_response(e).map_into_right_body();
+                Box::pin(ready(Ok(response)))
+            }
+        }
+    }
+}
+
+impl ResponseError for SessionError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if self.status_code() == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, SESSION_SCHEME));
+        }
+        response.json(self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{session_header, verification_options, TestSigner};
+    use crate::Builder;
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    async fn address(session: web::ReqData<VerifiedSession<Value>>) -> String {
+        format!("{:?}", session.address())
+    }
+
+    #[tokio::test]
+    async fn middleware() {
+        let options = verification_options();
+        let required = |ability: &str| {
+            RequiredCapabilities::new().with_action(
+                "https://example.com/kv".parse().unwrap(),
+                ability.parse().unwrap(),
+            )
+        };
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::resource("/get")
+                        .wrap(
+                            RecapSession::<Value>::new(options.clone()).require(required("kv/get")),
+                        )
+                        .to(address),
+                )
+                .service(
+                    web::resource("/put")
+                        .wrap(RecapSession::<Value>::new(options).require(required("kv/put")))
+                        .to(address),
+                ),
+        )
+        .await;
+
+        let header = session_header(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+
+        let call = |path: &str, authorization: Option<&str>| {
+            let mut request = test::TestRequest::get().uri(path);
+            if let Some(authorization) = authorization {
+                request = request.insert_header((header::AUTHORIZATION, authorization));
+            }
+            test::call_service(&app, request.to_request())
+        };
+        let response = call("/get", Some(&header)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            test::read_body(response).await,
+            format!("{:?}", TestSigner::new().address())
+        );
+        assert_eq!(
+            call("/put", Some(&header)).await.status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(call("/get", None).await.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,223 @@
+use cid::Cid;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde::{de, ser, ser::SerializeMap, Deserialize, Deserializer, Serializer};
+use serde_with::{de::DeserializeAsWrap, ser::SerializeAsWrap, DeserializeAs, Same, SerializeAs};
+use smallvec::{Array, SmallVec};
+
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+/// Serde adapter for [`Cid`]s, encoded as base58btc strings as in the `prf` field of a ReCap.
+///
+/// ```
+/// # use cid::Cid;
+/// # use serde::{Deserialize, Serialize};
+/// #[serde_with::serde_as]
+/// #[derive(Serialize, Deserialize)]
+/// struct Session {
+///     #[serde_as(as = "Vec<siwe_recap::AsB58Cid>")]
+///     proofs: Vec<Cid>,
+/// }
+/// ```
+pub struct AsB58Cid;
+
+impl SerializeAs<Cid> for AsB58Cid {
+    fn serialize_as<S>(source: &Cid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(
+            &source
+                .to_string_of_base(cid::multibase::Base::Base58Btc)
+                .map_err(ser::Error::custom)?,
+        )
+    }
+}
+
+impl<'de> DeserializeAs<'de, Cid> for AsB58Cid {
+    fn deserialize_as<D>(deserializer: D) -> Result<Cid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        parse_b58_cid(&String::deserialize(deserializer)?)
+    }
+}
+
+/// Parse a base58btc encoded CID, as [`AsB58Cid`] deserializes them.
+pub(crate) fn parse_b58_cid<E: de::Error>(s: &str) -> Result<Cid, E> {
+    if !s.starts_with('z') {
+        return Err(E::custom("non-base58btc encoded Cid"));
+    };
+    Cid::from_str(s).map_err(E::custom)
+}
+
+/// Serde adapter for [`Ability`]s, encoded as `namespace/name` strings.
+pub struct AsAbilityString;
+
+impl SerializeAs<Ability> for AsAbilityString {
+    fn serialize_as<S>(source: &Ability, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(source)
+    }
+}
+
+impl<'de> DeserializeAs<'de, Ability> for AsAbilityString {
+    fn deserialize_as<D>(deserializer: D) -> Result<Ability, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ability::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Serde adapter for maps keyed by target URI, encoded as objects with string keys as in the `att`
+/// field of a ReCap. The values are encoded with the adapter `T`.
+///
+/// ```
+/// # use iri_string::types::UriString;
+/// # use serde::{Deserialize, Serialize};
+/// # use std::collections::BTreeMap;
+/// # use ucan_capabilities_object::Ability;
+/// #[serde_with::serde_as]
+/// #[derive(Serialize, Deserialize)]
+/// struct Grants {
+///     #[serde_as(as = "siwe_recap::AsUriStringMap<Vec<siwe_recap::AsAbilityString>>")]
+///     grants: BTreeMap<UriString, Vec<Ability>>,
+/// }
+/// ```
+pub struct AsUriStringMap<T = Same>(PhantomData<T>);
+
+impl<V, T> SerializeAs<BTreeMap<UriString, V>> for AsUriStringMap<T>
+where
+    T: SerializeAs<V>,
+{
+    fn serialize_as<S>(source: &BTreeMap<UriString, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(source.len()))?;
+        for (target, value) in source {
+            map.serialize_entry(target.as_str(), &SerializeAsWrap::<V, T>::new(value))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, V, T> DeserializeAs<'de, BTreeMap<UriString, V>> for AsUriStringMap<T>
+where
+    T: DeserializeAs<'de, V>,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<BTreeMap<UriString, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BTreeMap::<String, DeserializeAsWrap<V, T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(target, value)| {
+                UriString::try_from(target)
+                    .map(|target| (target, value.into_inner()))
+                    .map_err(de::Error::custom)
+            })
+            .collect()
+    }
+}
+
+/// Serde adapter for [`SmallVec`]s, encoded as sequences of values encoded with the adapter `T`,
+/// which are deserialized without an intermediate heap allocation.
+pub(crate) struct AsSmallVec<T = Same>(PhantomData<T>);
+
+impl<A, T> SerializeAs<SmallVec<A>> for AsSmallVec<T>
+where
+    A: Array,
+    T: SerializeAs<A::Item>,
+{
+    fn serialize_as<S>(source: &SmallVec<A>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(source.iter().map(SerializeAsWrap::<A::Item, T>::new))
+    }
+}
+
+impl<'de, A, T> DeserializeAs<'de, SmallVec<A>> for AsSmallVec<T>
+where
+    A: Array,
+    T: DeserializeAs<'de, A::Item>,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<SmallVec<A>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor<A, T>(PhantomData<(A, T)>);
+
+        impl<'de, A, T> de::Visitor<'de> for Visitor<A, T>
+        where
+            A: Array,
+            T: DeserializeAs<'de, A::Item>,
+        {
+            type Value = SmallVec<A>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+            where
+                S: de::SeqAccess<'de>,
+            {
+                let mut values = SmallVec::new();
+                while let Some(value) = seq.next_element::<DeserializeAsWrap<A::Item, T>>()? {
+                    values.push(value.into_inner());
+                }
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor::<A, T>(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Serialize;
+    use serde_with::serde_as;
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Session {
+        #[serde_as(as = "AsUriStringMap<Vec<AsAbilityString>>")]
+        grants: BTreeMap<UriString, Vec<Ability>>,
+        #[serde_as(as = "Vec<AsB58Cid>")]
+        proofs: Vec<Cid>,
+    }
+
+    #[test]
+    fn adapters() {
+        let json = r#"{"grants":{"https://example.com/kv":["kv/get","kv/put"]},"proofs":["zb2rhdt1wgqfpzMgYf7mefxCWToqUTTyriWA1ctNxmy5WojSz"]}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            session.grants["https://example.com/kv"],
+            [
+                Ability::from_str("kv/get").unwrap(),
+                Ability::from_str("kv/put").unwrap()
+            ]
+        );
+        assert_eq!(serde_json::to_string(&session).unwrap(), json);
+
+        assert!(serde_json::from_str::<Session>(
+            r#"{"grants":{},"proofs":["bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"]}"#
+        )
+        .is_err());
+        assert!(
+            serde_json::from_str::<Session>(r#"{"grants":{"kv":["kv"]},"proofs":[]}"#).is_err()
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{AsyncSigner, SignatureVerificationError};
+
+use alloy_primitives::{eip191_hash_message, Address, TxKind, U256};
+use alloy_provider::transport::TransportError;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{TransactionInput, TransactionRequest};
+use alloy_signer::Signer;
+use siwe::Message;
+
+/// The selector of `isValidSignature(bytes32,bytes)`, which is also the magic value returned for a
+/// valid signature.
+const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Adapts an alloy [`Signer`], e.g. a `PrivateKeySigner` or a Ledger, to an [`AsyncSigner`], for
+/// use with [`Builder::build_and_sign_async`](crate::Builder::build_and_sign_async).
+#[derive(Clone, Debug)]
+pub struct AlloySigner<S>(pub S);
+
+impl<S> AsyncSigner for AlloySigner<S>
+where
+    S: Signer + Send + Sync,
+{
+    type Error = alloy_signer::Error;
+
+    async fn sign_async(&self, message: &str) -> Result<Vec<u8>, Self::Error> {
+        self.0
+            .sign_message(message.as_bytes())
+            .await
+            .map(|signature| signature.as_bytes().to_vec())
+    }
+}
+
+/// Verify the signature of a SIWE message, either an EIP-191 signature by the address, or, for a
+/// smart contract wallet, an EIP-1271 signature checked by calling the contract through the
+/// provider.
+pub async fn verify_signature_alloy<P>(
+    message: &Message,
+    signature: &[u8],
+    provider: &P,
+) -> Result<(), SignatureVerificationError<TransportError>>
+where
+    P: Provider,
+{
+    if let Ok(signature) = <&[u8; 65]>::try_from(signature) {
+        if message.verify_eip191(signature).is_ok() {
+            return Ok(());
+        }
+    }
+    let call = TransactionRequest {
+        to: Some(TxKind::Call(Address::from(message.address))),
+        input: TransactionInput::new(
+            is_valid_signature_call(&message.to_string(), signature).into(),
+        ),
+        ..Default::default()
+    };
+    let result = provider
+        .call(call)
+        .await
+        .map_err(SignatureVerificationError::Provider)?;
+    if result.get(..4) == Some(EIP1271_MAGIC_VALUE.as_slice()) {
+        Ok(())
+    } else {
+        Err(SignatureVerificationError::InvalidSignature)
+    }
+}
+
+/// ABI-encode the call of `isValidSignature(bytes32,bytes)` for the EIP-191 hash of the message.
+fn is_valid_signature_call(message: &str, signature: &[u8]) -> Vec<u8> {
+    let mut data = EIP1271_MAGIC_VALUE.to_vec();
+    data.extend_from_slice(eip191_hash_message(message).as_slice());
+    data.extend_from_slice(&U256::from(64).to_be_bytes::<32>());
+    data.extend_from_slice(&U256::from(signature.len()).to_be_bytes::<32>());
+    data.extend_from_slice(signature);
+    data.resize(data.len() + (32 - signature.len() % 32) % 32, 0);
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+    use alloy_primitives::Bytes;
+    use alloy_provider::ProviderBuilder;
+    use alloy_signer_local::PrivateKeySigner;
+    use alloy_transport::mock::Asserter;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn sign_and_verify() {
+        let signer = PrivateKeySigner::from_slice(&[1; 32]).unwrap();
+        let mut message = crate::test_utils::message();
+        message.address = signer.address().into_array();
+        let (message, signature) = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build_and_sign_async(message, &AlloySigner(signer))
+            .await
+            .unwrap();
+
+        // EOA signatures are checked without calling the provider
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        verify_signature_alloy(&message, &signature, &provider)
+            .await
+            .unwrap();
+
+        // anything else is checked by the contract
+        let mut magic = EIP1271_MAGIC_VALUE.to_vec();
+        magic.resize(32, 0);
+        asserter.push_success(&Bytes::from(magic));
+        verify_signature_alloy(&message, b"contract signature", &provider)
+            .await
+            .unwrap();
+
+        asserter.push_success(&Bytes::from(vec![0; 32]));
+        assert!(matches!(
+            verify_signature_alloy(&message, b"contract signature", &provider).await,
+            Err(SignatureVerificationError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn abi_encoding() {
+        let data = is_valid_signature_call("message", &[0xff; 33]);
+        assert_eq!(data.len(), 4 + 32 * 5);
+        assert_eq!(data[4 + 32 * 2 - 1], 64);
+        assert_eq!(data[4 + 32 * 3 - 1], 33);
+        assert_eq!(&data[4 + 32 * 4..4 + 32 * 4 + 1], &[0xff]);
+        assert!(data[4 + 32 * 4 + 1..].iter().all(|byte| *byte == 0));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Capability;
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use std::collections::BTreeMap;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+const SEGMENT_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_.+";
+const SCHEMES: &[&str] = &["https", "kepler", "credential", "did", "urn"];
+const MAX_ITEMS: usize = 4;
+
+fn segment(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(1..=8)?;
+    (0..len)
+        .map(|_| u.choose(SEGMENT_CHARS).map(|c| char::from(*c)))
+        .collect()
+}
+
+/// Generate a valid ability, for use with cargo-fuzz and other structured input generators.
+pub fn arbitrary_ability(u: &mut Unstructured<'_>) -> Result<Ability> {
+    let namespace = match u.ratio(1, 8)? {
+        true => "*".to_string(),
+        false => segment(u)?,
+    };
+    let name = match u.ratio(1, 8)? {
+        true => "*".to_string(),
+        false => segment(u)?,
+    };
+    format!("{namespace}/{name}")
+        .parse()
+        .map_err(|_| arbitrary::Error::IncorrectFormat)
+}
+
+/// Generate a valid target URI.
+pub fn arbitrary_target(u: &mut Unstructured<'_>) -> Result<UriString> {
+    let scheme = u.choose(SCHEMES)?;
+    let path = segment(u)?;
+    format!("{scheme}://{path}")
+        .parse()
+        .map_err(|_| arbitrary::Error::IncorrectFormat)
+}
+
+impl<'a, NB> Arbitrary<'a> for Capability<NB>
+where
+    NB: Arbitrary<'a>,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut capability = Capability::new();
+        for _ in 0..u.int_in_range(0..=MAX_ITEMS)? {
+            let target = arbitrary_target(u)?;
+            let mut abilities = Vec::new();
+            for _ in 0..u.int_in_range(1..=MAX_ITEMS)? {
+                let mut nbs = Vec::new();
+                for _ in 0..u.int_in_range(0..=MAX_ITEMS)? {
+                    let mut nb = BTreeMap::new();
+                    for _ in 0..u.int_in_range(0..=MAX_ITEMS)? {
+                        nb.insert(segment(u)?, NB::arbitrary(u)?);
+                    }
+                    nbs.push(nb);
+                }
+                abilities.push((arbitrary_ability(u)?, nbs));
+            }
+            capability.with_actions(target, abilities);
+        }
+        let proofs = (0..u.int_in_range(0..=MAX_ITEMS)?)
+            .map(|_| {
+                let bytes: [u8; 32] = u.arbitrary()?;
+                Ok(Cid::new_v1(0x55, Code::Sha2_256.digest(&bytes)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(capability.with_proofs(&proofs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arbitrary_capability() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&bytes);
+        let capability = Capability::<u32>::arbitrary(&mut u).unwrap();
+        let uri = UriString::try_from(&capability).unwrap();
+        let decoded = Capability::<u32>::try_from(&uri).unwrap();
+        assert_eq!(
+            serde_jcs::to_string(&capability).unwrap(),
+            serde_jcs::to_string(&decoded).unwrap()
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use crate::capability::check_payload;
+use crate::capability_ref::check_raw_grants;
+use crate::{
+    base64url, AbilityStr, AttOrdering, DecodingError, VerificationOptions, RESOURCE_PREFIX,
+};
+use std::fmt;
+
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use iri_string::types::UriString;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::value::RawValue;
+
+/// Capabilities decoded into an arena, for pipelines which decode, inspect and discard large
+/// batches of capabilities.
+///
+/// The decoded JSON, targets, abilities and proofs are all allocated from the arena, and
+/// note-benes are left as raw JSON, so that decoding makes no allocations of its own once the
+/// arena has grown to fit a batch. Use [`Bump::reset`] to reuse the arena for the next batch.
+///
+/// The decoded capabilities are validated as [`CapabilityRef`](crate::CapabilityRef)s are, but
+/// duplicate targets or abilities are kept rather than merged.
+#[derive(Debug)]
+pub struct ArenaCapability<'a> {
+    attenuations: BumpVec<'a, (&'a str, BumpVec<'a, (&'a str, &'a RawValue)>)>,
+    proof: BumpVec<'a, &'a str>,
+}
+
+impl<'a> ArenaCapability<'a> {
+    /// Decode a ReCap URI into the arena.
+    pub fn decode(arena: &'a Bump, uri: &str) -> Result<Self, DecodingError> {
+        Self::decode_with(arena, uri, &VerificationOptions::default())
+    }
+
+    /// Decode a ReCap URI into the arena, with the decoding profile, limits, key ordering and
+    /// degenerate grant allowance of the options.
+    pub fn decode_with(
+        arena: &'a Bump,
+        uri: &str,
+        options: &VerificationOptions,
+    ) -> Result<Self, DecodingError> {
+        let encoded = uri
+            .strip_prefix(RESOURCE_PREFIX)
+            .ok_or_else(|| DecodingError::InvalidResourcePrefix(uri.to_string()))?;
+        let buffer = arena.alloc_slice_fill_copy(encoded.len().div_ceil(4) * 3, 0u8);
+        let len = base64url::decode_slice(encoded, buffer)?;
+        let json: &'a [u8] = &buffer[..len];
+        check_payload(json, options.decoding_profile(), options.decoding_limits())?;
+        if options.requires_ordered_keys() {
+            AttOrdering::check(json)?;
+        }
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let capability = CapabilitySeed(arena).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        check_raw_grants(
+            capability
+                .attenuations
+                .iter()
+                .map(|(target, abilities)| (*target, abilities.iter().map(|(a, nb)| (a, *nb)))),
+            &capability.proof,
+            options,
+        )?;
+        Ok(capability)
+    }
+
+    /// Decode many ReCap URIs into the arena, returning the results in the same order.
+    pub fn decode_batch<'u>(
+        arena: &'a Bump,
+        uris: impl IntoIterator<Item = &'u UriString>,
+    ) -> Vec<Result<Self, DecodingError>> {
+        Self::decode_batch_with(arena, uris, &VerificationOptions::default())
+    }
+
+    /// Decode many ReCap URIs into the arena with the options, returning the results in the same
+    /// order.
+    pub fn decode_batch_with<'u>(
+        arena: &'a Bump,
+        uris: impl IntoIterator<Item = &'u UriString>,
+        options: &VerificationOptions,
+    ) -> Vec<Result<Self, DecodingError>> {
+        uris.into_iter()
+            .map(|uri| Self::decode_with(arena, uri.as_str(), options))
+            .collect()
+    }
+
+    /// The targets of the granted abilities.
+    pub fn targets(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.attenuations.iter().map(|(target, _)| *target)
+    }
+
+    /// The abilities granted for a target, along with their raw JSON note-benes.
+    pub fn abilities_for(&self, target: &str) -> Option<&[(&'a str, &'a RawValue)]> {
+        self.attenuations
+            .iter()
+            .find(|(t, _)| *t == target)
+            .map(|(_, abilities)| abilities.as_slice())
+    }
+
+    /// The raw JSON note-benes of an ability granted for a target, if granted.
+    pub fn can_do(&self, target: &str, ability: &str) -> Option<&'a RawValue> {
+        self.abilities_for(target)?
+            .iter()
+            .find(|(a, _)| *a == ability)
+            .map(|(_, nb)| *nb)
+    }
+
+    /// The CIDs of the proofs, as encoded.
+    pub fn proof(&self) -> &[&'a str] {
+        &self.proof
+    }
+}
+
+/// Deserializes a string borrowed from the input, or copied into the arena if it is escaped.
+struct StrSeed<'a>(&'a Bump);
+
+impl<'a> DeserializeSeed<'a> for StrSeed<'a> {
+    type Value = &'a str;
+
+    fn deserialize<D: de::Deserializer<'a>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'a> Visitor<'a> for StrSeed<'a> {
+    type Value = &'a str;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'a str) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(self.0.alloc_str(v))
+    }
+}
+
+struct CapabilitySeed<'a>(&'a Bump);
+
+impl<'a> DeserializeSeed<'a> for CapabilitySeed<'a> {
+    type Value = ArenaCapability<'a>;
+
+    fn deserialize<D: de::Deserializer<'a>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a> Visitor<'a> for CapabilitySeed<'a> {
+    type Value = ArenaCapability<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a ReCap object")
+    }
+
+    fn visit_map<A: MapAccess<'a>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut attenuations, mut proof) = (None, None);
+        while let Some(key) = map.next_key_seed(StrSeed(self.0))? {
+            match key {
+                "att" => attenuations = Some(map.next_value_seed(AttenuationsSeed(self.0))?),
+                "prf" => proof = Some(map.next_value_seed(ProofSeed(self.0))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(ArenaCapability {
+            attenuations: attenuations.ok_or_else(|| de::Error::missing_field("att"))?,
+            proof: proof.ok_or_else(|| de::Error::missing_field("prf"))?,
+        })
+    }
+}
+
+struct AttenuationsSeed<'a>(&'a Bump);
+
+impl<'a> DeserializeSeed<'a> for AttenuationsSeed<'a> {
+    type Value = BumpVec<'a, (&'a str, BumpVec<'a, (&'a str, &'a RawValue)>)>;
+
+    fn deserialize<D: de::Deserializer<'a>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a> Visitor<'a> for AttenuationsSeed<'a> {
+    type Value = BumpVec<'a, (&'a str, BumpVec<'a, (&'a str, &'a RawValue)>)>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of targets to abilities")
+    }
+
+    fn visit_map<A: MapAccess<'a>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut attenuations = BumpVec::new_in(self.0);
+        while let Some(target) = map.next_key_seed(StrSeed(self.0))? {
+            attenuations.push((target, map.next_value_seed(AbilitiesSeed(self.0))?));
+        }
+        Ok(attenuations)
+    }
+}
+
+struct AbilitiesSeed<'a>(&'a Bump);
+
+impl<'a> DeserializeSeed<'a> for AbilitiesSeed<'a> {
+    type Value = BumpVec<'a, (&'a str, &'a RawValue)>;
+
+    fn deserialize<D: de::Deserializer<'a>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a> Visitor<'a> for AbilitiesSeed<'a> {
+    type Value = BumpVec<'a, (&'a str, &'a RawValue)>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of abilities to note-benes")
+    }
+
+    fn visit_map<A: MapAccess<'a>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut abilities = BumpVec::new_in(self.0);
+        while let Some(ability) = map.next_key_seed(StrSeed(self.0))? {
+            AbilityStr::parse(ability).map_err(de::Error::custom)?;
+            abilities.push((ability, map.next_value()?));
+        }
+        Ok(abilities)
+    }
+}
+
+struct ProofSeed<'a>(&'a Bump);
+
+impl<'a> DeserializeSeed<'a> for ProofSeed<'a> {
+    type Value = BumpVec<'a, &'a str>;
+
+    fn deserialize<D: de::Deserializer<'a>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a> Visitor<'a> for ProofSeed<'a> {
+    type Value = BumpVec<'a, &'a str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of CIDs")
+    }
+
+    fn visit_seq<A: SeqAccess<'a>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut proof = BumpVec::new_in(self.0);
+        while let Some(cid) = seq.next_element_seed(StrSeed(self.0))? {
+            proof.push(cid);
+        }
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Capability;
+    use cid::Cid;
+    use serde_json::Value;
+
+    #[test]
+    fn arena_decode() {
+        let mut cap = Capability::<Value>::new().with_proofs(&[Cid::default()]);
+        cap.with_action_convert(
+            "https://example.com/kv",
+            "kv/get",
+            [[("key".to_string(), Value::from("a\"b"))].into()],
+        )
+        .unwrap()
+        .with_action_convert("https://example.com/msg", "msg/send", [])
+        .unwrap();
+        let uris = [
+            UriString::try_from(&cap).unwrap(),
+            "urn:recap:!".parse().unwrap(),
+        ];
+
+        let mut arena = Bump::new();
+        let decoded = ArenaCapability::decode_batch(&arena, &uris);
+        let arena_cap = decoded[0].as_ref().unwrap();
+        assert_eq!(
+            arena_cap.targets().collect::<Vec<_>>(),
+            ["https://example.com/kv", "https://example.com/msg"]
+        );
+        assert_eq!(
+            arena_cap
+                .can_do("https://example.com/kv", "kv/get")
+                .unwrap()
+                .get(),
+            r#"[{"key":"a\"b"}]"#
+        );
+        assert!(arena_cap
+            .can_do("https://example.com/kv", "kv/put")
+            .is_none());
+        assert_eq!(Cid::try_from(arena_cap.proof()[0]).unwrap(), Cid::default());
+        assert!(matches!(decoded[1], Err(DecodingError::Base64Decode(_))));
+        assert!(matches!(
+            ArenaCapability::decode(&arena, "https://example.com"),
+            Err(DecodingError::InvalidResourcePrefix(_))
+        ));
+        for invalid in [
+            r#"{"att":{"not a uri":{"kv/get":[]}},"prf":[]}"#,
+            r#"{"att":{"https://example.com":{"kv":[]}},"prf":[]}"#,
+            r#"{"att":{"https://example.com":{"kv/get":[1]}},"prf":[]}"#,
+            r#"{"att":{"https://example.com":{}},"prf":[]}"#,
+            r#"{"att":{"https://example.com":{"kv/get":[]}},"prf":["garbage"]}"#,
+        ] {
+            let uri = format!("{RESOURCE_PREFIX}{}", base64url::encode(invalid));
+            assert!(ArenaCapability::decode(&arena, &uri).is_err(), "{invalid}");
+        }
+        let degenerate = format!(
+            "{RESOURCE_PREFIX}{}",
+            base64url::encode(r#"{"att":{"https://example.com":{}},"prf":[]}"#)
+        );
+        assert!(ArenaCapability::decode_with(
+            &arena,
+            &degenerate,
+            &VerificationOptions::new().allow_degenerate_grants()
+        )
+        .is_ok());
+        drop(decoded);
+        arena.reset();
+    }
+}
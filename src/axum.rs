@@ -0,0 +1,114 @@
+use crate::session::header_str;
+use crate::{SessionError, SessionHeader, VerificationOptions, VerifiedSession, SESSION_SCHEME};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::{header, request::Parts, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+/// An axum extractor for a session verified from the `Authorization` header, using the
+/// [`VerificationOptions`] of the router state.
+///
+/// Requests are rejected with a 401 response if the session cannot be authenticated, or a 403
+/// response if its capabilities are rejected, with a JSON body holding the
+/// [code](SessionError::code) and description of the error.
+#[derive(Clone, Debug)]
+pub struct VerifiedRecap<NB>(pub VerifiedSession<NB>);
+
+impl<S, NB> FromRequestParts<S> for VerifiedRecap<NB>
+where
+    S: Send + Sync,
+    Arc<VerificationOptions>: FromRef<S>,
+    NB: for<'a> Deserialize<'a> + Clone,
+{
+    type Rejection = SessionError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let options = Arc::<VerificationOptions>::from_ref(state);
+        let header = header_str(
+            parts
+                .headers
+                .get(header::AUTHORIZATION)
+                .map(HeaderValue::as_bytes),
+        )?;
+        SessionHeader::decode_and_verify(header, &options).map(Self)
+    }
+}
+
+impl<NB> Deref for VerifiedRecap<NB> {
+    type Target = VerifiedSession<NB>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoResponse for SessionError {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            self.to_json().to_string(),
+        )
+            .into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static(SESSION_SCHEME),
+            );
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, sign, verification_options};
+    use crate::Builder;
+    use axum::http::Request;
+    use serde_json::Value;
+
+    async fn extract(authorization: Option<&str>) -> Result<VerifiedRecap<Value>, SessionError> {
+        let mut request = Request::builder();
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        let options = Arc::new(verification_options());
+        VerifiedRecap::from_request_parts(&mut parts, &options).await
+    }
+
+    #[tokio::test]
+    async fn extractor() {
+        let message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build(message())
+            .unwrap();
+        let (message, signature) = sign(message);
+        let header = SessionHeader::encode(&message, &signature);
+
+        let session = extract(Some(&header)).await.unwrap();
+        assert_eq!(session.address(), &message.address);
+
+        let mut tampered = message.clone();
+        tampered.statement = Some("I authorize nothing".into());
+        let (tampered, signature) = sign(tampered);
+        let rejection = extract(Some(&SessionHeader::encode(&tampered, &signature)))
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.code(), "invalid_capability");
+        assert_eq!(rejection.into_response().status(), StatusCode::FORBIDDEN);
+
+        let rejection = extract(None).await.unwrap_err();
+        assert_eq!(rejection.code(), "missing_session");
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], SESSION_SCHEME);
+    }
+}
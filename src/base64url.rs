@@ -0,0 +1,51 @@
+//! Unpadded URL-safe base64, as used by ReCap URIs and session headers, encoded with the SIMD
+//! accelerated `base64-simd` crate if the `simd` feature is enabled, and the `base64` crate
+//! otherwise.
+
+pub(crate) fn encode(bytes: impl AsRef<[u8]>) -> String {
+    #[cfg(feature = "simd")]
+    return base64_simd::URL_SAFE_NO_PAD.encode_to_string(bytes);
+    #[cfg(not(feature = "simd"))]
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub(crate) fn decode(encoded: impl AsRef<[u8]>) -> Result<Vec<u8>, base64::DecodeError> {
+    #[cfg(feature = "simd")]
+    if let Ok(bytes) = base64_simd::URL_SAFE_NO_PAD.decode_to_vec(encoded.as_ref()) {
+        return Ok(bytes);
+    }
+    // the `base64` crate also decides what is accepted on the slow path, so that both backends
+    // accept the same input and report the same errors
+    base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decode into a buffer of at least `encoded.len().div_ceil(4) * 3` bytes, returning the decoded
+/// length.
+pub(crate) fn decode_slice(encoded: &str, out: &mut [u8]) -> Result<usize, base64::DecodeError> {
+    #[cfg(feature = "simd")]
+    if let Ok(bytes) = base64_simd::URL_SAFE_NO_PAD
+        .decode(encoded.as_bytes(), base64_simd::Out::from_slice(&mut *out))
+    {
+        return Ok(bytes.len());
+    }
+    base64::decode_config_slice(encoded, base64::URL_SAFE_NO_PAD, out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn matches_base64(bytes in prop::collection::vec(any::<u8>(), 0..256), text in "[A-Za-z0-9_=+/-]{0,64}") {
+            let encoded = encode(&bytes);
+            prop_assert_eq!(&encoded, &base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD));
+            prop_assert_eq!(decode(&encoded).unwrap(), bytes);
+            prop_assert_eq!(
+                decode(&text).ok(),
+                base64::decode_config(&text, base64::URL_SAFE_NO_PAD).ok()
+            );
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    Capability, DecodingError, SessionError, SessionHeader, VerificationError, VerificationOptions,
+    VerifiedSession,
+};
+
+use iri_string::types::UriString;
+use serde::Deserialize;
+use siwe::Message;
+
+/// Apply `f` to each item, in parallel with the `parallel` feature, keeping the order of the items.
+fn map_ordered<T, R, F>(items: impl IntoIterator<Item = T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        items
+            .into_iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(f)
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    items.into_iter().map(f).collect()
+}
+
+impl SessionHeader {
+    /// Verify many sessions, e.g. from an archive of signed messages, returning the results in the
+    /// same order. With the `parallel` feature, sessions are verified on the rayon thread pool.
+    pub fn verify_batch<NB>(
+        headers: impl IntoIterator<Item = SessionHeader>,
+        options: &VerificationOptions,
+    ) -> Vec<Result<VerifiedSession<NB>, SessionError>>
+    where
+        NB: for<'a> Deserialize<'a> + Clone + Send,
+    {
+        map_ordered(headers, |header| header.verify(options))
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: for<'a> Deserialize<'a> + Send,
+{
+    /// Decode many ReCap URIs, returning the results in the same order. With the `parallel`
+    /// feature, URIs are decoded on the rayon thread pool.
+    pub fn decode_batch<'u>(
+        uris: impl IntoIterator<Item = &'u UriString>,
+    ) -> Vec<Result<Self, DecodingError>> {
+        map_ordered(uris, Self::try_from)
+    }
+
+    /// Extract and verify the capabilities of many messages, returning the results in the same
+    /// order. With the `parallel` feature, messages are processed on the rayon thread pool.
+    pub fn extract_and_verify_batch<'m>(
+        messages: impl IntoIterator<Item = &'m Message>,
+    ) -> Vec<Result<Option<Self>, VerificationError>> {
+        map_ordered(messages, |message| Self::extract_and_verify(message))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, sign, verification_options};
+    use crate::Builder;
+    use serde_json::Value;
+
+    #[test]
+    fn batches() {
+        let headers: Vec<_> = (0..16)
+            .map(|n| {
+                let (message, mut signature) = sign(
+                    Builder::<Value>::new()
+                        .with_action_convert(format!("https://example.com/{n}"), "kv/get", [])
+                        .unwrap()
+                        .build(message())
+                        .unwrap(),
+                );
+                if n % 5 == 0 {
+                    signature[0] ^= 1;
+                }
+                SessionHeader::new(message, signature)
+            })
+            .collect();
+        let messages: Vec<_> = headers.iter().map(|h| h.message().clone()).collect();
+        let verified = SessionHeader::verify_batch::<Value>(headers, &verification_options());
+        for (n, result) in verified.iter().enumerate() {
+            match result {
+                Ok(session) => assert!(session
+                    .capability()
+                    .abilities_for_str(&format!("https://example.com/{n}"))
+                    .is_some()),
+                Err(_) => assert_eq!(n % 5, 0),
+            }
+        }
+
+        let extracted = Capability::<Value>::extract_and_verify_batch(&messages);
+        let uris: Vec<_> = messages
+            .iter()
+            .map(|m| m.resources.last().unwrap().clone())
+            .collect();
+        let decoded = Capability::<Value>::decode_batch(&uris);
+        for (extracted, decoded) in extracted.into_iter().zip(decoded) {
+            assert_eq!(
+                serde_jcs::to_string(&extracted.unwrap().unwrap()).unwrap(),
+                serde_jcs::to_string(&decoded.unwrap()).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use iri_string::types::UriString;
+use serde_json::Value;
+use siwe::Message;
+use siwe_recap::Capability;
+
+/// Inspect and produce EIP-5573 ReCap resources.
+#[derive(Parser)]
+#[command(name = "recap", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Pretty-print the grants and proofs of a `urn:recap:` URI.
+    Decode {
+        urn: String,
+        /// Print the canonical JSON instead of a tree.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Encode a capability JSON object, or `-` to read it from stdin, as a `urn:recap:` URI.
+    Encode { json: String },
+    /// Print the ReCap statement of a `urn:recap:` URI.
+    Statement { urn: String },
+    /// Verify the ReCap statement of a SIWE message file, and optionally its EIP-191 signature.
+    Verify {
+        message: PathBuf,
+        /// The hex-encoded signature over the message.
+        #[arg(long)]
+        signature: Option<String>,
+    },
+    /// Show the grants and proofs which differ between two `urn:recap:` URIs.
+    Diff { old: String, new: String },
+}
+
+fn decode(urn: &str) -> Result<Capability<Value>, Box<dyn Error>> {
+    let uri = UriString::try_from(urn)?;
+    Ok(Capability::try_from(&uri)?)
+}
+
+fn grants(capability: &Capability<Value>) -> Vec<(String, String, String)> {
+    capability
+        .abilities()
+        .iter()
+        .flat_map(|(target, abilities)| {
+            abilities.iter().map(move |(ability, nbs)| {
+                (
+                    target.to_string(),
+                    ability.to_string(),
+                    serde_jcs::to_string(&**nbs).unwrap_or_default(),
+                )
+            })
+        })
+        .collect()
+}
+
+fn diff(old: &Capability<Value>, new: &Capability<Value>) -> Vec<String> {
+    let (old_grants, new_grants) = (grants(old), grants(new));
+    let find = |grants: &[(String, String, String)], target: &str, ability: &str| {
+        grants
+            .iter()
+            .find(|(t, a, _)| t == target && a == ability)
+            .map(|(_, _, nbs)| nbs.clone())
+    };
+    let mut lines = Vec::new();
+    for (target, ability, nbs) in &old_grants {
+        match find(&new_grants, target, ability) {
+            None => lines.push(format!("- {target} {ability}")),
+            Some(new_nbs) if new_nbs != *nbs => {
+                lines.push(format!("~ {target} {ability} {nbs} -> {new_nbs}"))
+            }
+            Some(_) => {}
+        }
+    }
+    for (target, ability, _) in &new_grants {
+        if find(&old_grants, target, ability).is_none() {
+            lines.push(format!("+ {target} {ability}"));
+        }
+    }
+    for proof in old.proof().iter().filter(|p| !new.proof().contains(p)) {
+        lines.push(format!("- proof {proof}"));
+    }
+    for proof in new.proof().iter().filter(|p| !old.proof().contains(p)) {
+        lines.push(format!("+ proof {proof}"));
+    }
+    lines
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Decode { urn, json } => {
+            let capability = decode(&urn)?;
+            if json {
+                println!("{}", serde_jcs::to_string(&capability)?);
+            } else {
+                let colored = std::io::stdout().is_terminal();
+                print!("{}", capability.tree().colored(colored));
+            }
+        }
+        Command::Encode { json } => {
+            let json = match json.as_str() {
+                "-" => {
+                    let mut input = String::new();
+                    std::io::stdin().read_to_string(&mut input)?;
+                    input
+                }
+                _ => json,
+            };
+            let capability: Capability<Value> = serde_json::from_str(&json)?;
+            println!("{}", UriString::try_from(&capability)?);
+        }
+        Command::Statement { urn } => println!("{}", decode(&urn)?.to_statement()),
+        Command::Verify { message, signature } => {
+            let message: Message = std::fs::read_to_string(message)?.trim().parse()?;
+            match Capability::<Value>::extract_and_verify(&message)? {
+                Some(capability) => print!("{:#}", capability),
+                None => println!("no capabilities"),
+            }
+            if let Some(signature) = signature {
+                let signature = hex::decode(signature.trim_start_matches("0x"))?;
+                let signature: &[u8; 65] = signature
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| siwe::VerificationError::SignatureLength)?;
+                message.verify_eip191(signature)?;
+                println!("valid signature by 0x{}", hex::encode(message.address));
+            }
+            if !message.valid_now() {
+                println!("message is not currently valid");
+            }
+        }
+        Command::Diff { old, new } => {
+            for line in diff(&decode(&old)?, &decode(&new)?) {
+                println!("{line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_grants() {
+        let old: Capability<Value> = serde_json::from_str(
+            r#"{"att":{"https://example.com/kv":{"kv/get":[{}],"kv/put":[{"max_size":10}]}},"prf":[]}"#,
+        )
+        .unwrap();
+        let new: Capability<Value> = serde_json::from_str(
+            r#"{"att":{"https://example.com/kv":{"kv/list":[{}],"kv/put":[{"max_size":20}]}},"prf":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                "- https://example.com/kv kv/get",
+                r#"~ https://example.com/kv kv/put [{"max_size":10}] -> [{"max_size":20}]"#,
+                "+ https://example.com/kv kv/list",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1054 @@
+use crate::{
+    AbilityError, AbilityPolicy, AbilitySet, Capability, CaseNormalization, ComplianceProfile,
+    EncodingError, NamespaceRegistry, ProofPolicy, ProofPolicyError, RegistryError,
+    VerificationError,
+};
+use cid::Cid;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use iri_string::types::UriString;
+use siwe::{Message, TimeStamp};
+use time::{Duration, OffsetDateTime};
+
+use ucan_capabilities_object::{Ability, ConvertError};
+
+type ValidateFn = dyn Fn(&UriString) -> Result<(), String> + Send + Sync;
+
+/// A check run against the target of every grant in a given ability namespace, returning a reason on failure.
+#[derive(Clone)]
+pub struct TargetValidator(Arc<ValidateFn>);
+
+impl TargetValidator {
+    /// Create a validator from a function.
+    pub fn new(f: impl Fn(&UriString) -> Result<(), String> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Create a validator requiring targets to use the given URI scheme.
+    pub fn scheme(scheme: impl Into<String>) -> Self {
+        let scheme = scheme.into();
+        Self::new(move |target| match target.as_str().split_once(':') {
+            Some((s, _)) if s == scheme => Ok(()),
+            _ => Err(format!("expected a '{scheme}:' URI")),
+        })
+    }
+
+    /// Run the validator against a target.
+    pub fn validate(&self, target: &UriString) -> Result<(), String> {
+        (self.0)(target)
+    }
+}
+
+impl fmt::Debug for TargetValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TargetValidator")
+    }
+}
+
+/// How the builder handles a grant for a target and ability which is already granted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateGrantPolicy {
+    /// Append the new nota benes to the existing ones, so either set of constraints is sufficient.
+    #[default]
+    Merge,
+    /// Replace the existing nota benes with the new ones.
+    LastWins,
+    /// Fail when building.
+    Error,
+}
+
+/// Fluent builder for applying a set of delegated capabilities to a SIWE message.
+#[derive(Clone, Debug)]
+pub struct Builder<NB> {
+    capability: Capability<NB>,
+    parent: Option<Attenuation<NB>>,
+    validators: BTreeMap<String, Vec<TargetValidator>>,
+    registry: Option<NamespaceRegistry>,
+    ability_policy: AbilityPolicy,
+    allowed_reserved: BTreeSet<String>,
+    duplicate_policy: DuplicateGrantPolicy,
+    case_normalization: CaseNormalization,
+    proof_policy: ProofPolicy,
+    allowed_schemes: Option<BTreeSet<String>>,
+    allowed_abilities: Option<AbilitySet>,
+    compliance_profile: ComplianceProfile,
+    duplicates: Vec<(UriString, Ability)>,
+    statement: Option<String>,
+    max_statement_length: Option<usize>,
+    issued_now: bool,
+    expires_in: Option<Duration>,
+}
+
+impl<NB> Builder<NB> {
+    /// Create a new builder with no capabilities.
+    pub fn new() -> Self {
+        Self {
+            capability: Capability::new(),
+            parent: None,
+            validators: BTreeMap::new(),
+            registry: None,
+            ability_policy: AbilityPolicy::default(),
+            allowed_reserved: BTreeSet::new(),
+            duplicate_policy: DuplicateGrantPolicy::default(),
+            case_normalization: CaseNormalization::default(),
+            proof_policy: ProofPolicy::default(),
+            allowed_schemes: None,
+            allowed_abilities: None,
+            compliance_profile: ComplianceProfile::default(),
+            duplicates: Vec::new(),
+            statement: None,
+            max_statement_length: None,
+            issued_now: false,
+            expires_in: None,
+        }
+    }
+
+    /// Create a builder re-delegating (a subset of) the capabilities of a parent delegation.
+    ///
+    /// All of the parent's grants are pre-loaded and can be narrowed with [`Builder::retain`]; the
+    /// parent CID is added as a proof, and [`Builder::build`] fails if the result is not a subset of the parent.
+    pub fn attenuating(parent: &Capability<NB>, parent_cid: Cid) -> Self
+    where
+        NB: Clone + PartialEq,
+    {
+        let mut capability = Capability::new().with_proof(&parent_cid);
+        for (target, abilities) in parent.abilities() {
+            capability.with_actions(
+                target.clone(),
+                abilities
+                    .iter()
+                    .map(|(ability, nbs)| (ability.clone(), nbs.iter().cloned())),
+            );
+        }
+        Self {
+            parent: Some(Attenuation {
+                parent: parent.clone(),
+                is_subset: Capability::is_subset_of,
+            }),
+            ..capability.into()
+        }
+    }
+
+    /// Keep only the grants for which the predicate returns true.
+    pub fn retain(mut self, f: impl FnMut(&UriString, &Ability) -> bool) -> Self
+    where
+        NB: Clone,
+    {
+        self.capability = self.capability.filtered(f);
+        self
+    }
+
+    /// Read the parent delegation being attenuated, if any
+    pub fn parent(&self) -> Option<&Capability<NB>> {
+        self.parent.as_ref().map(|attenuation| &attenuation.parent)
+    }
+
+    /// Add a supporting proof CID
+    pub fn with_proof(mut self, proof: &Cid) -> Self {
+        self.capability = self.capability.with_proof(proof);
+        self
+    }
+
+    /// Add a set of supporting proofs
+    pub fn with_proofs<'l>(mut self, proofs: impl IntoIterator<Item = &'l Cid>) -> Self {
+        self.capability = self.capability.with_proofs(proofs);
+        self
+    }
+
+    /// Register a validator for the targets of all grants in the given ability namespace.
+    ///
+    /// Grants added afterwards are rejected as soon as they are added; grants added before are
+    /// checked by [`Builder::validate`] and before building.
+    pub fn with_target_validator(
+        mut self,
+        namespace: impl Into<String>,
+        validator: TargetValidator,
+    ) -> Self {
+        self.validators
+            .entry(namespace.into())
+            .or_default()
+            .push(validator);
+        self
+    }
+
+    /// Reject grants which are not declared in the given registry when building.
+    pub fn with_namespace_registry(mut self, registry: NamespaceRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set the policy which the abilities of all grants must satisfy when building.
+    ///
+    /// By default only ASCII characters are allowed, and lengths are limited to those of [`AbilityPolicy::new`].
+    pub fn with_ability_policy(mut self, policy: AbilityPolicy) -> Self {
+        self.ability_policy = policy;
+        self
+    }
+
+    /// Allow grants in a reserved namespace such as `ucan`, which are otherwise rejected when building.
+    pub fn allow_reserved_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.allowed_reserved.insert(namespace.into());
+        self
+    }
+
+    /// Reject proofs which are not accepted by the given policy when building.
+    pub fn with_proof_policy(mut self, policy: ProofPolicy) -> Self {
+        self.proof_policy = policy;
+        self
+    }
+
+    /// Reject grants for targets whose URI scheme is not one of the given schemes when building,
+    /// e.g. `kepler` and `https`.
+    pub fn with_allowed_schemes<S>(mut self, schemes: S) -> Self
+    where
+        S: IntoIterator,
+        S::Item: AsRef<str>,
+    {
+        self.allowed_schemes = Some(
+            schemes
+                .into_iter()
+                .map(|scheme| scheme.as_ref().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Reject grants whose ability is not contained in the given set when building, where wildcards
+    /// such as `kv/*` allow every ability they cover.
+    pub fn with_allowed_abilities(mut self, abilities: AbilitySet) -> Self {
+        self.allowed_abilities = Some(abilities);
+        self
+    }
+
+    /// Produce the statement and ReCap URI in the format of the given compliance profile, e.g. for
+    /// verifiers which have not moved to final EIP-5573.
+    pub fn with_compliance_profile(mut self, profile: ComplianceProfile) -> Self {
+        self.compliance_profile = profile;
+        self
+    }
+
+    /// Set the maximum length in bytes of the message statement, including the ReCap statement.
+    pub fn with_max_statement_length(mut self, max: usize) -> Self {
+        self.max_statement_length = Some(max);
+        self
+    }
+
+    /// Check every grant against the registered target validators, ability policy, registry and
+    /// duplicate grant policy, reporting all problems found.
+    pub fn validate(&self) -> Result<(), BuildReport> {
+        BuildReport::from_errors(self.problems())
+    }
+
+    /// Run the registered target validators against every grant of the capabilities.
+    fn invalid_targets<'a>(
+        &'a self,
+        capability: &'a Capability<NB>,
+    ) -> impl Iterator<Item = BuildError> + 'a {
+        capability
+            .abilities()
+            .iter()
+            .flat_map(|(target, abilities)| abilities.keys().map(move |ability| (target, ability)))
+            .flat_map(move |(target, ability)| {
+                self.validators
+                    .get(&ability.namespace().to_string())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(move |validator| {
+                        validator
+                            .validate(target)
+                            .err()
+                            .map(|reason| BuildError::InvalidTarget {
+                                target: target.clone(),
+                                ability: ability.clone(),
+                                reason,
+                            })
+                    })
+            })
+    }
+
+    pub(crate) fn problems(&self) -> Vec<BuildError> {
+        let mut problems: Vec<_> = self.invalid_targets(&self.capability).collect();
+        problems.extend(
+            self.capability
+                .policy_violations(&self.ability_policy)
+                .into_iter()
+                .map(|(target, source)| BuildError::InvalidAbility { target, source }),
+        );
+        if let Some(registry) = &self.registry {
+            problems.extend(
+                registry
+                    .violations(&self.capability)
+                    .into_iter()
+                    .map(|(target, source)| BuildError::Registry { target, source }),
+            );
+        }
+        problems.extend(self.capability.reserved_grants(&self.allowed_reserved).map(
+            |(target, ability)| BuildError::ReservedNamespace {
+                target: target.clone(),
+                ability: ability.clone(),
+            },
+        ));
+        if let Some(schemes) = &self.allowed_schemes {
+            problems.extend(
+                self.capability
+                    .disallowed_targets(schemes)
+                    .map(|target| BuildError::DisallowedScheme(target.clone())),
+            );
+        }
+        if let Some(allowed) = &self.allowed_abilities {
+            problems.extend(
+                self.capability
+                    .disallowed_grants(allowed)
+                    .map(|(target, ability)| BuildError::DisallowedAbility {
+                        target: target.clone(),
+                        ability: ability.clone(),
+                    }),
+            );
+        }
+        problems.extend(
+            self.capability
+                .proof()
+                .iter()
+                .filter_map(|cid| self.proof_policy.check(cid).err())
+                .map(BuildError::Proof),
+        );
+        problems.extend(self.duplicates.iter().map(|(target, ability)| {
+            BuildError::DuplicateGrant {
+                target: target.clone(),
+                ability: ability.clone(),
+            }
+        }));
+        problems
+    }
+
+    /// Set the custom statement which precedes the ReCap statement, replacing any statement already in the message.
+    pub fn with_statement(mut self, statement: impl Into<String>) -> Self {
+        self.statement = Some(statement.into());
+        self
+    }
+
+    /// Set the message's issued-at time to the current time when building.
+    pub fn with_issued_now(mut self) -> Self {
+        self.issued_now = true;
+        self
+    }
+
+    /// Set the message's expiration time to the given offset from its issued-at time when building,
+    /// unless the message already has one.
+    pub fn with_expiration_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+
+    /// Read the custom statement, if set
+    pub fn statement(&self) -> Option<&str> {
+        self.statement.as_deref()
+    }
+
+    /// Read the compliance profile messages are built for
+    pub fn compliance_profile(&self) -> ComplianceProfile {
+        self.compliance_profile
+    }
+
+    /// Read the capabilities built so far
+    pub fn capability(&self) -> &Capability<NB> {
+        &self.capability
+    }
+
+    /// Consume the builder, returning the built capabilities
+    pub fn into_capability(self) -> Capability<NB> {
+        self.capability
+    }
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Clone,
+{
+    /// Set how grants for an already granted target and ability are handled.
+    pub fn with_duplicate_policy(mut self, policy: DuplicateGrantPolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Set how the case of the abilities of subsequently added grants is normalized.
+    pub fn with_case_normalization(mut self, normalization: CaseNormalization) -> Self {
+        self.case_normalization = normalization;
+        self
+    }
+
+    /// Add an allowed action for the given target, with a set of note-benes.
+    ///
+    /// Fails if the target is rejected by a registered [`TargetValidator`].
+    pub fn with_action(
+        mut self,
+        target: UriString,
+        action: Ability,
+        nb: impl IntoIterator<Item = BTreeMap<String, NB>>,
+    ) -> Result<Self, BuildError> {
+        let mut grants = Capability::new();
+        grants.with_action(target, action, nb);
+        self.add_grants(grants)?;
+        Ok(self)
+    }
+
+    /// Add an allowed action for the given target, with a set of note-benes.
+    ///
+    /// This method automatically converts the provided args into the correct types for convenience.
+    pub fn with_action_convert<T, A>(
+        mut self,
+        target: T,
+        action: A,
+        nb: impl IntoIterator<Item = BTreeMap<String, NB>>,
+    ) -> Result<Self, GrantError<T::Error, A::Error>>
+    where
+        T: TryInto<UriString>,
+        A: TryInto<Ability>,
+    {
+        let mut grants = Capability::new();
+        grants.with_action_convert(target, action, nb)?;
+        self.add_grants(grants)?;
+        Ok(self)
+    }
+
+    /// Add a set of allowed action for the given target, with associated note-benes.
+    ///
+    /// Fails if the target is rejected by a registered [`TargetValidator`].
+    pub fn with_actions(
+        mut self,
+        target: UriString,
+        abilities: impl IntoIterator<Item = (Ability, impl IntoIterator<Item = BTreeMap<String, NB>>)>,
+    ) -> Result<Self, BuildError> {
+        let mut grants = Capability::new();
+        grants.with_actions(target, abilities);
+        self.add_grants(grants)?;
+        Ok(self)
+    }
+
+    /// Add the abilities of a set for the given target, without note-benes.
+    ///
+    /// Fails if the target is rejected by a registered [`TargetValidator`].
+    pub fn with_abilities(
+        self,
+        target: UriString,
+        abilities: AbilitySet,
+    ) -> Result<Self, BuildError> {
+        self.with_actions(
+            target,
+            abilities
+                .into_iter()
+                .map(|ability| (ability, std::iter::empty())),
+        )
+    }
+
+    /// Add a set of allowed action for the given target, with associated note-benes.
+    ///
+    /// This method automatically converts the provided args into the correct types for convenience.
+    pub fn with_actions_convert<T, A, N>(
+        mut self,
+        target: T,
+        abilities: impl IntoIterator<Item = (A, N)>,
+    ) -> Result<Self, GrantError<T::Error, A::Error>>
+    where
+        T: TryInto<UriString>,
+        A: TryInto<Ability>,
+        N: IntoIterator<Item = BTreeMap<String, NB>>,
+    {
+        let mut grants = Capability::new();
+        grants.with_actions_convert(target, abilities)?;
+        self.add_grants(grants)?;
+        Ok(self)
+    }
+
+    fn add_grants(&mut self, grants: Capability<NB>) -> Result<(), BuildError> {
+        let grants = grants.normalized(self.case_normalization);
+        if let Some(invalid) = self.invalid_targets(&grants).next() {
+            return Err(invalid);
+        }
+        let mut replaced = Vec::new();
+        for (target, abilities) in grants.abilities() {
+            for ability in abilities.keys() {
+                if self.capability.can_do(target, ability).is_none() {
+                    continue;
+                }
+                match self.duplicate_policy {
+                    DuplicateGrantPolicy::Merge => {}
+                    DuplicateGrantPolicy::LastWins => replaced.push((target, ability)),
+                    DuplicateGrantPolicy::Error => {
+                        self.duplicates.push((target.clone(), ability.clone()))
+                    }
+                }
+            }
+        }
+        if !replaced.is_empty() {
+            self.capability = self.capability.filtered(|t, a| !replaced.contains(&(t, a)));
+        }
+        self.capability.merge_in(grants);
+        Ok(())
+    }
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Serialize,
+{
+    /// Apply the built capabilities and statement options to a SIWE message.
+    ///
+    /// A random nonce is generated if the message's nonce is empty.
+    ///
+    /// All problems with the builder are reported together.
+    pub fn build(self, mut message: Message) -> Result<Message, BuildReport> {
+        let mut problems = self.problems();
+        if let Some(Attenuation { parent, is_subset }) = &self.parent {
+            if !is_subset(&self.capability, parent) {
+                problems.push(BuildError::NotAttenuated);
+            }
+        }
+        if let Some(statement) = self.statement {
+            message.statement = Some(statement);
+        }
+        if let Some(max) = self.max_statement_length {
+            let custom = message.statement.as_ref().map_or(0, String::len);
+            let length = match self.capability.abilities().is_empty() {
+                true => custom,
+                false => (custom + usize::from(custom > 0))
+                    .saturating_add(self.capability.statement_len(self.compliance_profile)),
+            };
+            if length > max {
+                problems.push(BuildError::StatementTooLong { length, max });
+            }
+        }
+        BuildReport::from_errors(problems)?;
+        if message.nonce.is_empty() {
+            message.nonce = siwe::generate_nonce();
+        }
+        if self.issued_now {
+            message.issued_at = now().into();
+        }
+        if let (None, Some(expires_in)) = (&message.expiration_time, self.expires_in) {
+            let issued_at: &OffsetDateTime = message.issued_at.as_ref();
+            message.expiration_time = Some(TimeStamp::from(*issued_at + expires_in));
+        }
+        Ok(self
+            .capability
+            .build_message_with(message, self.compliance_profile)
+            .map_err(BuildError::from)?)
+    }
+}
+
+impl<NB> Builder<NB>
+where
+    NB: for<'a> Deserialize<'a>,
+{
+    /// Load the capabilities of an already-built SIWE message into a new builder.
+    ///
+    /// The ReCap resource and statement are verified and stripped from the message, which is returned
+    /// alongside the builder so that more capabilities can be added before rebuilding it.
+    pub fn from_message(mut message: Message) -> Result<(Self, Message), VerificationError> {
+        let capability = match Capability::extract_and_verify(&message)? {
+            Some(capability) => capability,
+            None => return Ok((Self::new(), message)),
+        };
+        message.resources.pop();
+        let expected = capability.to_statement();
+        message.statement = message
+            .statement
+            .as_deref()
+            .and_then(|s| s.strip_suffix(&expected))
+            .map(str::trim_end)
+            .filter(|s| !s.is_empty())
+            .map(String::from);
+        Ok((capability.into(), message))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BuildError {
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
+    #[error("capabilities are not a subset of the parent delegation")]
+    NotAttenuated,
+    #[error("unknown capability preset: {0}")]
+    UnknownPreset(String),
+    #[error("failed to apply capability preset {name}: {source}")]
+    Preset {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("missing required message field: {0}")]
+    MissingField(&'static str),
+    #[error("invalid nota bene for {ability}: {reason}")]
+    InvalidNotaBene { ability: String, reason: String },
+    #[error("duplicate grant of {ability} for {target}")]
+    DuplicateGrant { target: UriString, ability: Ability },
+    #[error("invalid target {target} for {ability}: {reason}")]
+    InvalidTarget {
+        target: UriString,
+        ability: Ability,
+        reason: String,
+    },
+    #[error("grant on {target} rejected: {source}")]
+    Registry {
+        target: UriString,
+        source: RegistryError,
+    },
+    #[error("{ability} on {target} uses a reserved namespace")]
+    ReservedNamespace { target: UriString, ability: Ability },
+    #[error("invalid ability on {target}: {source}")]
+    InvalidAbility {
+        target: UriString,
+        source: AbilityError,
+    },
+    #[error("statement is {length} bytes long, exceeding the maximum of {max}")]
+    StatementTooLong { length: usize, max: usize },
+    #[error(transparent)]
+    Proof(ProofPolicyError),
+    #[error("the scheme of target {0} is not allowed")]
+    DisallowedScheme(UriString),
+    #[error("{ability} on {target} is not an allowed ability")]
+    DisallowedAbility { target: UriString, ability: Ability },
+}
+
+/// An error adding a grant to a [`Builder`].
+#[derive(thiserror::Error, Debug)]
+pub enum GrantError<T, A> {
+    #[error(transparent)]
+    Convert(#[from] ConvertError<T, A>),
+    #[error(transparent)]
+    Rejected(#[from] BuildError),
+}
+
+/// All of the problems found while building a message.
+#[derive(Debug)]
+pub struct BuildReport {
+    errors: Vec<BuildError>,
+}
+
+impl BuildReport {
+    pub(crate) fn from_errors(errors: Vec<BuildError>) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self { errors })
+        }
+    }
+
+    /// Read the problems found, in the order they were detected
+    pub fn errors(&self) -> &[BuildError] {
+        &self.errors
+    }
+
+    /// Consume the report, returning the problems found
+    pub fn into_errors(self) -> Vec<BuildError> {
+        self.errors
+    }
+}
+
+impl From<BuildError> for BuildReport {
+    fn from(error: BuildError) -> Self {
+        Self {
+            errors: vec![error],
+        }
+    }
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to build message: ")?;
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildReport {}
+
+/// The current time, truncated to millisecond precision as is conventional in SIWE messages.
+fn now() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    now - Duration::nanoseconds((now.nanosecond() % 1_000_000).into())
+}
+
+/// The parent delegation of an attenuating builder, with the subset check captured where `NB: PartialEq`
+/// is known so that building does not require it.
+#[derive(Clone, Debug)]
+struct Attenuation<NB> {
+    parent: Capability<NB>,
+    is_subset: fn(&Capability<NB>, &Capability<NB>) -> bool,
+}
+
+impl<NB> Default for Builder<NB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NB> From<Capability<NB>> for Builder<NB> {
+    fn from(capability: Capability<NB>) -> Self {
+        Self {
+            capability,
+            parent: None,
+            validators: BTreeMap::new(),
+            registry: None,
+            ability_policy: AbilityPolicy::default(),
+            allowed_reserved: BTreeSet::new(),
+            duplicate_policy: DuplicateGrantPolicy::default(),
+            case_normalization: CaseNormalization::default(),
+            proof_policy: ProofPolicy::default(),
+            allowed_schemes: None,
+            allowed_abilities: None,
+            compliance_profile: ComplianceProfile::default(),
+            duplicates: Vec::new(),
+            statement: None,
+            max_statement_length: None,
+            issued_now: false,
+            expires_in: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::message;
+    use crate::NamespaceSpec;
+    use serde_json::Value;
+
+    #[test]
+    fn build_with_statement() {
+        let msg = Builder::<Value>::new()
+            .with_action_convert("credential:*", "credential/present", [])
+            .unwrap()
+            .with_statement("Some custom statement.")
+            .build(Message {
+                domain: "example.com".parse().unwrap(),
+                address: Default::default(),
+                statement: Some("Replaced statement.".into()),
+                uri: "did:key:example".parse().unwrap(),
+                version: siwe::Version::V1,
+                chain_id: 1,
+                nonce: "mynonce1".into(),
+                issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                expiration_time: None,
+                not_before: None,
+                request_id: None,
+                resources: vec!["http://example.com".parse().unwrap()],
+            })
+            .unwrap();
+
+        assert_eq!(
+            include_str!("../tests/siwe_with_statement.txt").trim(),
+            msg.to_string()
+        );
+    }
+
+    #[test]
+    fn generated_fields() {
+        let msg = Builder::<Value>::new()
+            .with_issued_now()
+            .with_expiration_in(Duration::hours(1))
+            .build(Message {
+                domain: "example.com".parse().unwrap(),
+                address: Default::default(),
+                statement: None,
+                uri: "did:key:example".parse().unwrap(),
+                version: siwe::Version::V1,
+                chain_id: 1,
+                nonce: String::new(),
+                issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                expiration_time: None,
+                not_before: None,
+                request_id: None,
+                resources: vec![],
+            })
+            .unwrap();
+
+        assert!(msg.nonce.len() >= 8);
+        let issued_at: &OffsetDateTime = msg.issued_at.as_ref();
+        let expiration: &OffsetDateTime = msg.expiration_time.as_ref().unwrap().as_ref();
+        assert!(issued_at.year() > 2022);
+        assert_eq!(*expiration - *issued_at, Duration::hours(1));
+    }
+
+    #[test]
+    fn attenuate() {
+        let mut parent = Capability::<Value>::new();
+        parent
+            .with_actions_convert(
+                "kepler:ens:example.eth://default/kv",
+                [("kv/get", []), ("kv/put", [])],
+            )
+            .unwrap();
+        let parent_cid: Cid = "zdj7WWeQ43G6JJvLWQWZpyHuAMq6uYWRjkBXFad11vE2LHhQ7"
+            .parse()
+            .unwrap();
+        let msg = Message {
+            domain: "example.com".parse().unwrap(),
+            address: Default::default(),
+            statement: None,
+            uri: "did:key:example".parse().unwrap(),
+            version: siwe::Version::V1,
+            chain_id: 1,
+            nonce: "mynonce1".into(),
+            issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: vec![],
+        };
+
+        let child = Builder::attenuating(&parent, parent_cid)
+            .retain(|_, ability| ability.to_string() == "kv/get")
+            .build(msg.clone())
+            .unwrap();
+        let cap = Capability::<Value>::extract_and_verify(&child)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cap.proof(), &[parent_cid]);
+        assert!(cap.is_subset_of(&parent));
+        assert!(!parent.is_subset_of(&cap));
+
+        assert!(matches!(
+            Builder::attenuating(&parent, parent_cid)
+                .with_action_convert("kepler:ens:example.eth://default/kv", "kv/delete", [])
+                .unwrap()
+                .build(msg)
+                .unwrap_err()
+                .errors(),
+            [BuildError::NotAttenuated]
+        ));
+    }
+
+    #[test]
+    fn target_validation() {
+        let builder = Builder::<Value>::new()
+            .with_target_validator("kv", TargetValidator::scheme("kepler"))
+            .with_action_convert("kepler:ens:example.eth://default/kv", "kv/get", [])
+            .unwrap();
+        assert!(builder.validate().is_ok());
+
+        // grants are rejected as soon as they are added
+        assert!(matches!(
+            builder.with_action_convert("https://example.com/kv", "kv/get", []),
+            Err(GrantError::Rejected(BuildError::InvalidTarget { target, .. }))
+                if target.as_str() == "https://example.com/kv"
+        ));
+
+        // grants added before the validator are checked when validating
+        let builder = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_target_validator("kv", TargetValidator::scheme("kepler"));
+        assert!(matches!(
+            builder.validate().unwrap_err().errors(),
+            [BuildError::InvalidTarget { target, .. }] if target.as_str() == "https://example.com/kv"
+        ));
+    }
+
+    #[test]
+    fn duplicate_grants() {
+        let target = "kepler:ens:example.eth://default/kv";
+        let limit = |n: u64| BTreeMap::from([("max".to_string(), Value::from(n))]);
+        let builder = |policy| {
+            Builder::<Value>::new()
+                .with_duplicate_policy(policy)
+                .with_action_convert(target, "kv/put", [limit(1)])
+                .unwrap()
+                .with_action_convert(target, "kv/put", [limit(2)])
+                .unwrap()
+        };
+        let nbs = |builder: &Builder<Value>| {
+            builder
+                .capability()
+                .can(target, "kv/put")
+                .unwrap()
+                .map(|nbs| nbs.to_vec())
+        };
+
+        assert_eq!(
+            nbs(&builder(DuplicateGrantPolicy::Merge)),
+            Some(vec![limit(1), limit(2)])
+        );
+        assert_eq!(
+            nbs(&builder(DuplicateGrantPolicy::LastWins)),
+            Some(vec![limit(2)])
+        );
+        assert!(matches!(
+            builder(DuplicateGrantPolicy::Error)
+                .build(Message {
+                    domain: "example.com".parse().unwrap(),
+                    address: Default::default(),
+                    statement: None,
+                    uri: "did:key:example".parse().unwrap(),
+                    version: siwe::Version::V1,
+                    chain_id: 1,
+                    nonce: "mynonce1".into(),
+                    issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                    expiration_time: None,
+                    not_before: None,
+                    request_id: None,
+                    resources: vec![],
+                })
+                .unwrap_err()
+                .errors(),
+            [BuildError::DuplicateGrant { .. }]
+        ));
+    }
+
+    #[test]
+    fn aggregated_problems() {
+        let report = Builder::<Value>::new()
+            .with_duplicate_policy(DuplicateGrantPolicy::Error)
+            .with_max_statement_length(80)
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_target_validator("kv", TargetValidator::scheme("kepler"))
+            .build(Message {
+                domain: "example.com".parse().unwrap(),
+                address: Default::default(),
+                statement: None,
+                uri: "did:key:example".parse().unwrap(),
+                version: siwe::Version::V1,
+                chain_id: 1,
+                nonce: "mynonce1".into(),
+                issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                expiration_time: None,
+                not_before: None,
+                request_id: None,
+                resources: vec![],
+            })
+            .unwrap_err();
+        assert!(matches!(
+            report.errors(),
+            [
+                BuildError::InvalidTarget { .. },
+                BuildError::DuplicateGrant { .. },
+                BuildError::StatementTooLong { max: 80, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn case_normalization() {
+        let builder = Builder::<Value>::new()
+            .with_case_normalization(CaseNormalization::Lowercase)
+            .with_duplicate_policy(DuplicateGrantPolicy::Error)
+            .with_action_convert("https://example.com/kv", "KV/Get", [])
+            .unwrap()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        assert!(matches!(
+            builder.validate().unwrap_err().errors(),
+            [BuildError::DuplicateGrant { ability, .. }] if ability.to_string() == "kv/get"
+        ));
+    }
+
+    #[test]
+    fn reserved_namespaces() {
+        let builder = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "ucan/*", [])
+            .unwrap();
+        assert!(matches!(
+            builder.validate().unwrap_err().errors(),
+            [BuildError::ReservedNamespace { .. }]
+        ));
+        assert!(builder.allow_reserved_namespace("ucan").validate().is_ok());
+    }
+
+    #[test]
+    fn ability_policy_on_build() {
+        let report = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/gét", [])
+            .unwrap()
+            .build(message())
+            .unwrap_err();
+        assert!(matches!(
+            report.errors(),
+            [BuildError::InvalidAbility { .. }]
+        ));
+
+        let target = format!("https://example.com/{}", "a".repeat(64));
+        let report = Builder::<Value>::new()
+            .with_ability_policy(AbilityPolicy::new().with_max_target_length(32))
+            .with_action_convert(target.as_str(), "kv/get", [])
+            .unwrap()
+            .build(message())
+            .unwrap_err();
+        assert!(matches!(
+            report.errors(),
+            [BuildError::InvalidAbility {
+                source: AbilityError::TargetTooLong { max: 32, .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn registry_on_build() {
+        let registry = NamespaceRegistry::new()
+            .with_namespace("credential", NamespaceSpec::new().with_ability("present"));
+        let builder = Builder::<Value>::new().with_namespace_registry(registry);
+        assert!(builder
+            .clone()
+            .with_action_convert("urn:credential:type:type1", "credential/present", [])
+            .unwrap()
+            .build(message())
+            .is_ok());
+        let report = builder
+            .with_action_convert("urn:credential:type:type1", "credentail/present", [])
+            .unwrap()
+            .build(message())
+            .unwrap_err();
+        assert!(matches!(
+            report.errors(),
+            [BuildError::Registry {
+                source: RegistryError::UnknownNamespace(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn extend_message() {
+        let msg: Message = include_str!("../tests/siwe_with_statement.txt")
+            .trim()
+            .parse()
+            .unwrap();
+        let (builder, stripped) = Builder::<Value>::from_message(msg).unwrap();
+        assert_eq!(
+            stripped.statement.as_deref(),
+            Some("Some custom statement.")
+        );
+        assert_eq!(
+            stripped.resources,
+            vec!["http://example.com".parse::<UriString>().unwrap()]
+        );
+
+        let upgraded = builder
+            .with_action_convert("kepler:ens:example.eth://default/kv", "kv/put", [])
+            .unwrap()
+            .build(stripped)
+            .unwrap();
+        let cap = Capability::<Value>::extract_and_verify(&upgraded)
+            .unwrap()
+            .unwrap();
+        assert!(cap
+            .can("credential:*", "credential/present")
+            .unwrap()
+            .is_some());
+        assert!(cap
+            .can("kepler:ens:example.eth://default/kv", "kv/put")
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{base64url, Capability, SessionHeader, VerificationError, VerifiedSession};
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use siwe::Message;
+
+const VERSION: u8 = 1;
+#[cfg(feature = "compression")]
+const DEFLATE: u8 = 1;
+/// The maximum size of an inflated bundle, so that a small deflated bundle cannot exhaust memory.
+#[cfg(feature = "compression")]
+const MAX_INFLATED_SIZE: u64 = 1 << 18;
+
+/// A session bundled into a compact, versioned string, safe to stash in browser storage or
+/// cookies: `base64url(version || flags || u16 signature length || signature || message)`.
+///
+/// With the `compression` feature, the signature and message are deflated when that makes the
+/// bundle smaller, which keeps typical sessions under [`SessionBundle::COOKIE_SIZE_LIMIT`].
+#[derive(Clone, Debug)]
+pub struct SessionBundle<NB> {
+    message: Message,
+    signature: Vec<u8>,
+    capability: Capability<NB>,
+}
+
+impl<NB> SessionBundle<NB> {
+    /// The size limit of a cookie in most browsers, including its name.
+    pub const COOKIE_SIZE_LIMIT: usize = 4096;
+
+    /// The signed message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// The signature over the message.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The capabilities extracted from the message, which are empty if it contains no ReCap.
+    pub fn capability(&self) -> &Capability<NB> {
+        &self.capability
+    }
+
+    /// Recover the session header, e.g. to verify the session again.
+    pub fn into_session_header(self) -> SessionHeader {
+        SessionHeader::new(self.message, self.signature)
+    }
+
+    /// Encode the bundle, failing if the signature is longer than the `u16` length prefix allows.
+    pub fn encode(&self) -> Result<String, BundleError> {
+        let signature_len = u16::try_from(self.signature.len())
+            .map_err(|_| BundleError::SignatureTooLong(self.signature.len()))?;
+        let mut body = Vec::with_capacity(2 + self.signature.len() + 512);
+        body.extend_from_slice(&signature_len.to_be_bytes());
+        body.extend_from_slice(&self.signature);
+        body.extend_from_slice(self.message.to_string().as_bytes());
+
+        #[cfg(feature = "compression")]
+        let (flags, mut body) = match deflate(&body).filter(|deflated| deflated.len() < body.len())
+        {
+            Some(deflated) => (DEFLATE, deflated),
+            None => (0, body),
+        };
+        #[cfg(not(feature = "compression"))]
+        let flags = 0;
+        let mut bytes = vec![VERSION, flags];
+        bytes.append(&mut body);
+        Ok(base64url::encode(bytes))
+    }
+}
+
+impl<NB> SessionBundle<NB>
+where
+    NB: Clone,
+{
+    /// Bundle a verified session.
+    pub fn from_session(session: &VerifiedSession<NB>) -> Self {
+        Self {
+            message: session.message().clone(),
+            signature: session.signature().to_vec(),
+            capability: session.capability().clone(),
+        }
+    }
+}
+
+impl<NB> SessionBundle<NB>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    /// Decode a bundle, extracting the capabilities from the message.
+    ///
+    /// The signature is not verified, so the bundle should only be trusted as far as the storage
+    /// it was read from; [verify](SessionHeader::verify) the
+    /// [session header](Self::into_session_header) before presenting it to a server.
+    pub fn decode(encoded: &str) -> Result<Self, BundleError> {
+        let bytes = base64url::decode(encoded)?;
+        let (version, flags, body) = match bytes.as_slice() {
+            [version, flags, body @ ..] => (*version, *flags, body),
+            _ => return Err(BundleError::Format),
+        };
+        if version != VERSION {
+            return Err(BundleError::Version(version));
+        }
+        let body = match flags {
+            0 => Cow::Borrowed(body),
+            #[cfg(feature = "compression")]
+            DEFLATE => Cow::Owned(inflate(body)?),
+            _ => return Err(BundleError::Flags(flags)),
+        };
+        let (signature, message) = match body.as_ref() {
+            [high, low, rest @ ..] if rest.len() >= u16::from_be_bytes([*high, *low]) as usize => {
+                rest.split_at(u16::from_be_bytes([*high, *low]) as usize)
+            }
+            _ => return Err(BundleError::Format),
+        };
+        let message: Message = std::str::from_utf8(message)
+            .map_err(|_| BundleError::Format)?
+            .parse()?;
+        let capability = Capability::extract_and_verify(&message)?.unwrap_or_default();
+        Ok(Self {
+            message,
+            signature: signature.to_vec(),
+            capability,
+        })
+    }
+}
+
+#[cfg(feature = "compression")]
+fn deflate(bytes: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(bytes).ok()?;
+    encoder.finish().ok()
+}
+
+#[cfg(feature = "compression")]
+fn inflate(bytes: &[u8]) -> Result<Vec<u8>, BundleError> {
+    use std::io::Read;
+    let mut inflated = Vec::new();
+    flate2::read::DeflateDecoder::new(bytes)
+        .take(MAX_INFLATED_SIZE + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|_| BundleError::Format)?;
+    if inflated.len() as u64 > MAX_INFLATED_SIZE {
+        return Err(BundleError::Format);
+    }
+    Ok(inflated)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BundleError {
+    #[error("failed to decode base64 bundle: {0}")]
+    Base64Decode(#[from] base64::DecodeError),
+    #[error("unsupported bundle version {0}")]
+    Version(u8),
+    #[error("unsupported bundle flags {0:#04x}")]
+    Flags(u8),
+    #[error("malformed bundle")]
+    Format,
+    #[error("signature of {0} bytes is too long to bundle")]
+    SignatureTooLong(usize),
+    #[error("failed to parse siwe message: {0}")]
+    Message(#[from] siwe::ParseError),
+    #[error(transparent)]
+    Capability(#[from] VerificationError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{verification_options, verified_session};
+    use crate::Builder;
+    use serde_json::Value;
+
+    #[test]
+    fn bundle_roundtrip() {
+        let session = verified_session(
+            Builder::new()
+                .with_actions_convert(
+                    "https://example.com/kv",
+                    [("kv/get", []), ("kv/put", []), ("kv/list", [])],
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        let encoded = SessionBundle::from_session(&session).encode().unwrap();
+        assert!(encoded.len() < SessionBundle::<Value>::COOKIE_SIZE_LIMIT);
+        let bundle = SessionBundle::<Value>::decode(&encoded).unwrap();
+        assert_eq!(bundle.message(), session.message());
+        assert_eq!(bundle.signature(), session.signature());
+        assert_eq!(
+            serde_jcs::to_string(bundle.capability()).unwrap(),
+            serde_jcs::to_string(session.capability()).unwrap()
+        );
+        assert!(bundle
+            .into_session_header()
+            .verify::<Value>(&verification_options())
+            .is_ok());
+
+        let mut bytes = base64::decode_config(&encoded, base64::URL_SAFE_NO_PAD).unwrap();
+        bytes[0] = 2;
+        assert!(matches!(
+            SessionBundle::<Value>::decode(&base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)),
+            Err(BundleError::Version(2))
+        ));
+
+        let mut bundle = SessionBundle::from_session(&session);
+        bundle.signature = vec![0; usize::from(u16::MAX) + 1];
+        assert!(matches!(
+            bundle.encode(),
+            Err(BundleError::SignatureTooLong(65536))
+        ));
+
+        #[cfg(feature = "compression")]
+        {
+            let bomb = deflate(&vec![0; MAX_INFLATED_SIZE as usize + 1]).unwrap();
+            let mut bytes = vec![VERSION, DEFLATE];
+            bytes.extend(bomb);
+            assert!(matches!(
+                SessionBundle::<Value>::decode(&base64url::encode(bytes)),
+                Err(BundleError::Format)
+            ));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::did::parse_delegator_did;
+use crate::{delegator_did, DidError, SessionHeader, VerificationOptions, VerifiedSession};
+
+use iri_string::types::UriString;
+use serde::{Deserialize, Serialize};
+use siwe::{Message, TimeStamp, Version};
+
+const HEADER_TYPE: &str = "eip4361";
+const SIGNATURE_TYPE: &str = "eip191";
+
+/// A CAIP-74 CACAO of a SIWE message, as used by Ceramic's `did-session` and ComposeDB, so that a
+/// session can be converted to and from the structure Ceramic expects.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cacao {
+    h: CacaoHeader,
+    p: CacaoPayload,
+    s: CacaoSignature,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CacaoHeader {
+    t: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacaoPayload {
+    domain: String,
+    iss: String,
+    aud: String,
+    version: String,
+    nonce: String,
+    iat: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    nbf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    exp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    statement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    resources: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CacaoSignature {
+    t: String,
+    s: String,
+}
+
+impl Cacao {
+    /// Represent a signed SIWE message as a CACAO.
+    pub fn new(message: &Message, signature: &[u8]) -> Self {
+        let signature: String = signature.iter().map(|b| format!("{b:02x}")).collect();
+        Self {
+            h: CacaoHeader {
+                t: HEADER_TYPE.to_string(),
+            },
+            p: CacaoPayload {
+                domain: message.domain.to_string(),
+                iss: delegator_did(message),
+                aud: message.uri.to_string(),
+                version: (message.version as u8).to_string(),
+                nonce: message.nonce.clone(),
+                iat: message.issued_at.to_string(),
+                nbf: message.not_before.as_ref().map(ToString::to_string),
+                exp: message.expiration_time.as_ref().map(ToString::to_string),
+                statement: message.statement.clone(),
+                request_id: message.request_id.clone(),
+                resources: message.resources.iter().map(ToString::to_string).collect(),
+            },
+            s: CacaoSignature {
+                t: SIGNATURE_TYPE.to_string(),
+                s: format!("0x{signature}"),
+            },
+        }
+    }
+
+    /// The `did:pkh` of the signer.
+    pub fn issuer(&self) -> &str {
+        &self.p.iss
+    }
+
+    /// The URI of the delegee.
+    pub fn audience(&self) -> &str {
+        &self.p.aud
+    }
+
+    /// Recover the signed SIWE message, as a session to be verified.
+    pub fn to_session_header(&self) -> Result<SessionHeader, CacaoError> {
+        if self.h.t != HEADER_TYPE {
+            return Err(CacaoError::HeaderType(self.h.t.clone()));
+        }
+        if self.s.t != SIGNATURE_TYPE {
+            return Err(CacaoError::SignatureType(self.s.t.clone()));
+        }
+        let p = &self.p;
+        let (chain_id, address) = parse_delegator_did(&p.iss)?;
+        let message = Message {
+            domain: p.domain.parse().map_err(|_| field("domain"))?,
+            address,
+            statement: p.statement.clone(),
+            uri: p.aud.parse().map_err(|_| field("aud"))?,
+            version: p.version.parse::<Version>().map_err(|_| field("version"))?,
+            chain_id,
+            nonce: p.nonce.clone(),
+            issued_at: timestamp(&p.iat, "iat")?,
+            expiration_time: p.exp.as_deref().map(|t| timestamp(t, "exp")).transpose()?,
+            not_before: p.nbf.as_deref().map(|t| timestamp(t, "nbf")).transpose()?,
+            request_id: p.request_id.clone(),
+            resources: p
+                .resources
+                .iter()
+                .map(|r| UriString::try_from(r.as_str()).map_err(|_| field("resources")))
+                .collect::<Result<_, _>>()?,
+        };
+        let signature = parse_hex(&self.s.s).ok_or_else(|| field("s"))?;
+        Ok(SessionHeader::new(message, signature))
+    }
+
+    /// Verify the signed SIWE message and its capabilities.
+    pub fn verify<NB>(
+        &self,
+        options: &VerificationOptions,
+    ) -> Result<VerifiedSession<NB>, CacaoError>
+    where
+        NB: for<'a> Deserialize<'a> + Clone,
+    {
+        Ok(self.to_session_header()?.verify(options)?)
+    }
+}
+
+fn field(name: &'static str) -> CacaoError {
+    CacaoError::Field(name)
+}
+
+fn timestamp(t: &str, name: &'static str) -> Result<TimeStamp, CacaoError> {
+    t.parse().map_err(|_| field(name))
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    crate::hex::decode(hex.strip_prefix("0x")?)
+}
+
+impl<NB> VerifiedSession<NB> {
+    /// Represent the session as a CACAO, e.g. for use with Ceramic.
+    pub fn to_cacao(&self) -> Cacao {
+        Cacao::new(self.message(), self.signature())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CacaoError {
+    #[error("unsupported CACAO header type {0}")]
+    HeaderType(String),
+    #[error("unsupported CACAO signature type {0}")]
+    SignatureType(String),
+    #[error("invalid CACAO issuer: {0}")]
+    Issuer(#[from] DidError),
+    #[error("invalid CACAO field {0}")]
+    Field(&'static str),
+    #[error(transparent)]
+    Session(#[from] crate::SessionError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{verification_options, verified_session};
+    use crate::Builder;
+    use serde_json::Value;
+
+    #[test]
+    fn cacao_roundtrip() {
+        let session = verified_session(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+        let message = session.message();
+
+        let cacao = serde_json::to_value(session.to_cacao()).unwrap();
+        assert_eq!(cacao["h"]["t"], "eip4361");
+        assert_eq!(cacao["p"]["iss"], delegator_did(message));
+        assert_eq!(cacao["p"]["aud"], "did:key:example");
+        assert_eq!(cacao["p"]["exp"], "2022-06-21T13:00:00.000Z");
+        assert_eq!(cacao["s"]["t"], "eip191");
+
+        let cacao: Cacao = serde_json::from_value(cacao).unwrap();
+        let verified = cacao.verify::<Value>(&verification_options()).unwrap();
+        assert_eq!(verified.message(), message);
+        assert_eq!(
+            serde_jcs::to_string(verified.capability()).unwrap(),
+            serde_jcs::to_string(session.capability()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use iri_string::types::UriString;
+use siwe::Message;
+
+/// A CAIP-122 Sign-In-With-X message, e.g. for Ethereum, Solana, Tezos or Stacks accounts, to which
+/// ReCap capabilities can be attached.
+///
+/// Capabilities only depend on the statement and resources of the message, so any chain's message
+/// type can carry them by implementing this trait.
+pub trait SignInMessage {
+    /// The RFC 3986 authority requesting the signing.
+    fn domain(&self) -> &str;
+
+    /// The account address of the signer, in the chain's textual form.
+    fn address(&self) -> String;
+
+    /// The URI of the subject of the signing, i.e. the delegee.
+    fn uri(&self) -> &str;
+
+    /// The human-readable statement.
+    fn statement(&self) -> Option<&str>;
+
+    /// Replace the human-readable statement.
+    fn set_statement(&mut self, statement: Option<String>);
+
+    /// The resources the signer wishes to have resolved, in order.
+    fn resources(&self) -> impl Iterator<Item = &str>;
+
+    /// Add a resource to the end of the resources.
+    fn push_resource(&mut self, resource: UriString);
+}
+
+impl SignInMessage for Message {
+    fn domain(&self) -> &str {
+        self.domain.as_str()
+    }
+
+    fn address(&self) -> String {
+        siwe::eip55(&self.address)
+    }
+
+    fn uri(&self) -> &str {
+        self.uri.as_str()
+    }
+
+    fn statement(&self) -> Option<&str> {
+        self.statement.as_deref()
+    }
+
+    fn set_statement(&mut self, statement: Option<String>) {
+        self.statement = statement;
+    }
+
+    fn resources(&self) -> impl Iterator<Item = &str> {
+        self.resources.iter().map(|resource| resource.as_str())
+    }
+
+    fn push_resource(&mut self, resource: UriString) {
+        self.resources.push(resource);
+    }
+}
+
+/// Messages of the 0.6 series of the `siwe` crate, so that applications which have not yet
+/// migrated can attach and verify capabilities with the same API.
+#[cfg(feature = "siwe06")]
+impl SignInMessage for siwe06::Message {
+    fn domain(&self) -> &str {
+        self.domain.as_str()
+    }
+
+    fn address(&self) -> String {
+        siwe06::eip55(&self.address)
+    }
+
+    fn uri(&self) -> &str {
+        self.uri.as_str()
+    }
+
+    fn statement(&self) -> Option<&str> {
+        self.statement.as_deref()
+    }
+
+    fn set_statement(&mut self, statement: Option<String>) {
+        self.statement = statement;
+    }
+
+    fn resources(&self) -> impl Iterator<Item = &str> {
+        self.resources.iter().map(|resource| resource.as_str())
+    }
+
+    fn push_resource(&mut self, resource: UriString) {
+        self.resources.push(
+            resource
+                .as_str()
+                .parse()
+                .expect("a valid URI remains valid across iri-string versions"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Capability;
+    use serde_json::Value;
+
+    #[derive(Default)]
+    struct SolanaMessage {
+        address: String,
+        statement: Option<String>,
+        resources: Vec<UriString>,
+    }
+
+    impl SignInMessage for SolanaMessage {
+        fn domain(&self) -> &str {
+            "example.com"
+        }
+
+        fn address(&self) -> String {
+            self.address.clone()
+        }
+
+        fn uri(&self) -> &str {
+            "did:key:example"
+        }
+
+        fn statement(&self) -> Option<&str> {
+            self.statement.as_deref()
+        }
+
+        fn set_statement(&mut self, statement: Option<String>) {
+            self.statement = statement;
+        }
+
+        fn resources(&self) -> impl Iterator<Item = &str> {
+            self.resources.iter().map(|resource| resource.as_str())
+        }
+
+        fn push_resource(&mut self, resource: UriString) {
+            self.resources.push(resource);
+        }
+    }
+
+    #[test]
+    fn caip122_message() {
+        let mut capability = Capability::<Value>::new();
+        capability
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let message = capability
+            .build_message(SolanaMessage {
+                address: "GwAF45zjfyGzUbd3i3hXxzGeuchzEZXwpRYHZM5912F1".into(),
+                statement: Some("Sign in to example.com".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(message
+            .statement()
+            .unwrap()
+            .starts_with("Sign in to example.com I further authorize"));
+
+        let extracted = Capability::<Value>::extract_and_verify(&message)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_jcs::to_string(&extracted).unwrap(),
+            serde_jcs::to_string(&capability).unwrap()
+        );
+    }
+}
@@ -1,12 +1,21 @@
-use crate::RESOURCE_PREFIX;
+use crate::statement_hash::StatementHash;
+use crate::target_index::TargetIndex;
+use crate::{
+    adapters::AsSmallVec, base64url, has_orphaned_statement, AbilityError, AbilityExt, AbilityStr,
+    AsB58Cid, AttOrdering, ComplianceProfile, ProofPolicyError, RegistryError, SignInMessage,
+    RESOURCE_PREFIX,
+};
+use cid::multihash::{Code, MultihashDigest};
 use cid::Cid;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+use std::sync::OnceLock;
 
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DeserializeAs, SerializeAs};
+use serde_with::serde_as;
+use smallvec::SmallVec;
 
 use iri_string::types::UriString;
-use siwe::Message;
 
 use ucan_capabilities_object::{
     Ability, AbilityNameRef, AbilityNamespaceRef, Capabilities, CapsInner, ConvertError,
@@ -21,10 +30,26 @@ pub struct Capability<NB> {
     #[serde(rename = "att")]
     attenuations: Capabilities<NB>,
 
-    /// Cids of parent delegations which these capabilities are attenuated from
+    /// Cids of parent delegations which these capabilities are attenuated from, most often none or
+    /// one, which are held inline
     #[serde(rename = "prf")]
-    #[serde_as(as = "Vec<B58Cid>")]
-    proof: Vec<Cid>,
+    #[serde_as(as = "AsSmallVec<AsB58Cid>")]
+    proof: SmallVec<[Cid; 1]>,
+
+    /// The encoded ReCap URI, computed on first use and cleared on mutation, so that messages can
+    /// be issued repeatedly from one set of capabilities without re-encoding it.
+    #[serde(skip)]
+    encoded: OnceLock<UriString>,
+
+    /// An index of the targets by path, built on the first hierarchical lookup and cleared on
+    /// mutation.
+    #[serde(skip)]
+    index: OnceLock<Box<TargetIndex>>,
+
+    /// The ReCap statement and its hash in the wording of each compliance profile, indexed by
+    /// profile, computed on first use and cleared on mutation.
+    #[serde(skip)]
+    statement_hashes: OnceLock<Box<[OnceLock<StatementHash>; ComplianceProfile::ALL.len()]>>,
 }
 
 impl<NB> Capability<NB> {
@@ -33,6 +58,9 @@ impl<NB> Capability<NB> {
         Self {
             attenuations: Capabilities::new(),
             proof: Default::default(),
+            encoded: OnceLock::new(),
+            index: OnceLock::new(),
+            statement_hashes: OnceLock::new(),
         }
     }
 
@@ -54,12 +82,125 @@ impl<NB> Capability<NB> {
         self.attenuations.can_do(target, action)
     }
 
+    /// Check if a particular action is allowed for the specified target, without allocating owned keys.
+    ///
+    /// Wildcards are not expanded, as with [`Capability::can_do`].
+    pub fn can_do_str(
+        &self,
+        target: &str,
+        action: AbilityStr<'_>,
+    ) -> Option<&NotaBeneCollection<NB>> {
+        self.abilities_for_str(target)?
+            .iter()
+            .find(|(ability, _)| action == **ability)
+            .map(|(_, nbs)| nbs)
+    }
+
+    /// Find the grant of an action on the target or on the most specific target above it in the
+    /// path hierarchy, returning the granted target along with the nota benes.
+    ///
+    /// A grant on a target covers the targets beneath it, so a grant on `https://example.com/kv`
+    /// covers `https://example.com/kv/public` but not `https://example.com/kvstore`. Targets with
+    /// dot-segments, e.g. `https://example.com/kv/../admin`, are never covered, even when encoded as
+    /// `%2e`. Targets are indexed by path on the first lookup, so that checks stay fast with
+    /// hundreds of targets.
+    pub fn can_do_hierarchical(
+        &self,
+        target: &str,
+        action: AbilityStr<'_>,
+    ) -> Option<(&UriString, &NotaBeneCollection<NB>)> {
+        self.index
+            .get_or_init(|| Box::new(TargetIndex::new(self.attenuations.abilities().keys())))
+            .covering(target)
+            .into_iter()
+            .find_map(|granted| {
+                self.can_do_str(granted.as_str(), action)
+                    .map(|nbs| (granted, nbs))
+            })
+    }
+
+    /// Read the set of abilities granted for a given target, without allocating an owned key.
+    pub fn abilities_for_str(
+        &self,
+        target: &str,
+    ) -> Option<&BTreeMap<Ability, NotaBeneCollection<NB>>> {
+        self.attenuations.abilities().get(target)
+    }
+
+    /// Check if every capability in this set is also granted by `other`, with nota benes at least as restrictive.
+    ///
+    /// An action is granted by `other` if one of its abilities for the same target [covers](AbilityExt::covers) it.
+    /// A nota bene is at least as restrictive as another if it contains all of the other's entries.
+    /// An empty nota bene collection is unrestricted, the same as a single empty nota bene.
+    pub fn is_subset_of(&self, other: &Capability<NB>) -> bool
+    where
+        NB: PartialEq,
+    {
+        fn or_unrestricted<'a, NB>(
+            nbs: &'a NotaBeneCollection<NB>,
+            unrestricted: &'a [BTreeMap<String, NB>],
+        ) -> &'a [BTreeMap<String, NB>] {
+            match nbs.is_empty() {
+                true => unrestricted,
+                false => nbs,
+            }
+        }
+        let unrestricted = [BTreeMap::new()];
+        self.attenuations
+            .abilities()
+            .iter()
+            .all(|(target, abilities)| {
+                let parent_abilities = match other.attenuations.abilities().get(target) {
+                    Some(parent_abilities) => parent_abilities,
+                    None => return false,
+                };
+                abilities.iter().all(|(ability, nbs)| {
+                    let covering: Vec<_> = parent_abilities
+                        .iter()
+                        .filter(|(parent, _)| parent.covers(ability))
+                        .map(|(_, parent_nbs)| parent_nbs)
+                        .collect();
+                    !covering.is_empty()
+                        && or_unrestricted(nbs, &unrestricted).iter().all(|nb| {
+                            covering
+                                .iter()
+                                .flat_map(|parent_nbs| or_unrestricted(parent_nbs, &unrestricted))
+                                .any(|parent_nb| {
+                                    parent_nb.iter().all(|(k, v)| nb.get(k) == Some(v))
+                                })
+                        })
+                })
+            })
+    }
+
+    /// Copy the grants for which the predicate returns true into a new capabilities set, keeping all proofs.
+    pub(crate) fn filtered(&self, mut f: impl FnMut(&UriString, &Ability) -> bool) -> Self
+    where
+        NB: Clone,
+    {
+        let mut capability = Self::new().with_proofs(self.proof());
+        for (target, abilities) in self.attenuations.abilities() {
+            let kept: Vec<_> = abilities
+                .iter()
+                .filter(|(ability, _)| f(target, ability))
+                .map(|(ability, nbs)| (ability.clone(), nbs.iter().cloned()))
+                .collect();
+            if !kept.is_empty() {
+                capability.with_actions(target.clone(), kept);
+            }
+        }
+        capability
+    }
+
     /// Merge this Capabilities set with another
+    ///
+    /// As the note-benes may change type, both sides are rebuilt; use [`Capability::merge_in`] to
+    /// add grants to a large set of capabilities.
     pub fn merge<NB1, NB2>(self, other: Capability<NB1>) -> Capability<NB2>
     where
         NB2: From<NB> + From<NB1>,
     {
-        let (caps, mut proofs) = self.into_inner();
+        let (caps, mut proofs) = (self.attenuations, self.proof);
         for proof in &other.proof {
             if proofs.contains(proof) {
                 continue;
@@ -70,9 +211,138 @@ impl<NB> Capability<NB> {
         Capability {
             attenuations: caps.merge(other.attenuations),
             proof: proofs,
+            encoded: OnceLock::new(),
+            index: OnceLock::new(),
+            statement_hashes: OnceLock::new(),
+        }
+    }
+
+    /// Merge another Capabilities set into this one, moving its grants and note-benes rather than
+    /// rebuilding either side. If this set is empty, it takes the other's grants wholesale, and if
+    /// the other is empty, only its proofs are added.
+    pub fn merge_in(&mut self, other: Self) {
+        let Capability {
+            attenuations,
+            proof,
+            ..
+        } = other;
+        if !attenuations.abilities().is_empty() {
+            self.invalidate();
+            if self.attenuations.abilities().is_empty() {
+                self.attenuations = attenuations;
+            } else {
+                for (target, abilities) in attenuations.into_inner() {
+                    self.attenuations.with_actions(
+                        target,
+                        abilities
+                            .into_iter()
+                            .map(|(ability, nbs)| (ability, nbs.into_inner())),
+                    );
+                }
+            }
+        }
+        for cid in proof {
+            if !self.proof.contains(&cid) {
+                self.invalidate();
+                self.proof.push(cid);
+            }
+        }
+    }
+
+    /// The approximate number of heap bytes held by the capabilities, including the cached URI and
+    /// index, e.g. to measure a cache of decoded capabilities. Note-benes are counted by their
+    /// inline size, as the heap usage of `NB` is not known.
+    pub fn memory_footprint(&self) -> usize {
+        use std::mem::size_of;
+        let nb_entry = size_of::<String>() + size_of::<NB>();
+        let abilities: usize = self
+            .attenuations
+            .abilities()
+            .iter()
+            .map(|(target, abilities)| {
+                size_of::<UriString>()
+                    + target.as_str().len()
+                    + abilities
+                        .iter()
+                        .map(|(ability, nbs)| {
+                            size_of::<(Ability, NotaBeneCollection<NB>)>()
+                                + ability.to_string().len()
+                                + nbs.capacity() * size_of::<BTreeMap<String, NB>>()
+                                + nbs
+                                    .iter()
+                                    .flatten()
+                                    .map(|(key, _)| nb_entry + key.len())
+                                    .sum::<usize>()
+                        })
+                        .sum::<usize>()
+            })
+            .sum();
+        let proof = if self.proof.spilled() {
+            self.proof.capacity() * size_of::<Cid>()
+        } else {
+            0
+        };
+        abilities
+            + proof
+            + self.encoded.get().map_or(0, |uri| uri.as_str().len())
+            + self.index.get().map_or(0, |index| {
+                size_of::<TargetIndex>() + index.memory_footprint()
+            })
+    }
+
+    /// Release excess capacity, e.g. before holding the capabilities in a long-lived cache.
+    pub fn shrink_to_fit(&mut self) {
+        let attenuations = std::mem::replace(&mut self.attenuations, Capabilities::new());
+        for (target, abilities) in attenuations.into_inner() {
+            self.attenuations.with_actions(
+                target,
+                abilities
+                    .into_iter()
+                    .map(|(ability, nbs)| (ability, nbs.into_inner())),
+            );
+        }
+        self.proof.shrink_to_fit();
+        if let Some(index) = self.index.get_mut() {
+            index.shrink_to_fit();
         }
     }
 
+    /// Find a target which is empty, has no abilities, or has an ability with an empty namespace
+    /// or name, along with the reason it is degenerate.
+    fn degenerate_grant(&self) -> Option<(&UriString, &'static str)> {
+        self.attenuations
+            .abilities()
+            .iter()
+            .find_map(|(target, abilities)| {
+                degenerate_reason(
+                    target.as_str(),
+                    abilities.keys().map(|ability| {
+                        ability.namespace().to_string().is_empty()
+                            || ability.name().to_string().is_empty()
+                    }),
+                )
+                .map(|reason| (target, reason))
+            })
+    }
+
+    /// The targets whose URI scheme is not one of the given lowercase schemes.
+    pub(crate) fn disallowed_targets<'s>(
+        &'s self,
+        schemes: &'s BTreeSet<String>,
+    ) -> impl Iterator<Item = &'s UriString> {
+        self.attenuations
+            .abilities()
+            .keys()
+            .filter(|target| !schemes.contains(&target.scheme_str().to_ascii_lowercase()))
+    }
+
+    /// Clear the cached encoding, index and statement hash, before a mutation.
+    fn invalidate(&mut self) {
+        self.encoded.take();
+        self.index.take();
+        self.statement_hashes.take();
+    }
+
     /// Add an allowed action for the given target, with a set of note-benes
     pub fn with_action(
         &mut self,
@@ -80,6 +350,7 @@ impl<NB> Capability<NB> {
         action: Ability,
         nb: impl IntoIterator<Item = BTreeMap<String, NB>>,
     ) -> &mut Self {
+        self.invalidate();
         self.attenuations.with_action(target, action, nb);
         self
     }
@@ -97,6 +368,7 @@ impl<NB> Capability<NB> {
         T: TryInto<UriString>,
         A: TryInto<Ability>,
     {
+        self.invalidate();
         self.attenuations.with_action_convert(target, action, nb)?;
         Ok(self)
     }
@@ -107,6 +379,7 @@ impl<NB> Capability<NB> {
         target: UriString,
         abilities: impl IntoIterator<Item = (Ability, impl IntoIterator<Item = BTreeMap<String, NB>>)>,
     ) -> &mut Self {
+        self.invalidate();
         self.attenuations.with_actions(target, abilities);
         self
     }
@@ -124,6 +397,7 @@ impl<NB> Capability<NB> {
         A: TryInto<Ability>,
         N: IntoIterator<Item = BTreeMap<String, NB>>,
     {
+        self.invalidate();
         self.attenuations.with_actions_convert(target, abilities)?;
         Ok(self)
     }
@@ -154,6 +428,7 @@ impl<NB> Capability<NB> {
         if self.proof.contains(proof) {
             return self;
         }
+        self.invalidate();
         self.proof.push(*proof);
         self
     }
@@ -164,67 +439,171 @@ impl<NB> Capability<NB> {
             if self.proof.contains(proof) {
                 continue;
             }
+            self.invalidate();
             self.proof.push(*proof);
         }
         self
     }
 
+    /// Group the abilities on each resource by namespace, without allocating. As abilities are
+    /// ordered by namespace then name, each namespace is a contiguous run of the ability map.
     fn to_line_groups(
         &self,
-    ) -> impl Iterator<Item = (&UriString, AbilityNamespaceRef, Vec<AbilityNameRef>)> {
+    ) -> impl Iterator<
+        Item = (
+            &UriString,
+            AbilityNamespaceRef<'_>,
+            impl Iterator<Item = AbilityNameRef<'_>>,
+        ),
+    > {
         self.attenuations
             .abilities()
             .iter()
             .flat_map(|(resource, abilities)| {
-                // group abilities by namespace
-                abilities
-                    .iter()
-                    .fold(
-                        BTreeMap::<AbilityNamespaceRef, Vec<AbilityNameRef>>::new(),
-                        |mut map, (ability, _)| {
-                            map.entry(ability.namespace())
-                                .or_default()
-                                .push(ability.name());
-                            map
-                        },
-                    )
-                    .into_iter()
-                    .map(move |(namespace, names)| (resource, namespace, names))
+                namespace_runs(abilities.keys(), |ability| ability.namespace()).map(
+                    move |(namespace, run)| {
+                        (resource, namespace, run.map(|ability| ability.name()))
+                    },
+                )
             })
     }
 
-    fn to_statement_lines(&self) -> impl Iterator<Item = String> + '_ {
-        self.to_line_groups().map(|(resource, namespace, names)| {
-            format!(
-                "'{}': {} for '{}'.",
-                namespace,
-                names
-                    .iter()
-                    .map(|an| format!("'{an}'"))
-                    .collect::<Vec<String>>()
-                    .join(", "),
-                resource
-            )
-        })
+    /// The numbered lines of the ReCap statement in the wording of the given compliance profile,
+    /// without their numbers.
+    pub(crate) fn to_statement_lines_with(&self, profile: ComplianceProfile) -> Vec<String> {
+        self.to_line_groups()
+            .map(|(resource, namespace, names)| {
+                let mut line = String::with_capacity(resource.as_str().len() + 32);
+                push_statement_line(&mut line, profile, resource.as_str(), namespace, names);
+                line
+            })
+            .collect()
     }
 
     pub fn into_inner(self) -> (Capabilities<NB>, Vec<Cid>) {
-        (self.attenuations, self.proof)
+        (self.attenuations, self.proof.into_vec())
     }
+    /// The ReCap statement in the wording of the given compliance profile and its hash, computed
+    /// once, so that messages can be [checked](Capability::verify_statement) against the
+    /// capabilities without regenerating the statement.
+    pub fn statement_hash(&self, profile: ComplianceProfile) -> &StatementHash {
+        self.statement_hashes.get_or_init(Default::default)[profile as usize]
+            .get_or_init(|| StatementHash::new(self.to_statement_with(profile)))
+    }
+
     /// Generate a ReCap statement from capabilities and URI (delegee).
     pub fn to_statement(&self) -> String {
-        [
-            "I further authorize the stated URI to perform the following actions on my behalf:"
-                .to_string(),
-            self.to_statement_lines()
-                .enumerate()
-                .map(|(n, line)| format!(" ({}) {line}", n + 1))
-                .collect(),
-        ]
-        .concat()
+        self.to_statement_with(ComplianceProfile::Eip5573Final)
+    }
+
+    /// Generate a ReCap statement in the wording of the given compliance profile.
+    pub fn to_statement_with(&self, profile: ComplianceProfile) -> String {
+        let mut statement = String::with_capacity(
+            self.attenuations
+                .abilities()
+                .iter()
+                .map(|(resource, abilities)| {
+                    abilities
+                        .len()
+                        .saturating_mul(16)
+                        .saturating_add(resource.as_str().len() + 24)
+                })
+                .fold(profile.preamble().len(), usize::saturating_add),
+        );
+        self.write_statement(&mut statement, profile);
+        statement
+    }
+
+    /// The length in bytes of the ReCap statement in the wording of the given compliance
+    /// profile, computed without generating it, e.g. to enforce a size limit before building.
+    pub fn statement_len(&self, profile: ComplianceProfile) -> usize {
+        let mut counter = LengthCounter(0);
+        self.write_statement(&mut counter, profile);
+        counter.0
+    }
+
+    fn write_statement(&self, out: &mut impl Write, profile: ComplianceProfile) {
+        push_statement(
+            out,
+            profile,
+            self.to_line_groups()
+                .map(|(resource, namespace, names)| (resource.as_str(), namespace, names)),
+        );
     }
 }
 
+/// Counts the bytes written to it, saturating rather than overflowing.
+struct LengthCounter(usize);
+
+impl Write for LengthCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 = self.0.saturating_add(s.len());
+        Ok(())
+    }
+}
+
+/// Split abilities, ordered by namespace then name, into the runs of each namespace.
+pub(crate) fn namespace_runs<A, N, I>(
+    abilities: I,
+    namespace: impl Fn(&A) -> N,
+) -> impl Iterator<Item = (N, std::iter::Take<I>)>
+where
+    I: Iterator<Item = A> + Clone,
+    N: PartialEq,
+{
+    let mut rest = abilities;
+    std::iter::from_fn(move || {
+        let run = rest.clone();
+        let first = namespace(&rest.next()?);
+        let len = 1 + rest
+            .clone()
+            .take_while(|ability| namespace(ability) == first)
+            .count();
+        if len > 1 {
+            rest.nth(len - 2);
+        }
+        Some((first, run.take(len)))
+    })
+}
+
+/// Write a ReCap statement, given the namespace groups of abilities of each resource, in order.
+pub(crate) fn push_statement<'r, N, I>(
+    out: &mut impl Write,
+    profile: ComplianceProfile,
+    groups: impl Iterator<Item = (&'r str, N, I)>,
+) where
+    N: fmt::Display,
+    I: Iterator,
+    I::Item: fmt::Display,
+{
+    // writing to a string or counter never fails
+    let _ = out.write_str(profile.preamble());
+    for (n, (resource, namespace, names)) in (1..).zip(groups) {
+        let _ = write!(out, " ({n}) ");
+        push_statement_line(out, profile, resource, namespace, names);
+    }
+}
+
+/// Write a statement line, e.g. `'kv': 'get', 'put' for 'https://example.com/kv'.`, without
+/// allocating intermediate strings.
+fn push_statement_line(
+    out: &mut impl Write,
+    profile: ComplianceProfile,
+    resource: &str,
+    namespace: impl fmt::Display,
+    names: impl Iterator<Item = impl fmt::Display>,
+) {
+    let q = profile.quote();
+    let _ = write!(out, "{q}{namespace}{q}: ");
+    for (i, name) in names.enumerate() {
+        if i > 0 {
+            let _ = out.write_str(", ");
+        }
+        let _ = write!(out, "{q}{name}{q}");
+    }
+    let _ = write!(out, " for {q}{resource}{q}.");
+}
+
 impl<NB> Capability<NB>
 where
     NB: Serialize,
@@ -232,23 +611,52 @@ where
     fn encode(&self) -> Result<String, EncodingError> {
         serde_jcs::to_vec(self)
             .map_err(EncodingError::Ser)
-            .map(|bytes| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD))
+            .map(base64url::encode)
     }
 
-    /// Apply this capabilities set to a SIWE message by writing to it's statement and resource list
-    pub fn build_message(&self, mut message: Message) -> Result<Message, EncodingError> {
+    /// Compute the CID of this capabilities set: a CIDv1 with the raw codec over the SHA2-256 digest of its canonical JSON.
+    pub fn to_cid(&self) -> Result<Cid, EncodingError> {
+        self.to_block().map(|(cid, _)| cid)
+    }
+
+    /// The canonical JSON of this capabilities set along with its CID.
+    pub(crate) fn to_block(&self) -> Result<(Cid, Vec<u8>), EncodingError> {
+        const RAW: u64 = 0x55;
+        let bytes = serde_jcs::to_vec(self).map_err(EncodingError::Ser)?;
+        Ok((Cid::new_v1(RAW, Code::Sha2_256.digest(&bytes)), bytes))
+    }
+
+    /// Apply this capabilities set to a SIWE message, or any other [CAIP-122](SignInMessage)
+    /// message, by writing to it's statement and resource list
+    pub fn build_message<M: SignInMessage>(&self, message: M) -> Result<M, EncodingError> {
+        self.build_message_with(message, ComplianceProfile::Eip5573Final)
+    }
+
+    /// Apply this capabilities set to a message in the format of the given compliance profile.
+    pub fn build_message_with<M: SignInMessage>(
+        &self,
+        mut message: M,
+        profile: ComplianceProfile,
+    ) -> Result<M, EncodingError> {
         if self.attenuations.abilities().is_empty() {
             return Ok(message);
         }
-        let statement = self.to_statement();
-        let encoded: UriString = self.try_into()?;
-        message.resources.push(encoded);
-        let m = message.statement.unwrap_or_default();
-        message.statement = Some(if m.is_empty() {
+        // the ReCap must be the last resource, so an existing one would not be verified
+        if let Some(position) = message
+            .resources()
+            .position(|resource| resource.starts_with(RESOURCE_PREFIX))
+        {
+            return Err(EncodingError::RecapAlreadyPresent(position));
+        }
+        let statement = self.to_statement_with(profile);
+        message.push_resource(self.try_into()?);
+        let m = message.statement().unwrap_or_default();
+        let statement = if m.is_empty() {
             statement
         } else {
             format!("{m} {statement}")
-        });
+        };
+        message.set_statement(Some(statement));
         Ok(message)
     }
 }
@@ -257,34 +665,264 @@ impl<NB> Capability<NB>
 where
     NB: for<'a> Deserialize<'a>,
 {
-    /// Extract the encoded capabilities from a SIWE message and ensures the correctness of the statement.
-    pub fn extract_and_verify(message: &Message) -> Result<Option<Self>, VerificationError> {
-        if let Some(c) = Self::extract(message)? {
-            let expected = c.to_statement();
-            match &message.statement {
-                Some(s) if s.ends_with(&expected) => Ok(Some(c)),
-                _ => Err(VerificationError::IncorrectStatement(expected)),
-            }
+    /// Extract the encoded capabilities from a SIWE message, or any other [CAIP-122](SignInMessage)
+    /// message, and ensures the correctness of the statement.
+    pub fn extract_and_verify<M: SignInMessage + ?Sized>(
+        message: &M,
+    ) -> Result<Option<Self>, VerificationError> {
+        Self::extract_and_verify_as(
+            message,
+            DecodingProfile::Lenient,
+            &DecodingLimits::default(),
+            false,
+            ComplianceProfile::Eip5573Final,
+        )
+    }
+
+    pub(crate) fn extract_and_verify_as<M: SignInMessage + ?Sized>(
+        message: &M,
+        profile: DecodingProfile,
+        limits: &DecodingLimits,
+        allow_degenerate: bool,
+        compliance: ComplianceProfile,
+    ) -> Result<Option<Self>, VerificationError> {
+        if let Some(c) = Self::extract(message, profile, limits, allow_degenerate)? {
+            check_statement(message, compliance, |p| c.to_statement_with(p))?;
+            Ok(Some(c))
+        } else if profile == DecodingProfile::Strict && has_orphaned_statement(message) {
+            Err(VerificationError::OrphanedStatement)
         } else {
             // no caps
             Ok(None)
         }
     }
 
-    fn extract(message: &Message) -> Result<Option<Self>, DecodingError> {
+    fn extract<M: SignInMessage + ?Sized>(
+        message: &M,
+        profile: DecodingProfile,
+        limits: &DecodingLimits,
+        allow_degenerate: bool,
+    ) -> Result<Option<Self>, DecodingError> {
         message
-            .resources
-            .iter()
+            .resources()
             .last()
-            .filter(|u| u.as_str().starts_with(RESOURCE_PREFIX))
-            .map(Self::try_from)
+            .and_then(|u| u.strip_prefix(RESOURCE_PREFIX))
+            .map(|encoded| Self::decode(encoded, profile, limits, allow_degenerate))
             .transpose()
     }
 
-    fn decode(encoded: &str) -> Result<Self, DecodingError> {
-        base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
-            .map_err(DecodingError::Base64Decode)
-            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(DecodingError::De))
+    /// Decode a ReCap URI with the given profile and the default [limits](DecodingLimits),
+    /// rejecting degenerate grants.
+    pub fn decode_with(uri: &UriString, profile: DecodingProfile) -> Result<Self, DecodingError> {
+        uri.as_str()
+            .strip_prefix(RESOURCE_PREFIX)
+            .ok_or_else(|| DecodingError::InvalidResourcePrefix(uri.to_string()))
+            .and_then(|encoded| Self::decode(encoded, profile, &DecodingLimits::default(), false))
+    }
+
+    fn decode(
+        encoded: &str,
+        profile: DecodingProfile,
+        limits: &DecodingLimits,
+        allow_degenerate: bool,
+    ) -> Result<Self, DecodingError> {
+        let bytes = base64url::decode(encoded)?;
+        check_payload(&bytes, profile, limits)?;
+        let capability: Self = serde_json::from_slice(&bytes)
+            .map_err(|error| invalid_nota_bene(&bytes).unwrap_or(DecodingError::De(error)))?;
+        match capability.degenerate_grant() {
+            Some((target, reason)) if !allow_degenerate => Err(DecodingError::DegenerateGrant {
+                target: target.to_string(),
+                reason,
+            }),
+            _ => Ok(capability),
+        }
+    }
+}
+
+/// The reason a grant on a target is degenerate, if it is, given whether each of its abilities
+/// has an empty namespace or name.
+pub(crate) fn degenerate_reason(
+    target: &str,
+    empty_abilities: impl Iterator<Item = bool>,
+) -> Option<&'static str> {
+    let mut empty_abilities = empty_abilities.peekable();
+    if target.is_empty() {
+        Some("the target is empty")
+    } else if empty_abilities.peek().is_none() {
+        Some("no abilities are granted")
+    } else {
+        empty_abilities
+            .any(|empty| empty)
+            .then_some("an ability has an empty namespace or name")
+    }
+}
+
+/// Check that the statement of a message ends with the ReCap statement in the wording of the
+/// compliance profile, given a function generating the ReCap statement in any wording.
+pub(crate) fn check_statement<M: SignInMessage + ?Sized>(
+    message: &M,
+    compliance: ComplianceProfile,
+    statement: impl Fn(ComplianceProfile) -> String,
+) -> Result<(), VerificationError> {
+    let expected = statement(compliance);
+    match message.statement() {
+        Some(s) if s.ends_with(&expected) => Ok(()),
+        Some(s) => match ComplianceProfile::detect(s, statement) {
+            Some(found) => Err(VerificationError::StatementWording {
+                found,
+                expected: compliance,
+            }),
+            None => Err(VerificationError::IncorrectStatement(expected)),
+        },
+        None => Err(VerificationError::IncorrectStatement(expected)),
+    }
+}
+
+/// Check the JSON of a ReCap against the limits and, with the strict profile, that it is
+/// canonical, before it is deserialized.
+pub(crate) fn check_payload(
+    bytes: &[u8],
+    profile: DecodingProfile,
+    limits: &DecodingLimits,
+) -> Result<(), DecodingError> {
+    limits.check(bytes)?;
+    if profile == DecodingProfile::Strict {
+        AttOrdering::check(bytes)?;
+        let value: serde_json::Value = serde_json::from_slice(bytes)?;
+        if serde_jcs::to_vec(&value)? != bytes {
+            return Err(DecodingError::NonCanonical);
+        }
+        if let Some(field) = value.as_object().and_then(|object| {
+            object
+                .keys()
+                .find(|key| !matches!(key.as_str(), "att" | "prf"))
+        }) {
+            return Err(DecodingError::UnknownField(field.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Find a grant whose note-benes are not an array of objects, as EIP-5573 requires, to explain
+/// why a payload failed to deserialize.
+pub(crate) fn invalid_nota_bene(bytes: &[u8]) -> Option<DecodingError> {
+    let value: serde_json::Value = serde_json::from_slice(bytes).ok()?;
+    value
+        .get("att")?
+        .as_object()?
+        .iter()
+        .find_map(|(target, abilities)| {
+            abilities
+                .as_object()?
+                .iter()
+                .find(|(_, nbs)| {
+                    !nbs.as_array()
+                        .is_some_and(|nbs| nbs.iter().all(serde_json::Value::is_object))
+                })
+                .map(|(ability, _)| DecodingError::InvalidNotaBene {
+                    target: target.clone(),
+                    ability: ability.clone(),
+                })
+        })
+}
+
+/// How strictly a ReCap payload is checked when decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DecodingProfile {
+    /// Accept any JSON which deserializes to capabilities, ignoring unknown fields.
+    #[default]
+    Lenient,
+    /// Only accept JSON in JCS canonical form without fields besides `att` and `prf`, so that a
+    /// set of capabilities has exactly one encoding and nothing is dropped when it is re-encoded.
+    Strict,
+}
+
+/// Limits on the note-benes of a decoded ReCap, so that hostile payloads cannot exhaust the stack
+/// or memory when deserialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DecodingLimits {
+    max_nb_depth: usize,
+    max_nb_size: usize,
+}
+
+impl DecodingLimits {
+    /// The default maximum nesting depth of the note-benes of a grant, counting the array itself.
+    pub const DEFAULT_MAX_NB_DEPTH: usize = 32;
+    /// The default maximum total size in bytes of the note-bene JSON of all grants.
+    pub const DEFAULT_MAX_NB_SIZE: usize = 256 * 1024;
+
+    /// Create the default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum nesting depth of the note-benes of a grant, counting the array itself.
+    pub fn with_max_nb_depth(mut self, depth: usize) -> Self {
+        self.max_nb_depth = depth;
+        self
+    }
+
+    /// Set the maximum total size in bytes of the note-bene JSON of all grants.
+    pub fn with_max_nb_size(mut self, size: usize) -> Self {
+        self.max_nb_size = size;
+        self
+    }
+
+    /// Scan the JSON of a ReCap, without recursing, for note-benes exceeding the limits. The
+    /// note-benes are the values nested more than three levels deep, i.e. below `att`, a target
+    /// and an ability.
+    fn check(&self, json: &[u8]) -> Result<(), DecodingError> {
+        const NB_DEPTH: usize = 4;
+        let (mut depth, mut start, mut size) = (0, 0, 0);
+        let (mut in_string, mut escaped) = (false, false);
+        for (i, &byte) in json.iter().enumerate() {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => {
+                    depth += 1;
+                    if depth == NB_DEPTH {
+                        start = i;
+                    }
+                    if depth + 1 > NB_DEPTH + self.max_nb_depth {
+                        return Err(DecodingError::NbTooDeep {
+                            max: self.max_nb_depth,
+                        });
+                    }
+                }
+                b'}' | b']' => {
+                    if depth == NB_DEPTH {
+                        size += i + 1 - start;
+                        if size > self.max_nb_size {
+                            return Err(DecodingError::NbTooLarge {
+                                max: self.max_nb_size,
+                            });
+                        }
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodingLimits {
+    fn default() -> Self {
+        Self {
+            max_nb_depth: Self::DEFAULT_MAX_NB_DEPTH,
+            max_nb_size: Self::DEFAULT_MAX_NB_SIZE,
+        }
     }
 }
 
@@ -294,16 +932,34 @@ impl<NB> Default for Capability<NB> {
     }
 }
 
+/// Capabilities are equal if they grant the same abilities with the same nota benes and proofs,
+/// regardless of their caches.
+impl<NB: PartialEq> PartialEq for Capability<NB> {
+    fn eq(&self, other: &Self) -> bool {
+        self.attenuations.abilities() == other.attenuations.abilities() && self.proof == other.proof
+    }
+}
+
+impl<NB: Eq> Eq for Capability<NB> {}
+
+/// Hashes the canonical encoding, which is cached, so that capabilities with nota benes which are
+/// not [`Hash`], such as JSON values, can be used as keys.
+impl<NB: Serialize> std::hash::Hash for Capability<NB> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match UriString::try_from(self) {
+            Ok(uri) => uri.as_str().hash(state),
+            Err(_) => self.proof.hash(state),
+        }
+    }
+}
+
 impl<NB> TryFrom<&UriString> for Capability<NB>
 where
     NB: for<'a> Deserialize<'a>,
 {
     type Error = DecodingError;
     fn try_from(uri: &UriString) -> Result<Self, Self::Error> {
-        uri.as_str()
-            .strip_prefix(RESOURCE_PREFIX)
-            .ok_or_else(|| DecodingError::InvalidResourcePrefix(uri.to_string()))
-            .and_then(Capability::decode)
+        Capability::decode_with(uri, DecodingProfile::Lenient)
     }
 }
 
@@ -313,9 +969,14 @@ where
 {
     type Error = EncodingError;
     fn try_from(cap: &Capability<NB>) -> Result<Self, Self::Error> {
-        cap.encode()
+        if let Some(uri) = cap.encoded.get() {
+            return Ok(uri.clone());
+        }
+        let uri: UriString = cap
+            .encode()
             .map(|encoded| format!("{RESOURCE_PREFIX}{encoded}"))
-            .and_then(|s| s.parse().map_err(EncodingError::UriParse))
+            .and_then(|s| s.parse().map_err(EncodingError::UriParse))?;
+        Ok(cap.encoded.get_or_init(|| uri).clone())
     }
 }
 
@@ -330,6 +991,23 @@ pub enum DecodingError {
     Base64Decode(#[from] base64::DecodeError),
     #[error("failed to deserialize capability from json: {0}")]
     De(#[from] serde_json::Error),
+    #[error("capability json is not in JCS canonical form")]
+    NonCanonical,
+    #[error("capability key {0} is out of canonical order")]
+    UnorderedKey(String),
+    #[error("unknown capability field {0}")]
+    UnknownField(String),
+    #[error("note-benes of {ability} on {target} must be an array of objects")]
+    InvalidNotaBene { target: String, ability: String },
+    #[error("degenerate grant on {target:?}: {reason}")]
+    DegenerateGrant {
+        target: String,
+        reason: &'static str,
+    },
+    #[error("note-benes are nested more than {max} levels deep")]
+    NbTooDeep { max: usize },
+    #[error("note-benes exceed {max} bytes")]
+    NbTooLarge { max: usize },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -338,6 +1016,8 @@ pub enum EncodingError {
     UriParse(#[from] iri_string::validate::Error),
     #[error("failed to serialize capability to json: {0}")]
     Ser(#[from] serde_json::Error),
+    #[error("message already contains a ReCap resource at position {0}")]
+    RecapAlreadyPresent(usize),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -346,40 +1026,37 @@ pub enum VerificationError {
     Decoding(#[from] DecodingError),
     #[error("incorrect statement in siwe message, expected to end with: {0}")]
     IncorrectStatement(String),
-}
-
-struct B58Cid;
-
-impl SerializeAs<Cid> for B58Cid {
-    fn serialize_as<S>(source: &Cid, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(
-            &source
-                .to_string_of_base(cid::multibase::Base::Base58Btc)
-                .map_err(serde::ser::Error::custom)?,
-        )
-    }
-}
-
-impl<'de> DeserializeAs<'de, Cid> for B58Cid {
-    fn deserialize_as<D>(deserializer: D) -> Result<Cid, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        use std::str::FromStr;
-        let s = String::deserialize(deserializer)?;
-        if !s.starts_with('z') {
-            return Err(serde::de::Error::custom("non-base58btc encoded Cid"));
-        };
-        Cid::from_str(&s).map_err(serde::de::Error::custom)
-    }
+    #[error("statement uses {found} wording, expected {expected} wording")]
+    StatementWording {
+        found: ComplianceProfile,
+        expected: ComplianceProfile,
+    },
+    #[error("grant on {target} rejected: {source}")]
+    Registry {
+        target: UriString,
+        source: RegistryError,
+    },
+    #[error("{ability} on {target} uses a reserved namespace")]
+    ReservedNamespace { target: UriString, ability: Ability },
+    #[error("invalid ability on {target}: {source}")]
+    InvalidAbility {
+        target: UriString,
+        source: AbilityError,
+    },
+    #[error(transparent)]
+    Proof(#[from] ProofPolicyError),
+    #[error("the scheme of target {0} is not allowed")]
+    DisallowedScheme(UriString),
+    #[error("{ability} on {target} is not an allowed ability")]
+    DisallowedAbility { target: UriString, ability: Ability },
+    #[error("statement contains a ReCap statement, but the message has no ReCap resource")]
+    OrphanedStatement,
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::STATEMENT_PREAMBLE;
 
     const JSON_CAP: &str = include_str!("../tests/serialized_cap.json");
 
@@ -389,4 +1066,387 @@ mod test {
         let reser = serde_jcs::to_string(&cap).unwrap();
         assert_eq!(JSON_CAP.trim(), reser);
     }
+
+    #[test]
+    fn borrowed_lookup() {
+        let cap: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let (target, abilities) = cap.abilities().iter().next().unwrap();
+        let ability = abilities.keys().next().unwrap().to_string();
+        assert_eq!(
+            cap.can_do_str(target.as_str(), AbilityStr::parse(&ability).unwrap()),
+            cap.can_do(target, &ability.parse().unwrap())
+        );
+        assert!(cap.abilities_for_str(target.as_str()).is_some());
+        assert!(cap
+            .abilities_for_str("https://example.com/missing")
+            .is_none());
+        assert!(cap
+            .can_do_str(target.as_str(), AbilityStr::parse("missing/get").unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn wildcard_subset() {
+        let mut parent = Capability::<serde_json::Value>::default();
+        parent
+            .with_action_convert("kepler:ens:example.eth://default/kv", "kv/*", [])
+            .unwrap();
+        let mut child = Capability::<serde_json::Value>::default();
+        child
+            .with_actions_convert(
+                "kepler:ens:example.eth://default/kv",
+                [("kv/get", []), ("kv/put", [])],
+            )
+            .unwrap();
+        assert!(child.is_subset_of(&parent));
+        assert!(!parent.is_subset_of(&child));
+    }
+
+    #[test]
+    fn unrestricted_subset() {
+        let target = "kepler:ens:example.eth://default/kv";
+        let restricted: BTreeMap<String, serde_json::Value> =
+            [("prefix".to_string(), "photos/".into())].into();
+        let mut parent = Capability::<serde_json::Value>::default();
+        parent
+            .with_action_convert(target, "kv/get", [restricted.clone()])
+            .unwrap();
+        let mut child = Capability::<serde_json::Value>::default();
+        child.with_action_convert(target, "kv/get", []).unwrap();
+        assert!(!child.is_subset_of(&parent));
+        assert!(parent.is_subset_of(&child));
+
+        let mut explicit = Capability::<serde_json::Value>::default();
+        explicit
+            .with_action_convert(target, "kv/get", [BTreeMap::new()])
+            .unwrap();
+        assert!(child.is_subset_of(&explicit));
+        assert!(explicit.is_subset_of(&child));
+        assert!(!explicit.is_subset_of(&parent));
+    }
+
+    #[test]
+    fn cached_uri() {
+        let mut cap: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let uri = UriString::try_from(&cap).unwrap();
+        assert_eq!(cap.encoded.get(), Some(&uri));
+        assert_eq!(UriString::try_from(&cap.clone()).unwrap(), uri);
+
+        cap.with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        assert!(cap.encoded.get().is_none());
+        let updated = UriString::try_from(&cap).unwrap();
+        assert_ne!(updated, uri);
+        assert_eq!(
+            serde_jcs::to_string(&Capability::<serde_json::Value>::try_from(&updated).unwrap())
+                .unwrap(),
+            serde_jcs::to_string(&cap).unwrap()
+        );
+    }
+
+    #[test]
+    fn hierarchical_lookup() {
+        let mut cap = Capability::<serde_json::Value>::default();
+        cap.with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let get = AbilityStr::parse("kv/get").unwrap();
+        let (granted, _) = cap
+            .can_do_hierarchical("https://example.com/kv/public", get)
+            .unwrap();
+        assert_eq!(granted.as_str(), "https://example.com/kv");
+        assert!(cap
+            .can_do_hierarchical("https://example.com/kvstore", get)
+            .is_none());
+        assert!(cap
+            .can_do_hierarchical("https://example.com/kv/../admin", get)
+            .is_none());
+        assert!(cap
+            .can_do_hierarchical("https://example.com/kv/%2e%2e/admin", get)
+            .is_none());
+
+        // the index is rebuilt after mutation
+        cap.with_action_convert("https://example.com/kv/public", "kv/get", [])
+            .unwrap();
+        let (granted, _) = cap
+            .can_do_hierarchical("https://example.com/kv/public/photo.png", get)
+            .unwrap();
+        assert_eq!(granted.as_str(), "https://example.com/kv/public");
+    }
+
+    #[test]
+    fn statement_groups() {
+        let mut cap = Capability::<serde_json::Value>::default();
+        cap.with_actions_convert(
+            "https://example.com/kv",
+            [
+                ("kv-x/get", []),
+                ("kv/put", []),
+                ("kv/get", []),
+                ("msg/send", []),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            cap.to_statement_lines_with(ComplianceProfile::Eip5573Final),
+            [
+                "'kv': 'get', 'put' for 'https://example.com/kv'.",
+                "'kv-x': 'get' for 'https://example.com/kv'.",
+                "'msg': 'send' for 'https://example.com/kv'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_in() {
+        let mut base = Capability::<serde_json::Value>::default();
+        let mut grant = Capability::default().with_proofs(&[Cid::default()]);
+        grant
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        base.merge_in(grant.clone());
+        assert_eq!(
+            serde_jcs::to_string(&base).unwrap(),
+            serde_jcs::to_string(&grant).unwrap()
+        );
+
+        let uri = UriString::try_from(&base).unwrap();
+        base.merge_in(Capability::default());
+        assert_eq!(base.encoded.get(), Some(&uri));
+
+        let mut other = Capability::default();
+        other
+            .with_action_convert("https://example.com/kv", "kv/put", [])
+            .unwrap()
+            .with_action_convert("https://example.com/msg", "msg/send", [])
+            .unwrap();
+        base.merge_in(other.clone());
+        assert!(base.encoded.get().is_none());
+        assert_eq!(
+            serde_jcs::to_string(&base).unwrap(),
+            serde_jcs::to_string(&grant.merge::<_, serde_json::Value>(other)).unwrap()
+        );
+    }
+
+    #[test]
+    fn memory_footprint() {
+        let mut cap: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let decoded = cap.memory_footprint();
+        assert!(decoded > 0);
+        let uri = UriString::try_from(&cap).unwrap();
+        assert!(cap.memory_footprint() >= decoded + uri.as_str().len());
+
+        let json = serde_jcs::to_string(&cap).unwrap();
+        cap.shrink_to_fit();
+        assert_eq!(serde_jcs::to_string(&cap).unwrap(), json);
+        assert!(cap.memory_footprint() <= decoded + uri.as_str().len());
+    }
+
+    #[test]
+    fn recap_already_present() {
+        let mut cap = Capability::<serde_json::Value>::default();
+        cap.with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let mut message = cap.build_message(crate::test_utils::message()).unwrap();
+        message
+            .resources
+            .push("https://example.com/a".parse().unwrap());
+        assert!(matches!(
+            cap.build_message(message),
+            Err(EncodingError::RecapAlreadyPresent(0))
+        ));
+    }
+
+    #[test]
+    fn strict_decoding() {
+        let cap: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let uri = UriString::try_from(&cap).unwrap();
+        assert!(
+            Capability::<serde_json::Value>::decode_with(&uri, DecodingProfile::Strict).is_ok()
+        );
+
+        for json in [
+            r#"{"att": {}, "prf": []}"#,
+            r#"{"prf":[],"att":{}}"#,
+            r#"{"att":{"https://example.com":{"kv/get":[{"n":1.0}]}},"prf":[]}"#,
+        ] {
+            let uri: UriString = format!("{RESOURCE_PREFIX}{}", base64url::encode(json))
+                .parse()
+                .unwrap();
+            assert!(
+                Capability::<serde_json::Value>::decode_with(&uri, DecodingProfile::Lenient)
+                    .is_ok()
+            );
+            assert!(matches!(
+                Capability::<serde_json::Value>::decode_with(&uri, DecodingProfile::Strict),
+                Err(DecodingError::NonCanonical)
+            ));
+        }
+
+        let uri: UriString = format!(
+            "{RESOURCE_PREFIX}{}",
+            base64url::encode(r#"{"att":{},"exp":1,"prf":[]}"#)
+        )
+        .parse()
+        .unwrap();
+        assert!(
+            Capability::<serde_json::Value>::decode_with(&uri, DecodingProfile::Lenient).is_ok()
+        );
+        assert!(matches!(
+            Capability::<serde_json::Value>::decode_with(&uri, DecodingProfile::Strict),
+            Err(DecodingError::UnknownField(field)) if field == "exp"
+        ));
+    }
+
+    #[test]
+    fn invalid_nota_bene() {
+        for nbs in ["5", "[[{}]]", r#"[{}, "a"]"#, "{}"] {
+            let json =
+                format!(r#"{{"att":{{"https://example.com":{{"kv/get":{nbs}}}}},"prf":[]}}"#);
+            let uri: UriString = format!("{RESOURCE_PREFIX}{}", base64url::encode(json))
+                .parse()
+                .unwrap();
+            assert!(matches!(
+                Capability::<serde_json::Value>::try_from(&uri),
+                Err(DecodingError::InvalidNotaBene { target, ability })
+                    if target == "https://example.com" && ability == "kv/get"
+            ));
+        }
+    }
+
+    #[test]
+    fn decoding_limits() {
+        let nbs = r#"[{"a":{"b":[1,"]]]\"{{"]}}]"#;
+        let json = format!(r#"{{"att":{{"https://example.com":{{"kv/get":{nbs}}}}},"prf":[]}}"#);
+        let bytes = json.as_bytes();
+        assert!(DecodingLimits::new().check(bytes).is_ok());
+        assert!(DecodingLimits::new()
+            .with_max_nb_depth(4)
+            .with_max_nb_size(nbs.len())
+            .check(bytes)
+            .is_ok());
+        assert!(matches!(
+            DecodingLimits::new().with_max_nb_depth(3).check(bytes),
+            Err(DecodingError::NbTooDeep { max: 3 })
+        ));
+        assert!(matches!(
+            DecodingLimits::new()
+                .with_max_nb_size(nbs.len() - 1)
+                .check(bytes),
+            Err(DecodingError::NbTooLarge { .. })
+        ));
+
+        let deep = format!(
+            r#"{{"att":{{"https://example.com":{{"kv/get":[{{"a":{}1{}}}]}}}},"prf":[]}}"#,
+            "[".repeat(100_000),
+            "]".repeat(100_000)
+        );
+        let uri: UriString = format!("{RESOURCE_PREFIX}{}", base64url::encode(deep))
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            Capability::<serde_json::Value>::try_from(&uri),
+            Err(DecodingError::NbTooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn allowed_schemes() {
+        let message = crate::Builder::<serde_json::Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_action_convert("urn:example:kv", "kv/get", [])
+            .unwrap()
+            .build(crate::test_utils::message())
+            .unwrap();
+        let options = crate::VerificationOptions::new();
+        assert!(Capability::<serde_json::Value>::extract_and_verify_with(
+            &message,
+            &options.clone().with_allowed_schemes(["HTTPS", "urn"])
+        )
+        .is_ok());
+        assert!(matches!(
+            Capability::<serde_json::Value>::extract_and_verify_with(
+                &message,
+                &options.with_allowed_schemes(["kepler", "https"])
+            ),
+            Err(VerificationError::DisallowedScheme(target)) if target.as_str() == "urn:example:kv"
+        ));
+
+        let builder = crate::Builder::<serde_json::Value>::new()
+            .with_allowed_schemes(["kepler"])
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        assert!(builder.validate().is_err());
+    }
+
+    #[test]
+    fn degenerate_grants() {
+        let json = r#"{"att":{"https://example.com":{}},"prf":[]}"#;
+        let uri: UriString = format!("{RESOURCE_PREFIX}{}", base64url::encode(json))
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            Capability::<serde_json::Value>::try_from(&uri),
+            Err(DecodingError::DegenerateGrant { target, .. }) if target == "https://example.com"
+        ));
+
+        let mut message = crate::test_utils::message();
+        message.statement = Some(STATEMENT_PREAMBLE.into());
+        message.resources.push(uri);
+        assert!(Capability::<serde_json::Value>::extract_and_verify(&message).is_err());
+        assert!(Capability::<serde_json::Value>::extract_and_verify_with(
+            &message,
+            &crate::VerificationOptions::new().allow_degenerate_grants()
+        )
+        .unwrap()
+        .is_some());
+    }
+
+    #[test]
+    fn large_grant_counts() {
+        let mut cap = Capability::<serde_json::Value>::new();
+        for i in 0..5000 {
+            cap.with_action_convert(
+                format!("https://example.com/{i}"),
+                format!("kv/get{}", i % 7).as_str(),
+                [],
+            )
+            .unwrap();
+        }
+        for profile in ComplianceProfile::ALL {
+            let statement = cap.to_statement_with(profile);
+            assert_eq!(cap.statement_len(profile), statement.len());
+            assert!(statement.contains(" (5000) "));
+        }
+        assert!(matches!(
+            crate::Builder::from(cap.clone())
+                .with_max_statement_length(1000)
+                .build(crate::test_utils::message())
+                .unwrap_err()
+                .errors(),
+            [crate::BuildError::StatementTooLong { max: 1000, .. }]
+        ));
+        assert_eq!(
+            cap.to_plain_language("the app")
+                .matches("Allow the app")
+                .count(),
+            5000
+        );
+    }
+
+    #[test]
+    fn equality() {
+        let cap: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let same: Capability<serde_json::Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let _ = UriString::try_from(&same).unwrap();
+        assert_eq!(cap, same);
+        let mut json: serde_json::Value = serde_json::from_str(JSON_CAP).unwrap();
+        json["prf"] = serde_json::json!([]);
+        let proofless: Capability<serde_json::Value> = serde_json::from_value(json).unwrap();
+        assert_ne!(cap, proofless);
+        assert_eq!(
+            std::collections::HashSet::from([cap, same, proofless]).len(),
+            2
+        );
+    }
 }
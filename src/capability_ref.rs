@@ -0,0 +1,342 @@
+use crate::adapters::parse_b58_cid;
+use crate::capability::{
+    check_payload, check_statement, degenerate_reason, invalid_nota_bene, namespace_runs,
+    push_statement,
+};
+use crate::{
+    base64url, has_orphaned_statement, AbilityStr, AttOrdering, ComplianceProfile, DecodingError,
+    DecodingProfile, SignInMessage, VerificationError, VerificationOptions, RESOURCE_PREFIX,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use iri_string::types::UriStr;
+use serde::de::Error as _;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+/// A borrowed view of the capabilities of a message, for middleware which only needs to look up a
+/// few grants per request.
+///
+/// The ReCap is decoded into a buffer provided by the caller, which can be reused across requests,
+/// and targets, abilities and proofs borrow from it, with note-benes left as raw JSON, so that no
+/// owned copies of the grants are made.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CapabilityRef<'b> {
+    #[serde(borrow, rename = "att")]
+    attenuations: BTreeMap<&'b str, BTreeMap<AbilityStr<'b>, &'b RawValue>>,
+    #[serde(borrow, rename = "prf")]
+    proof: Vec<&'b str>,
+}
+
+impl<'b> CapabilityRef<'b> {
+    /// Extract the capabilities of a message into the buffer, ensuring the correctness of the
+    /// statement as [`Capability::extract_and_verify`](crate::Capability::extract_and_verify) does.
+    pub fn extract_ref<M: SignInMessage + ?Sized>(
+        message: &M,
+        buffer: &'b mut Vec<u8>,
+    ) -> Result<Option<Self>, VerificationError> {
+        Self::extract_ref_with(message, buffer, &VerificationOptions::default())
+    }
+
+    /// Extract the capabilities of a message into the buffer, decoding and checking the statement
+    /// with the decoding profile, limits, key ordering, degenerate grant allowance and compliance
+    /// profile of the options, as [`Capability::extract_and_verify_with`](crate::Capability::extract_and_verify_with) does.
+    ///
+    /// The options' policies on the grants themselves, such as the namespace registry, are not
+    /// applied, as they need owned capabilities.
+    pub fn extract_ref_with<M: SignInMessage + ?Sized>(
+        message: &M,
+        buffer: &'b mut Vec<u8>,
+        options: &VerificationOptions,
+    ) -> Result<Option<Self>, VerificationError> {
+        let profile = options.decoding_profile();
+        let Some(encoded) = message
+            .resources()
+            .last()
+            .and_then(|u| u.strip_prefix(RESOURCE_PREFIX))
+        else {
+            return match profile == DecodingProfile::Strict && has_orphaned_statement(message) {
+                true => Err(VerificationError::OrphanedStatement),
+                false => Ok(None),
+            };
+        };
+        let capability = Self::decode(encoded, buffer, options)?;
+        check_statement(message, options.compliance_profile(), |p| {
+            capability.to_statement_with(p)
+        })?;
+        Ok(Some(capability))
+    }
+
+    fn decode(
+        encoded: &str,
+        buffer: &'b mut Vec<u8>,
+        options: &VerificationOptions,
+    ) -> Result<Self, DecodingError> {
+        buffer.clear();
+        buffer.resize(encoded.len().div_ceil(4) * 3, 0);
+        let len = base64url::decode_slice(encoded, buffer)?;
+        buffer.truncate(len);
+        check_payload(
+            buffer,
+            options.decoding_profile(),
+            options.decoding_limits(),
+        )?;
+        if options.requires_ordered_keys() {
+            AttOrdering::check(buffer)?;
+        }
+        let capability: Self = serde_json::from_slice(buffer)
+            .map_err(|error| invalid_nota_bene(buffer).unwrap_or(DecodingError::De(error)))?;
+        check_raw_grants(
+            capability
+                .attenuations
+                .iter()
+                .map(|(target, abilities)| (*target, abilities.iter().map(|(a, nb)| (a, *nb)))),
+            &capability.proof,
+            options,
+        )?;
+        Ok(capability)
+    }
+
+    /// The targets of the granted abilities.
+    pub fn targets(&self) -> impl Iterator<Item = &'b str> + '_ {
+        self.attenuations.keys().copied()
+    }
+
+    /// The abilities granted for a target, along with their raw JSON note-benes.
+    pub fn abilities_for(&self, target: &str) -> Option<&BTreeMap<AbilityStr<'b>, &'b RawValue>> {
+        self.attenuations.get(target)
+    }
+
+    /// The raw JSON note-benes of an ability granted for a target, if granted.
+    pub fn can_do(&self, target: &str, ability: AbilityStr<'_>) -> Option<&'b RawValue> {
+        let abilities: &BTreeMap<AbilityStr<'_>, &'b RawValue> = self.abilities_for(target)?;
+        abilities.get(&ability).copied()
+    }
+
+    /// The CIDs of the proofs, as encoded, which are checked to be base58btc CIDs.
+    pub fn proof(&self) -> &[&'b str] {
+        &self.proof
+    }
+
+    /// Generate the ReCap statement of the capabilities.
+    pub fn to_statement(&self) -> String {
+        self.to_statement_with(ComplianceProfile::Eip5573Final)
+    }
+
+    /// Generate the ReCap statement of the capabilities in the wording of the given compliance profile.
+    pub fn to_statement_with(&self, profile: ComplianceProfile) -> String {
+        let mut statement = String::new();
+        push_statement(
+            &mut statement,
+            profile,
+            self.attenuations.iter().flat_map(|(target, abilities)| {
+                namespace_runs(abilities.keys(), |ability| ability.namespace())
+                    .map(|(namespace, run)| (*target, namespace, run.map(|ability| ability.name())))
+            }),
+        );
+        statement
+    }
+}
+
+/// Check capabilities which were decoded with their note-benes left as raw JSON, given each target
+/// with its abilities, as deserializing [`Capability`](crate::Capability) would: targets must be
+/// URIs, note-benes arrays of objects and proofs base58btc CIDs, and no grant may be degenerate
+/// unless the options allow it.
+pub(crate) fn check_raw_grants<'g, G, A, D>(
+    attenuations: G,
+    proof: &[&str],
+    options: &VerificationOptions,
+) -> Result<(), DecodingError>
+where
+    G: Iterator<Item = (&'g str, A)> + Clone,
+    A: Iterator<Item = (D, &'g RawValue)>,
+    D: fmt::Display,
+{
+    if let Some((target, _)) = attenuations
+        .clone()
+        .find(|(target, _)| UriStr::new(target).is_err())
+    {
+        return Err(DecodingError::De(serde_json::Error::custom(format!(
+            "invalid target {target}"
+        ))));
+    }
+    for proof in proof {
+        parse_b58_cid(proof).map_err(DecodingError::De)?;
+    }
+    // note-benes are borrowed as raw JSON, so their shape is not checked when deserializing
+    for (target, mut abilities) in attenuations.clone() {
+        if let Some((ability, _)) = abilities.find(|(_, nbs)| !is_object_array(nbs)) {
+            return Err(DecodingError::InvalidNotaBene {
+                target: target.to_string(),
+                ability: ability.to_string(),
+            });
+        }
+    }
+    if options.allows_degenerate_grants() {
+        return Ok(());
+    }
+    // abilities are parsed when deserializing, so their segments are never empty
+    match attenuations.into_iter().find_map(|(target, abilities)| {
+        degenerate_reason(target, abilities.map(|_| false)).map(|reason| (target, reason))
+    }) {
+        Some((target, reason)) => Err(DecodingError::DegenerateGrant {
+            target: target.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Whether raw note-benes are an array of objects, as EIP-5573 requires.
+fn is_object_array(nbs: &RawValue) -> bool {
+    serde_json::from_str::<Vec<&RawValue>>(nbs.get())
+        .is_ok_and(|nbs| nbs.iter().all(|nb| nb.get().starts_with('{')))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::message;
+    use crate::{Builder, Capability};
+    use serde_json::Value;
+
+    #[test]
+    fn extract_ref() {
+        let mut message = Builder::<Value>::new()
+            .with_action_convert(
+                "https://example.com/kv",
+                "kv/get",
+                [[("key".to_string(), Value::from("a"))].into()],
+            )
+            .unwrap()
+            .with_action_convert("https://example.com/kv", "kv-x/put", [])
+            .unwrap()
+            .with_action_convert("https://example.com/msg", "msg/send", [])
+            .unwrap()
+            .build(message())
+            .unwrap();
+        let owned = Capability::<Value>::extract_and_verify(&message)
+            .unwrap()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        let capability = CapabilityRef::extract_ref(&message, &mut buffer)
+            .unwrap()
+            .unwrap();
+        assert_eq!(capability.to_statement(), owned.to_statement());
+        assert_eq!(
+            capability
+                .can_do(
+                    "https://example.com/kv",
+                    AbilityStr::parse("kv/get").unwrap()
+                )
+                .unwrap()
+                .get(),
+            r#"[{"key":"a"}]"#
+        );
+        assert!(capability
+            .can_do(
+                "https://example.com/msg",
+                AbilityStr::parse("kv/get").unwrap()
+            )
+            .is_none());
+        assert!(capability.proof().is_empty());
+
+        message.statement = Some("I".into());
+        assert!(matches!(
+            CapabilityRef::extract_ref(&message, &mut buffer),
+            Err(VerificationError::IncorrectStatement(_))
+        ));
+        message.resources.clear();
+        assert!(CapabilityRef::extract_ref(&message, &mut buffer)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn owned_parity() {
+        let with_payload = |json: &str| {
+            let mut message = message();
+            message.statement = Some(crate::STATEMENT_PREAMBLE.into());
+            message.resources.push(
+                format!("{RESOURCE_PREFIX}{}", base64url::encode(json))
+                    .parse()
+                    .unwrap(),
+            );
+            message
+        };
+        let mut buffer = Vec::new();
+
+        let degenerate = with_payload(r#"{"att":{"https://example.com":{}},"prf":[]}"#);
+        assert!(matches!(
+            CapabilityRef::extract_ref(&degenerate, &mut buffer),
+            Err(VerificationError::Decoding(
+                DecodingError::DegenerateGrant { .. }
+            ))
+        ));
+        assert!(CapabilityRef::extract_ref_with(
+            &degenerate,
+            &mut buffer,
+            &VerificationOptions::new().allow_degenerate_grants()
+        )
+        .unwrap()
+        .is_some());
+
+        let mut invalid_nb =
+            with_payload(r#"{"att":{"https://example.com":{"kv/get":{}}},"prf":[]}"#);
+        invalid_nb.statement = None;
+        assert!(matches!(
+            CapabilityRef::extract_ref(&invalid_nb, &mut buffer),
+            Err(VerificationError::Decoding(
+                DecodingError::InvalidNotaBene { .. }
+            ))
+        ));
+
+        let garbage_proof =
+            with_payload(r#"{"att":{"https://example.com":{"kv/get":[]}},"prf":["garbage"]}"#);
+        assert!(matches!(
+            CapabilityRef::extract_ref(&garbage_proof, &mut buffer),
+            Err(VerificationError::Decoding(DecodingError::De(_)))
+        ));
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify(&garbage_proof),
+            Err(VerificationError::Decoding(DecodingError::De(_)))
+        ));
+
+        let deep =
+            with_payload(r#"{"att":{"https://example.com":{"kv/get":[{"a":[[]]}]}},"prf":[]}"#);
+        assert!(matches!(
+            CapabilityRef::extract_ref_with(
+                &deep,
+                &mut buffer,
+                &VerificationOptions::new()
+                    .with_decoding_limits(crate::DecodingLimits::new().with_max_nb_depth(2))
+            ),
+            Err(VerificationError::Decoding(DecodingError::NbTooDeep {
+                max: 2
+            }))
+        ));
+
+        let legacy = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_compliance_profile(ComplianceProfile::SpruceLegacy)
+            .build(message())
+            .unwrap();
+        assert!(matches!(
+            CapabilityRef::extract_ref(&legacy, &mut buffer),
+            Err(VerificationError::StatementWording {
+                found: ComplianceProfile::SpruceLegacy,
+                ..
+            })
+        ));
+        assert!(CapabilityRef::extract_ref_with(
+            &legacy,
+            &mut buffer,
+            &VerificationOptions::new().with_compliance_profile(ComplianceProfile::SpruceLegacy)
+        )
+        .unwrap()
+        .is_some());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::STATEMENT_PREAMBLE;
+use std::fmt;
+
+/// The stage of the ReCap specification which messages are produced and consumed for, so that one
+/// build can interoperate with ecosystems which have not yet moved to the final EIP-5573 format.
+///
+/// Only stages whose exact wording is known from messages they produced are supported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ComplianceProfile {
+    /// Final EIP-5573: statement lines group the abilities of each namespace, with namespaces,
+    /// abilities and targets in single quotes.
+    #[default]
+    Eip5573Final,
+    /// Messages produced by early SpruceID releases, which authorize "the state URI" and put
+    /// namespaces, abilities and targets in double quotes, as in the README example of version 0.2.
+    SpruceLegacy,
+}
+
+impl ComplianceProfile {
+    /// Every profile, from the most to the least recent.
+    pub const ALL: [Self; 2] = [Self::Eip5573Final, Self::SpruceLegacy];
+
+    /// The name of the spec revision, e.g. `spruce-legacy`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Eip5573Final => "eip-5573",
+            Self::SpruceLegacy => "spruce-legacy",
+        }
+    }
+
+    /// The authorization sentence opening the ReCap statement in this revision.
+    pub const fn preamble(self) -> &'static str {
+        match self {
+            Self::Eip5573Final => STATEMENT_PREAMBLE,
+            Self::SpruceLegacy => {
+                "I further authorize the state URI to perform the following actions on my behalf:"
+            }
+        }
+    }
+
+    /// Find the profile whose wording a statement ends with, given the statements each profile
+    /// generates for the same capabilities.
+    pub(crate) fn detect(statement: &str, generate: impl Fn(Self) -> String) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| statement.ends_with(&generate(*profile)))
+    }
+
+    /// The quote around namespaces, abilities and targets in statement lines.
+    pub(crate) fn quote(self) -> char {
+        match self {
+            Self::Eip5573Final => '\'',
+            Self::SpruceLegacy => '"',
+        }
+    }
+}
+
+impl fmt::Display for ComplianceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Capability, VerificationError, VerificationOptions};
+    use serde_json::Value;
+    use siwe::Message;
+
+    /// The message of the README example of version 0.2.
+    const LEGACY_MESSAGE: &str = "example.com wants you to sign in with your Ethereum account:
+0x0000000000000000000000000000000000000000
+
+I further authorize the state URI to perform the following actions on my behalf: (1) \"kv\": \"get\", \"list\", \"metadata\" for \"kepler:ens:example.eth://default/kv\". (2) \"kv\": \"delete\", \"get\", \"list\", \"metadata\", \"put\" for \"kepler:ens:example.eth://default/kv/dapp-space\". (3) \"kv\": \"delete\", \"get\", \"list\", \"metadata\", \"put\" for \"kepler:ens:example.eth://default/kv/public\". (4) \"credential\": \"present\" for \"urn:credential:type:type1\".
+
+URI: did:key:example
+Version: 1
+Chain ID: 1
+Nonce: mynonce1
+Issued At: 2022-06-21T12:00:00.000Z
+Resources:
+- urn:recap:eyJhdHQiOnsia2VwbGVyOmVuczpleGFtcGxlLmV0aDovL2RlZmF1bHQva3YiOnsia3YvZ2V0IjpbXSwia3YvbGlzdCI6W10sImt2L21ldGFkYXRhIjpbXX0sImtlcGxlcjplbnM6ZXhhbXBsZS5ldGg6Ly9kZWZhdWx0L2t2L2RhcHAtc3BhY2UiOnsia3YvZGVsZXRlIjpbXSwia3YvZ2V0IjpbXSwia3YvbGlzdCI6W10sImt2L21ldGFkYXRhIjpbXSwia3YvcHV0IjpbXX0sImtlcGxlcjplbnM6ZXhhbXBsZS5ldGg6Ly9kZWZhdWx0L2t2L3B1YmxpYyI6eyJrdi9kZWxldGUiOltdLCJrdi9nZXQiOltdLCJrdi9saXN0IjpbXSwia3YvbWV0YWRhdGEiOltdLCJrdi9wdXQiOltdfSwidXJuOmNyZWRlbnRpYWw6dHlwZTp0eXBlMSI6eyJjcmVkZW50aWFsL3ByZXNlbnQiOltdfX0sInByZiI6W119";
+
+    #[test]
+    fn legacy_fixture() {
+        let legacy: Message = LEGACY_MESSAGE.parse().unwrap();
+        let options =
+            VerificationOptions::new().with_compliance_profile(ComplianceProfile::SpruceLegacy);
+        let capability = Capability::<Value>::extract_and_verify_with(&legacy, &options)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify(&legacy),
+            Err(VerificationError::StatementWording {
+                found: ComplianceProfile::SpruceLegacy,
+                expected: ComplianceProfile::Eip5573Final,
+            })
+        ));
+
+        let mut unsigned = legacy.clone();
+        unsigned.statement = None;
+        unsigned.resources.clear();
+        assert_eq!(
+            capability
+                .build_message_with(unsigned, ComplianceProfile::SpruceLegacy)
+                .unwrap()
+                .to_string(),
+            LEGACY_MESSAGE
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{delegator_did, Capability, VerifiedSession};
+
+use serde::{Deserialize, Serialize};
+
+const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+const CREDENTIAL_TYPE: &str = "ReCapDelegation";
+const EVIDENCE_TYPE: &str = "SiweMessage";
+
+/// An unsigned W3C Verifiable Credential representing a verified delegation: the issuer is the
+/// `did:pkh` of the delegator, the subject is the delegee with the granted capabilities, and the
+/// evidence is the signed SIWE message.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationCredential<NB> {
+    #[serde(rename = "@context")]
+    context: Vec<String>,
+    #[serde(rename = "type")]
+    types: Vec<String>,
+    issuer: String,
+    issuance_date: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    expiration_date: Option<String>,
+    credential_subject: CredentialSubject<NB>,
+    evidence: Vec<SiweEvidence>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CredentialSubject<NB> {
+    id: String,
+    #[serde(flatten)]
+    capability: Capability<NB>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SiweEvidence {
+    #[serde(rename = "type")]
+    types: Vec<String>,
+    message: String,
+    signature: String,
+}
+
+impl<NB> DelegationCredential<NB> {
+    /// The `did:pkh` of the delegator.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// The URI of the delegee.
+    pub fn subject(&self) -> &str {
+        &self.credential_subject.id
+    }
+
+    /// The delegated capabilities.
+    pub fn capability(&self) -> &Capability<NB> {
+        &self.credential_subject.capability
+    }
+
+    /// The signed SIWE message and its `0x`-prefixed hex signature.
+    pub fn evidence(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.evidence
+            .iter()
+            .map(|evidence| (evidence.message.as_str(), evidence.signature.as_str()))
+    }
+}
+
+impl<NB> VerifiedSession<NB>
+where
+    NB: Clone,
+{
+    /// Export the delegation as a Verifiable Credential, for systems which consume VCs.
+    pub fn to_credential(&self) -> DelegationCredential<NB> {
+        let message = self.message();
+        let signature: String = self
+            .signature()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        DelegationCredential {
+            context: vec![CREDENTIALS_CONTEXT.to_string()],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                CREDENTIAL_TYPE.to_string(),
+            ],
+            issuer: delegator_did(message),
+            issuance_date: message.issued_at.to_string(),
+            expiration_date: message.expiration_time.as_ref().map(ToString::to_string),
+            credential_subject: CredentialSubject {
+                id: message.uri.to_string(),
+                capability: self.capability().clone(),
+            },
+            evidence: vec![SiweEvidence {
+                types: vec![EVIDENCE_TYPE.to_string()],
+                message: message.to_string(),
+                signature: format!("0x{signature}"),
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::verified_session;
+    use crate::Builder;
+    use serde_json::Value;
+
+    #[test]
+    fn credential() {
+        let session = verified_session(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+        let message = session.message();
+
+        let credential = serde_json::to_value(session.to_credential()).unwrap();
+        assert_eq!(credential["issuer"], delegator_did(message));
+        assert_eq!(credential["issuanceDate"], "2022-06-21T12:00:00.000Z");
+        assert_eq!(credential["expirationDate"], "2022-06-21T13:00:00.000Z");
+        assert_eq!(credential["credentialSubject"]["id"], "did:key:example");
+        assert_eq!(
+            credential["credentialSubject"]["att"],
+            serde_json::json!({"https://example.com/kv": {"kv/get": [{}]}})
+        );
+
+        let credential: DelegationCredential<Value> = serde_json::from_value(credential).unwrap();
+        let (evidence, _) = credential.evidence().next().unwrap();
+        assert_eq!(&evidence.parse::<siwe::Message>().unwrap(), message);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::VerifiedSession;
+use std::future::Future;
+
+use serde::Deserialize;
+use siwe::{eip55, Message};
+
+const PKH_PREFIX: &str = "did:pkh:eip155:";
+
+/// The `did:pkh:eip155:<chain_id>:<address>` DID of the signer of a SIWE message, with the address
+/// in EIP-55 checksum form, as used by UCAN and CACAO representations of the delegation.
+pub fn delegator_did(message: &Message) -> String {
+    format!(
+        "{PKH_PREFIX}{}:{}",
+        message.chain_id,
+        eip55(&message.address)
+    )
+}
+
+/// Check that a `did:pkh` DID identifies the signer of a SIWE message.
+///
+/// The address is compared case-insensitively, so DIDs with lowercase addresses are accepted.
+pub fn check_delegator_did(message: &Message, did: &str) -> Result<(), DidError> {
+    let (chain_id, address) = parse_delegator_did(did)?;
+    if chain_id != message.chain_id || address != message.address {
+        return Err(DidError::Mismatch {
+            expected: delegator_did(message),
+            found: did.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Parse the chain id and address of a `did:pkh:eip155` DID.
+pub(crate) fn parse_delegator_did(did: &str) -> Result<(u64, [u8; 20]), DidError> {
+    let (chain_id, address) = did
+        .strip_prefix(PKH_PREFIX)
+        .and_then(|account| account.split_once(':'))
+        .ok_or_else(|| DidError::NotPkh(did.to_string()))?;
+    let chain_id: u64 = chain_id
+        .parse()
+        .map_err(|_| DidError::InvalidChainId(chain_id.to_string()))?;
+    let address =
+        parse_address(address).ok_or_else(|| DidError::InvalidAddress(address.to_string()))?;
+    Ok((chain_id, address))
+}
+
+fn parse_address(address: &str) -> Option<[u8; 20]> {
+    crate::hex::decode_array(address.strip_prefix("0x")?)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+struct VerificationMethod {
+    id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum VerificationRelationship {
+    Reference(String),
+    Embedded(VerificationMethod),
+}
+
+/// The parts of a DID document needed to check which keys it authorizes.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    id: String,
+    #[serde(default)]
+    authentication: Vec<VerificationRelationship>,
+}
+
+impl DidDocument {
+    /// Create a document authorizing the given verification methods, identified by DID URLs
+    /// such as `did:web:example.com#key-1` or fragments such as `#key-1`.
+    pub fn new<M>(id: impl Into<String>, authentication: M) -> Self
+    where
+        M: IntoIterator,
+        M::Item: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            authentication: authentication
+                .into_iter()
+                .map(|method| VerificationRelationship::Reference(method.into()))
+                .collect(),
+        }
+    }
+
+    /// The DID of the document.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Check whether the key identified by a DID URL is authorized for authentication.
+    pub fn authorizes(&self, key: &str) -> bool {
+        self.authentication.iter().any(|relationship| {
+            let id = match relationship {
+                VerificationRelationship::Reference(id) => id,
+                VerificationRelationship::Embedded(method) => &method.id,
+            };
+            match id.strip_prefix('#') {
+                Some(fragment) => {
+                    key.strip_prefix(self.id.as_str())
+                        .and_then(|rest| rest.strip_prefix('#'))
+                        == Some(fragment)
+                }
+                None => id == key,
+            }
+        })
+    }
+}
+
+/// Resolves DIDs, e.g. `did:key`, `did:pkh` or `did:web`, to their documents.
+pub trait DidResolver {
+    type Error;
+
+    /// Resolve the DID to its document.
+    fn resolve(&self, did: &str) -> impl Future<Output = Result<DidDocument, Self::Error>>;
+}
+
+/// Resolves `did:key` DIDs without network access, to a document authorizing only the key itself.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DidKeyResolver;
+
+impl DidResolver for DidKeyResolver {
+    type Error = DidError;
+
+    fn resolve(&self, did: &str) -> impl Future<Output = Result<DidDocument, Self::Error>> {
+        std::future::ready(match did.strip_prefix("did:key:") {
+            Some(key) if !key.is_empty() && !key.contains(['#', '/', '?']) => {
+                Ok(DidDocument::new(did, [format!("{did}#{key}")]))
+            }
+            _ => Err(DidError::Unsupported(did.to_string())),
+        })
+    }
+}
+
+impl<NB> VerifiedSession<NB> {
+    /// Check that the key presenting the session, identified by a DID URL, is authorized by the
+    /// DID document of the message `uri`, i.e. the delegee.
+    pub async fn verify_delegee<R>(
+        &self,
+        key: &str,
+        resolver: &R,
+    ) -> Result<(), DelegeeError<R::Error>>
+    where
+        R: DidResolver,
+    {
+        let did = self.message().uri.as_str();
+        if !did.starts_with("did:") {
+            return Err(DelegeeError::NotDid(did.to_string()));
+        }
+        let document = resolver
+            .resolve(did)
+            .await
+            .map_err(DelegeeError::Resolution)?;
+        if document.id() != did || !document.authorizes(key) {
+            return Err(DelegeeError::Unauthorized {
+                did: did.to_string(),
+                key: key.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DelegeeError<E> {
+    #[error("the delegee {0} is not a DID")]
+    NotDid(String),
+    #[error("failed to resolve the delegee: {0}")]
+    Resolution(E),
+    #[error("{key} is not authorized by the delegee {did}")]
+    Unauthorized { did: String, key: String },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DidError {
+    #[error("expected a did:pkh:eip155 DID, found {0}")]
+    NotPkh(String),
+    #[error("invalid chain id {0}")]
+    InvalidChainId(String),
+    #[error("invalid address {0}")]
+    InvalidAddress(String),
+    #[error("DID {found} does not identify the signer {expected}")]
+    Mismatch { expected: String, found: String },
+    #[error("unsupported DID {0}")]
+    Unsupported(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SessionHeader;
+
+    #[test]
+    fn pkh() {
+        let mut message = crate::test_utils::message();
+        message.address = [
+            0x5a, 0xae, 0xb6, 0x05, 0x3f, 0x3e, 0x94, 0xc9, 0xb9, 0xa0, 0x9f, 0x33, 0x66, 0x94,
+            0x35, 0xe7, 0xef, 0x1b, 0xea, 0xed,
+        ];
+        let did = delegator_did(&message);
+        assert_eq!(
+            did,
+            "did:pkh:eip155:1:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert!(check_delegator_did(&message, &did).is_ok());
+        assert!(check_delegator_did(&message, &did.to_lowercase()).is_ok());
+        assert!(matches!(
+            check_delegator_did(
+                &message,
+                "did:pkh:eip155:5:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            ),
+            Err(DidError::Mismatch { .. })
+        ));
+        assert!(matches!(
+            check_delegator_did(&message, "did:key:z6Mk"),
+            Err(DidError::NotPkh(_))
+        ));
+        assert!(matches!(
+            check_delegator_did(&message, "did:pkh:eip155:1:0x5aAe"),
+            Err(DidError::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            check_delegator_did(
+                &message,
+                "did:pkh:eip155:1:0x+aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            ),
+            Err(DidError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn did_document() {
+        let document: DidDocument = serde_json::from_str(
+            r##"{
+                "id": "did:web:example.com",
+                "verificationMethod": [{"id": "did:web:example.com#key-1"}, {"id": "#key-2"}],
+                "authentication": ["#key-1", {"id": "did:web:example.com#key-3"}]
+            }"##,
+        )
+        .unwrap();
+        assert!(document.authorizes("did:web:example.com#key-1"));
+        assert!(document.authorizes("did:web:example.com#key-3"));
+        assert!(!document.authorizes("did:web:example.com#key-2"));
+        assert!(!document.authorizes("did:web:example.org#key-1"));
+    }
+
+    #[tokio::test]
+    async fn delegee() {
+        let (message, signature) = crate::test_utils::sign(crate::test_utils::message());
+        let session = SessionHeader::new(message, signature)
+            .verify::<serde_json::Value>(&crate::test_utils::verification_options())
+            .unwrap();
+
+        assert!(matches!(
+            session
+                .verify_delegee("did:key:other", &DidKeyResolver)
+                .await,
+            Err(DelegeeError::Unauthorized { .. })
+        ));
+        assert!(session
+            .verify_delegee("did:key:example#example", &DidKeyResolver)
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::Capability;
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::Serialize;
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const GREEN: &str = "\x1b[32m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Indented tree rendering of a capability set (targets → abilities → nota benes), for CLI tools and debugging.
+///
+/// This is what `{:#}` uses for [`Capability`]; construct it via [`Capability::tree`] to enable colors.
+#[derive(Clone, Copy, Debug)]
+pub struct TreeDisplay<'a, NB> {
+    capability: &'a Capability<NB>,
+    colored: bool,
+    max_width: Option<usize>,
+}
+
+impl<'a, NB> TreeDisplay<'a, NB> {
+    /// Enable or disable ANSI colors in the output.
+    pub fn colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// Elide the middle of targets, abilities, nota benes and proofs longer than `max` characters.
+    pub fn truncated(mut self, max: usize) -> Self {
+        self.max_width = Some(max);
+        self
+    }
+
+    fn paint(&self, f: &mut fmt::Formatter<'_>, color: &str, text: &str) -> fmt::Result {
+        let text = match self.max_width {
+            Some(max) => elide_middle(text, max),
+            None => Cow::Borrowed(text),
+        };
+        if self.colored {
+            write!(f, "{color}{text}{RESET}")
+        } else {
+            f.write_str(&text)
+        }
+    }
+}
+
+/// Shorten text to at most `max` characters by replacing its middle with an ellipsis, e.g.
+/// `kepler:ens:examp…/kv/public`.
+pub fn elide_middle(text: &str, max: usize) -> Cow<'_, str> {
+    let count = text.chars().count();
+    if count <= max {
+        return Cow::Borrowed(text);
+    }
+    let kept = max.saturating_sub(1);
+    let tail = kept / 2;
+    let head: String = text.chars().take(kept - tail).collect();
+    let tail: String = text.chars().skip(count - tail).collect();
+    match max {
+        0 => Cow::Borrowed(""),
+        _ => Cow::Owned(format!("{head}…{tail}")),
+    }
+}
+
+fn branch(last: bool) -> (&'static str, &'static str) {
+    if last {
+        ("└── ", "    ")
+    } else {
+        ("├── ", "│   ")
+    }
+}
+
+impl<'a, NB> fmt::Display for TreeDisplay<'a, NB>
+where
+    NB: Serialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (target, abilities) in self.capability.abilities() {
+            self.paint(f, BOLD, target.as_str())?;
+            writeln!(f)?;
+            let count = abilities.len();
+            for (i, (ability, nbs)) in abilities.iter().enumerate() {
+                let (head, indent) = branch(i + 1 == count);
+                f.write_str(head)?;
+                self.paint(f, CYAN, &ability.to_string())?;
+                writeln!(f)?;
+                let nbs: Vec<String> = nbs
+                    .iter()
+                    .filter(|nb| !nb.is_empty())
+                    .map(|nb| serde_json::to_string(nb).map_err(|_| fmt::Error))
+                    .collect::<Result<_, _>>()?;
+                for (j, nb) in nbs.iter().enumerate() {
+                    f.write_str(indent)?;
+                    f.write_str(branch(j + 1 == nbs.len()).0)?;
+                    self.paint(f, DIM, nb)?;
+                    writeln!(f)?;
+                }
+            }
+        }
+        let proofs = self.capability.proof();
+        if !proofs.is_empty() {
+            self.paint(f, BOLD, "proofs")?;
+            writeln!(f)?;
+            for (i, proof) in proofs.iter().enumerate() {
+                f.write_str(branch(i + 1 == proofs.len()).0)?;
+                self.paint(f, GREEN, &proof.to_string())?;
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// Render this capability set as an indented tree, uncolored by default.
+    pub fn tree(&self) -> TreeDisplay<'_, NB> {
+        TreeDisplay {
+            capability: self,
+            colored: false,
+            max_width: None,
+        }
+    }
+}
+
+/// Displays a summary of the granted capabilities, with a line listing the abilities on each
+/// target and a line listing the proofs, or with `{:#}` an indented tree which also shows the
+/// nota benes. The ReCap statement is given by [`Capability::to_statement`].
+impl<NB> fmt::Display for Capability<NB>
+where
+    NB: Serialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.tree().fmt(f);
+        }
+        for (target, abilities) in self.abilities() {
+            write!(f, "{target}:")?;
+            for (i, ability) in abilities.keys().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(f, "{separator}{ability}")?;
+            }
+            writeln!(f)?;
+        }
+        if !self.proof().is_empty() {
+            f.write_str("proofs:")?;
+            for (i, proof) in self.proof().iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(f, "{separator}{proof}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn tree() {
+        let mut cap = Capability::<Value>::default();
+        cap.with_actions_convert(
+            "kepler:ens:example.eth://default/kv",
+            [
+                ("kv/get", vec![]),
+                (
+                    "kv/put",
+                    vec![BTreeMap::from([("max_size".to_string(), Value::from(10))])],
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{cap:#}"),
+            "kepler:ens:example.eth://default/kv\n├── kv/get\n└── kv/put\n    └── {\"max_size\":10}\n"
+        );
+        assert_eq!(
+            cap.to_string(),
+            "kepler:ens:example.eth://default/kv: kv/get, kv/put\n"
+        );
+        let proof = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            cap.clone().with_proof(&proof).to_string(),
+            format!("kepler:ens:example.eth://default/kv: kv/get, kv/put\nproofs: {proof}\n")
+        );
+        assert!(cap.tree().colored(true).to_string().contains(CYAN));
+        assert_eq!(
+            cap.tree().truncated(16).to_string(),
+            "kepler:e…ault/kv\n├── kv/get\n└── kv/put\n    └── {\"max_size\":10}\n"
+        );
+    }
+
+    #[test]
+    fn elision() {
+        assert_eq!(
+            elide_middle("kepler:ens:example.eth://default/kv/public", 27),
+            "kepler:ens:ex…ult/kv/public"
+        );
+        assert_eq!(elide_middle("kv/get", 6), "kv/get");
+        assert_eq!(elide_middle("kv/get", 5), "kv…et");
+        assert_eq!(elide_middle("kv/get", 1), "…");
+        assert_eq!(elide_middle("kv/get", 0), "");
+    }
+}
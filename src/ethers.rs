@@ -0,0 +1,245 @@
+use crate::kepler::ens_name;
+use crate::{AsyncSigner, Capability, SignatureVerificationError};
+use std::collections::BTreeSet;
+
+use ethers::abi::{encode, Token};
+use ethers::providers::{namehash, Middleware, ENS_ADDRESS};
+use ethers::signers::Signer;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionRequest, H160, H256};
+use ethers::utils::hash_message;
+use siwe::Message;
+
+/// The selector of `isValidSignature(bytes32,bytes)`, which is also the magic value returned for a
+/// valid signature.
+const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// The selector of `owner(bytes32)` on the ENS registry.
+const OWNER_SELECTOR: [u8; 4] = [0x02, 0x57, 0x1b, 0xe3];
+
+/// The selector of `ownerOf(uint256)` on the ENS NameWrapper.
+const OWNER_OF_SELECTOR: [u8; 4] = [0x63, 0x52, 0x21, 0x1e];
+
+/// The mainnet ENS NameWrapper (`0xD4416b13d2b3a9aBae7AcD5D6C2BbDBE25686401`), which owns wrapped
+/// names in the registry on behalf of their actual owners.
+const NAME_WRAPPER: Address = H160([
+    0xd4, 0x41, 0x6b, 0x13, 0xd2, 0xb3, 0xa9, 0xab, 0xae, 0x7a, 0xcd, 0x5d, 0x6c, 0x2b, 0xbd, 0xbe,
+    0x25, 0x68, 0x64, 0x01,
+]);
+
+/// Adapts an ethers [`Signer`], e.g. a local wallet, a Ledger or an AWS KMS key, to an
+/// [`AsyncSigner`], for use with [`Builder::build_and_sign_async`](crate::Builder::build_and_sign_async).
+#[derive(Clone, Debug)]
+pub struct EthersSigner<S>(pub S);
+
+impl<S> AsyncSigner for EthersSigner<S>
+where
+    S: Signer,
+{
+    type Error = S::Error;
+
+    async fn sign_async(&self, message: &str) -> Result<Vec<u8>, Self::Error> {
+        self.0
+            .sign_message(message)
+            .await
+            .map(|signature| signature.to_vec())
+    }
+}
+
+/// Verify the signature of a SIWE message, either an EIP-191 signature by the address, or, for a
+/// smart contract wallet, an EIP-1271 signature checked by calling the contract through the
+/// provider.
+pub async fn verify_signature<M>(
+    message: &Message,
+    signature: &[u8],
+    provider: &M,
+) -> Result<(), SignatureVerificationError<M::Error>>
+where
+    M: Middleware,
+{
+    if let Ok(signature) = <&[u8; 65]>::try_from(signature) {
+        if message.verify_eip191(signature).is_ok() {
+            return Ok(());
+        }
+    }
+    let hash = hash_message(message.to_string());
+    let data = [
+        EIP1271_MAGIC_VALUE.as_slice(),
+        &encode(&[
+            Token::FixedBytes(hash.as_bytes().to_vec()),
+            Token::Bytes(signature.to_vec()),
+        ]),
+    ]
+    .concat();
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(Address::from(message.address))
+        .data(data)
+        .into();
+    let result = provider
+        .call(&call, None)
+        .await
+        .map_err(SignatureVerificationError::Provider)?;
+    if result.get(..4) == Some(EIP1271_MAGIC_VALUE.as_slice()) {
+        Ok(())
+    } else {
+        Err(SignatureVerificationError::InvalidSignature)
+    }
+}
+
+/// Check that the ENS name of every `kepler:ens:` target of the capabilities is owned by the
+/// controller, e.g. the [address](crate::VerifiedSession::address) of the signer, catching
+/// delegations of orbits which the signer doesn't control.
+///
+/// The owner is looked up in the mainnet ENS registry, and for wrapped names in the NameWrapper,
+/// rather than through the name's resolver, whose address record the owner can point anywhere.
+pub async fn verify_ens_targets<M, NB>(
+    capability: &Capability<NB>,
+    controller: &[u8; 20],
+    provider: &M,
+) -> Result<(), EnsError<M::Error>>
+where
+    M: Middleware,
+{
+    let names: BTreeSet<&str> = capability
+        .abilities()
+        .keys()
+        .filter_map(|target| ens_name(target.as_str()))
+        .collect();
+    for name in names {
+        let node = namehash(name);
+        let mut owner = ens_owner(provider, name, ENS_ADDRESS, OWNER_SELECTOR, node).await?;
+        if owner == NAME_WRAPPER {
+            owner = ens_owner(provider, name, NAME_WRAPPER, OWNER_OF_SELECTOR, node).await?;
+        }
+        if owner.0 != *controller {
+            return Err(EnsError::NotControlled {
+                name: name.to_string(),
+                owner: owner.0,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Call an owner lookup of an ENS contract for the node of a name.
+async fn ens_owner<M>(
+    provider: &M,
+    name: &str,
+    contract: Address,
+    selector: [u8; 4],
+    node: H256,
+) -> Result<Address, EnsError<M::Error>>
+where
+    M: Middleware,
+{
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(contract)
+        .data([selector.as_slice(), node.as_bytes()].concat())
+        .into();
+    let result = provider
+        .call(&call, None)
+        .await
+        .map_err(|source| EnsError::Provider {
+            name: name.to_string(),
+            source,
+        })?;
+    result
+        .get(12..32)
+        .map(Address::from_slice)
+        .ok_or_else(|| EnsError::InvalidResponse(name.to_string()))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EnsError<E> {
+    #[error("failed to look up the owner of {name}: {source}")]
+    Provider { name: String, source: E },
+    #[error("invalid ENS owner response for {0}")]
+    InvalidResponse(String),
+    #[error("{name} is owned by 0x{}, not the expected controller", owner.iter().map(|b| format!("{b:02x}")).collect::<String>())]
+    NotControlled { name: String, owner: [u8; 20] },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+    use ethers::providers::Provider;
+    use ethers::signers::LocalWallet;
+    use ethers::types::Bytes;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn sign_and_verify() {
+        let wallet = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let mut message = crate::test_utils::message();
+        message.address = wallet.address().0;
+        let (message, signature) = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build_and_sign_async(message, &EthersSigner(wallet))
+            .await
+            .unwrap();
+
+        // EOA signatures are checked without calling the provider
+        let (provider, mock) = Provider::mocked();
+        verify_signature(&message, &signature, &provider)
+            .await
+            .unwrap();
+
+        // anything else is checked by the contract
+        let mut magic = EIP1271_MAGIC_VALUE.to_vec();
+        magic.resize(32, 0);
+        mock.push::<Bytes, _>(Bytes::from(magic)).unwrap();
+        verify_signature(&message, b"contract signature", &provider)
+            .await
+            .unwrap();
+
+        mock.push::<Bytes, _>(Bytes::from(vec![0; 32])).unwrap();
+        assert!(matches!(
+            verify_signature(&message, b"contract signature", &provider).await,
+            Err(SignatureVerificationError::InvalidSignature)
+        ));
+    }
+
+    #[tokio::test]
+    async fn ens_targets() {
+        let mut capability = Capability::<Value>::new();
+        capability
+            .with_action_convert("kepler:ens:example.eth://default/kv", "kv/get", [])
+            .unwrap()
+            .with_action_convert("kepler:ens:example.eth://default/kv/public", "kv/put", [])
+            .unwrap()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let controller = [0x11; 20];
+        let word = |bytes: &[u8]| {
+            let mut word = vec![0; 32 - bytes.len()];
+            word.extend_from_slice(bytes);
+            Bytes::from(word)
+        };
+
+        assert_eq!(OWNER_SELECTOR, ethers::utils::id("owner(bytes32)"));
+        assert_eq!(OWNER_OF_SELECTOR, ethers::utils::id("ownerOf(uint256)"));
+
+        // the registry owner
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(word(&controller)).unwrap();
+        verify_ens_targets(&capability, &controller, &provider)
+            .await
+            .unwrap();
+
+        // the NameWrapper owner of a wrapped name, popped in reverse order
+        mock.push::<Bytes, _>(word(&controller)).unwrap();
+        mock.push::<Bytes, _>(word(NAME_WRAPPER.as_bytes()))
+            .unwrap();
+        verify_ens_targets(&capability, &controller, &provider)
+            .await
+            .unwrap();
+
+        mock.push::<Bytes, _>(word(&[0x33; 20])).unwrap();
+        assert!(matches!(
+            verify_ens_targets(&capability, &controller, &provider).await,
+            Err(EnsError::NotControlled { name, owner }) if name == "example.eth" && owner == [0x33; 20]
+        ));
+    }
+}
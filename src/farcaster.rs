@@ -0,0 +1,141 @@
+use crate::SignInMessage;
+
+use iri_string::types::UriString;
+use siwe::Message;
+
+/// The prefix of the resource identifying the Farcaster ID of the signer.
+pub const FID_RESOURCE_PREFIX: &str = "farcaster://fid/";
+
+/// A Sign-In-With-Farcaster message: a SIWE message signed by the custody address of a Farcaster
+/// account, whose ID is given by a `farcaster://fid/<fid>` resource, so that social-login sessions
+/// can carry ReCap capabilities too.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FarcasterMessage(pub Message);
+
+impl FarcasterMessage {
+    /// Identify the Farcaster ID in the message, adding its resource before any other.
+    pub fn new(mut message: Message, fid: u64) -> Self {
+        let resource = format!("{FID_RESOURCE_PREFIX}{fid}")
+            .parse()
+            .expect("FID resources are valid URIs");
+        message.resources.insert(0, resource);
+        Self(message)
+    }
+
+    /// The Farcaster ID of the signer, if the message identifies one.
+    pub fn fid(&self) -> Option<u64> {
+        self.0
+            .resources
+            .iter()
+            .find_map(|resource| resource.as_str().strip_prefix(FID_RESOURCE_PREFIX))
+            .and_then(|fid| fid.parse().ok())
+    }
+
+    /// Verify that the message was signed by the custody address of the account, as looked up in
+    /// the Farcaster ID registry, returning the Farcaster ID of the signer.
+    pub fn verify_custody(
+        &self,
+        signature: &[u8],
+        custody: &[u8; 20],
+    ) -> Result<u64, FarcasterError> {
+        let fid = self.fid().ok_or(FarcasterError::MissingFid)?;
+        if self.0.address != *custody {
+            return Err(FarcasterError::NotCustody);
+        }
+        let signature: &[u8; 65] = signature
+            .try_into()
+            .map_err(|_| siwe::VerificationError::SignatureLength)?;
+        self.0.verify_eip191(signature)?;
+        Ok(fid)
+    }
+
+    /// The underlying SIWE message.
+    pub fn into_inner(self) -> Message {
+        self.0
+    }
+}
+
+impl SignInMessage for FarcasterMessage {
+    fn domain(&self) -> &str {
+        self.0.domain()
+    }
+
+    fn address(&self) -> String {
+        SignInMessage::address(&self.0)
+    }
+
+    fn uri(&self) -> &str {
+        SignInMessage::uri(&self.0)
+    }
+
+    fn statement(&self) -> Option<&str> {
+        SignInMessage::statement(&self.0)
+    }
+
+    fn set_statement(&mut self, statement: Option<String>) {
+        self.0.set_statement(statement);
+    }
+
+    fn resources(&self) -> impl Iterator<Item = &str> {
+        SignInMessage::resources(&self.0)
+    }
+
+    fn push_resource(&mut self, resource: UriString) {
+        self.0.push_resource(resource);
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FarcasterError {
+    #[error("the message does not identify a Farcaster ID")]
+    MissingFid,
+    #[error("the message was not signed by the custody address")]
+    NotCustody,
+    #[error(transparent)]
+    Signature(#[from] siwe::VerificationError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, TestSigner};
+    use crate::{Capability, Signer};
+    use serde_json::Value;
+
+    #[test]
+    fn farcaster_session() {
+        let signer = TestSigner::new();
+        let mut message = message();
+        message.statement = Some("Farcaster Auth".into());
+        message.chain_id = 10;
+        message.address = signer.address();
+
+        let mut capability = Capability::<Value>::new();
+        capability
+            .with_action_convert("https://example.com/casts", "casts/publish", [])
+            .unwrap();
+        let message = capability
+            .build_message(FarcasterMessage::new(message, 1234))
+            .unwrap();
+        assert_eq!(message.fid(), Some(1234));
+        assert!(Capability::<Value>::extract_and_verify(&message)
+            .unwrap()
+            .is_some());
+
+        let signature = signer.sign(&message.0.to_string()).unwrap();
+        assert_eq!(
+            message
+                .verify_custody(&signature, &signer.address())
+                .unwrap(),
+            1234
+        );
+        assert!(matches!(
+            message.verify_custody(&signature, &TestSigner::from_seed(2).address()),
+            Err(FarcasterError::NotCustody)
+        ));
+        assert!(matches!(
+            FarcasterMessage(message.into_inner()).verify_custody(&[0; 65], &signer.address()),
+            Err(FarcasterError::Signature(_))
+        ));
+    }
+}
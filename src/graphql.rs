@@ -0,0 +1,142 @@
+use crate::{Capability, RequiredCapabilities, SessionError, VerifiedSession};
+
+use async_graphql::{
+    Context, Error, ErrorExtensions, Guard, InputValueError, InputValueResult, Scalar, ScalarType,
+    Value,
+};
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+/// Capabilities are exposed as a JSON scalar holding the `att` and `prf` of the ReCap object.
+#[Scalar(name = "Recap")]
+impl ScalarType for Capability<serde_json::Value> {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        let json = value.into_json().map_err(InputValueError::custom)?;
+        serde_json::from_value(json).map_err(InputValueError::custom)
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|json| Value::from_json(json).ok())
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// A field guard requiring the [`VerifiedSession`] in the request data to have been delegated an
+/// action on a target, e.g. `#[graphql(guard = "RecapGuard::new(\"kv/get\", \"kepler:...\")")]`.
+///
+/// Requests are rejected with an error whose `code` extension is the
+/// [code](SessionError::code) of the failure.
+#[derive(Clone, Debug)]
+pub struct RecapGuard {
+    action: String,
+    target: String,
+}
+
+impl RecapGuard {
+    /// Require the action on the target.
+    pub fn new(action: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            action: action.into(),
+            target: target.into(),
+        }
+    }
+}
+
+impl Guard for RecapGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let target: UriString = self
+            .target
+            .parse()
+            .map_err(|_| Error::new(format!("invalid guard target {}", self.target)))?;
+        let action: Ability = self
+            .action
+            .parse()
+            .map_err(|_| Error::new(format!("invalid guard action {}", self.action)))?;
+        let session = ctx
+            .data_opt::<VerifiedSession<serde_json::Value>>()
+            .ok_or(SessionError::Missing)
+            .map_err(|e| e.extend())?;
+        session
+            .require(&RequiredCapabilities::new().with_action(target, action))
+            .map_err(|e| e.extend())
+    }
+}
+
+impl ErrorExtensions for SessionError {
+    fn extend(&self) -> Error {
+        Error::new(self.to_string())
+            .extend_with(|_, extensions| extensions.set("code", self.code()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::verified_session;
+    use crate::Builder;
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Request, Schema, Variables};
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        #[graphql(guard = "RecapGuard::new(\"kv/get\", \"https://example.com/kv\")")]
+        async fn read(&self) -> bool {
+            true
+        }
+
+        #[graphql(guard = "RecapGuard::new(\"kv/put\", \"https://example.com/kv\")")]
+        async fn write(&self) -> bool {
+            true
+        }
+
+        async fn echo(
+            &self,
+            capability: Capability<serde_json::Value>,
+        ) -> Capability<serde_json::Value> {
+            capability
+        }
+    }
+
+    #[tokio::test]
+    async fn guard() {
+        let session = verified_session(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+        let schema = Schema::new(Query, EmptyMutation, EmptySubscription);
+
+        let response = schema.execute("{ read }").await;
+        assert_eq!(
+            response.errors[0].extensions.as_ref().unwrap().get("code"),
+            Some(&Value::from("missing_session"))
+        );
+
+        let response = schema
+            .execute(Request::new("{ read }").data(session.clone()))
+            .await;
+        assert!(response.errors.is_empty());
+
+        let response = schema
+            .execute(Request::new("{ write }").data(session.clone()))
+            .await;
+        assert_eq!(
+            response.errors[0].extensions.as_ref().unwrap().get("code"),
+            Some(&Value::from("insufficient_capability"))
+        );
+
+        let capability = serde_json::to_value(session.capability()).unwrap();
+        let response = schema
+            .execute(
+                Request::new("query($c: Recap!) { echo(capability: $c) }")
+                    .variables(Variables::from_json(serde_json::json!({ "c": capability }))),
+            )
+            .await;
+        assert!(response.errors.is_empty());
+        assert_eq!(response.data.into_json().unwrap()["echo"], capability);
+    }
+}
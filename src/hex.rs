@@ -0,0 +1,43 @@
+//! Strict decoding of the hex digits of addresses and signatures, which unlike
+//! [`u8::from_str_radix`] rejects signs and any other non-hex characters.
+
+/// Decode an even number of hex digits, without a `0x` prefix.
+pub(crate) fn decode(hex: &str) -> Option<Vec<u8>> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| Some(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// Decode exactly `2 * N` hex digits, without a `0x` prefix.
+pub(crate) fn decode_array<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    decode(hex)?.try_into().ok()
+}
+
+fn digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strict() {
+        assert_eq!(decode("00fFa9"), Some(vec![0x00, 0xff, 0xa9]));
+        assert_eq!(decode(""), Some(vec![]));
+        assert_eq!(decode_array::<2>("0102"), Some([1, 2]));
+        for invalid in ["+1", "-1", "0", "0g", " 1", "0x01", "é1"] {
+            assert_eq!(decode(invalid), None, "{invalid}");
+        }
+        assert_eq!(decode_array::<2>("010203"), None);
+    }
+}
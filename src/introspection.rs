@@ -0,0 +1,86 @@
+use crate::{delegator_did, VerifiedSession};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+
+impl<NB> VerifiedSession<NB>
+where
+    NB: Serialize,
+{
+    /// Render the session as an RFC 7662 token introspection response, so that API gateways can
+    /// front ReCap-authenticated services with existing OAuth2 tooling.
+    ///
+    /// The response is active unless the message has expired or is not yet valid. The `recap`
+    /// claim holds the delegated capabilities; there is no `scope`, as a flat list of abilities
+    /// would lose which target each is granted on.
+    pub fn to_introspection_response(&self) -> Value {
+        self.to_introspection_response_at(&OffsetDateTime::now_utc())
+    }
+
+    /// Render the session as an introspection response at the given time.
+    pub fn to_introspection_response_at(&self, t: &OffsetDateTime) -> Value {
+        let message = self.message();
+        if !message.valid_at(t) {
+            return inactive_introspection_response();
+        }
+        let mut response = json!({
+            "active": true,
+            "sub": delegator_did(message),
+            "aud": message.uri.as_str(),
+            "iss": message.domain.as_str(),
+            "iat": unix_timestamp(&message.issued_at),
+            "recap": self.capability(),
+        });
+        if let Some(exp) = &message.expiration_time {
+            response["exp"] = unix_timestamp(exp).into();
+        }
+        if let Some(nbf) = &message.not_before {
+            response["nbf"] = unix_timestamp(nbf).into();
+        }
+        response
+    }
+}
+
+/// The introspection response for a session which failed verification.
+pub fn inactive_introspection_response() -> Value {
+    json!({ "active": false })
+}
+
+fn unix_timestamp(t: &siwe::TimeStamp) -> i64 {
+    AsRef::<OffsetDateTime>::as_ref(t).unix_timestamp()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{verified_session, FIXTURE_TIMESTAMP};
+    use crate::Builder;
+
+    #[test]
+    fn introspection() {
+        let session = verified_session(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap()
+                .with_action_convert("https://example.com/kv", "kv/put", [])
+                .unwrap(),
+        )
+        .unwrap();
+        let now = OffsetDateTime::from_unix_timestamp(FIXTURE_TIMESTAMP).unwrap();
+
+        let response = session.to_introspection_response_at(&now);
+        assert_eq!(response["active"], true);
+        assert_eq!(response["sub"], delegator_did(session.message()));
+        assert_eq!(response["aud"], "did:key:example");
+        assert_eq!(response["exp"], 1655816400);
+        assert!(response.get("scope").is_none());
+        assert!(response["recap"]["att"]["https://example.com/kv"]["kv/get"].is_array());
+
+        let later = OffsetDateTime::from_unix_timestamp(1655820000).unwrap();
+        assert_eq!(
+            session.to_introspection_response_at(&later),
+            inactive_introspection_response()
+        );
+    }
+}
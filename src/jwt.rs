@@ -0,0 +1,135 @@
+use crate::{delegator_did, Capability, VerifiedSession};
+
+use jsonwebtoken::errors::Error;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+/// The claims of a JWT bridging a verified session to services which only speak JWT, so that they
+/// can honor ReCap grants without parsing SIWE messages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionToken<NB> {
+    /// The `did:pkh` of the signer.
+    pub sub: String,
+    /// The URI of the delegee.
+    pub aud: String,
+    /// The issuance time, in seconds since the epoch.
+    pub iat: i64,
+    /// The expiration time, in seconds since the epoch, which is never later than that of the
+    /// SIWE message.
+    pub exp: i64,
+    /// The delegated capabilities.
+    pub recap: Capability<NB>,
+}
+
+impl<NB> SessionToken<NB>
+where
+    NB: Clone,
+{
+    /// The lifetime of tokens issued with [`SessionToken::issue`].
+    pub const DEFAULT_LIFETIME: Duration = Duration::minutes(5);
+
+    /// Derive the claims of a token issued at the given time, expiring after the given lifetime
+    /// or with the SIWE message, whichever comes first.
+    pub fn from_session(
+        session: &VerifiedSession<NB>,
+        issued_at: OffsetDateTime,
+        lifetime: Duration,
+    ) -> Self {
+        let message = session.message();
+        let mut exp = issued_at + lifetime;
+        if let Some(expiration) = &message.expiration_time {
+            exp = exp.min(*AsRef::<OffsetDateTime>::as_ref(expiration));
+        }
+        Self {
+            sub: delegator_did(message),
+            aud: message.uri.to_string(),
+            iat: issued_at.unix_timestamp(),
+            exp: exp.unix_timestamp(),
+            recap: session.capability().clone(),
+        }
+    }
+}
+
+impl<NB> SessionToken<NB>
+where
+    NB: Clone + Serialize,
+{
+    /// Issue an HS256 token for the session, valid for [`SessionToken::DEFAULT_LIFETIME`] at most.
+    pub fn issue(session: &VerifiedSession<NB>, key: &EncodingKey) -> Result<String, Error> {
+        Self::from_session(session, OffsetDateTime::now_utc(), Self::DEFAULT_LIFETIME)
+            .encode(&Header::default(), key)
+    }
+
+    /// Encode and sign the claims.
+    pub fn encode(&self, header: &Header, key: &EncodingKey) -> Result<String, Error> {
+        jsonwebtoken::encode(header, self, key)
+    }
+}
+
+impl<NB> SessionToken<NB>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    /// Validate a token, returning its claims.
+    ///
+    /// The validation should name the expected audience, as tokens always carry one.
+    pub fn validate(
+        token: &str,
+        key: &DecodingKey,
+        validation: &Validation,
+    ) -> Result<Self, Error> {
+        jsonwebtoken::decode(token, key, validation).map(|data| data.claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{verified_session, FIXTURE_TIMESTAMP};
+    use crate::Builder;
+    use jsonwebtoken::Algorithm;
+    use serde_json::Value;
+
+    #[test]
+    fn token_roundtrip() {
+        let session = verified_session(
+            Builder::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+        let message = session.message();
+        let now = OffsetDateTime::from_unix_timestamp(FIXTURE_TIMESTAMP).unwrap();
+
+        let claims = SessionToken::from_session(&session, now, Duration::minutes(5));
+        assert_eq!(claims.exp, now.unix_timestamp() + 300);
+        let claims = SessionToken::from_session(&session, now, Duration::days(1));
+        assert_eq!(claims.exp, 1655816400);
+
+        let token = claims
+            .encode(&Header::default(), &EncodingKey::from_secret(b"secret"))
+            .unwrap();
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&["did:key:example"]);
+        validation.validate_exp = false;
+        let validated = SessionToken::<Value>::validate(
+            &token,
+            &DecodingKey::from_secret(b"secret"),
+            &validation,
+        )
+        .unwrap();
+        assert_eq!(validated.sub, delegator_did(message));
+        assert!(validated
+            .recap
+            .abilities_for_str("https://example.com/kv")
+            .is_some_and(|abilities| abilities.contains_key(&"kv/get".parse().unwrap())));
+
+        assert!(SessionToken::<Value>::validate(
+            &token,
+            &DecodingKey::from_secret(b"other"),
+            &validation
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,235 @@
+//! Capabilities for Kepler orbits, built from an orbit id and the paths to be accessed.
+//!
+//! ```
+//! use siwe_recap::kepler::{KeplerGrants, KvAccess, Orbit};
+//!
+//! let capability = KeplerGrants::new(Orbit::new("ens:example.eth"))
+//!     .with_path("public", KvAccess::ReadWrite)
+//!     .with_path("", KvAccess::Read)
+//!     .to_capability::<serde_json::Value>()
+//!     .unwrap();
+//! assert!(capability
+//!     .abilities_for_str("kepler:ens:example.eth://default/kv/public")
+//!     .is_some());
+//! ```
+
+use crate::well_known::{kv, orbit};
+use crate::{AbilityStr, Builder, Capability};
+use std::collections::BTreeMap;
+
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+/// The default name of an orbit's hosting location.
+pub const DEFAULT_NAME: &str = "default";
+
+/// A Kepler orbit, identified e.g. by `ens:example.eth` or `pkh:eip155:1:0x…`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Orbit {
+    id: String,
+    name: String,
+}
+
+impl Orbit {
+    /// Refer to the orbit with the given id, at the [default](DEFAULT_NAME) name.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: DEFAULT_NAME.to_string(),
+        }
+    }
+
+    /// Refer to the orbit at the given name rather than the default.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The target URI of the orbit itself, e.g. `kepler:ens:example.eth://default`.
+    pub fn target(&self) -> Result<UriString, KeplerError> {
+        parse_target(format!("kepler:{}://{}", self.id, self.name))
+    }
+
+    /// The target URI of a key-value path in the orbit, e.g.
+    /// `kepler:ens:example.eth://default/kv/public`, or the whole store for an empty path.
+    pub fn kv_target(&self, path: &str) -> Result<UriString, KeplerError> {
+        match path.trim_matches('/') {
+            "" => parse_target(format!("kepler:{}://{}/kv", self.id, self.name)),
+            path => parse_target(format!("kepler:{}://{}/kv/{path}", self.id, self.name)),
+        }
+    }
+}
+
+/// The ENS name of the orbit of a `kepler:ens:<name>://…` target, e.g. `example.eth`.
+pub fn ens_name(target: &str) -> Option<&str> {
+    target
+        .strip_prefix("kepler:ens:")?
+        .split_once("://")
+        .map(|(name, _)| name)
+        .filter(|name| !name.is_empty())
+}
+
+fn parse_target(target: String) -> Result<UriString, KeplerError> {
+    match UriString::try_from(target.as_str()) {
+        Ok(uri) => Ok(uri),
+        Err(source) => Err(KeplerError::InvalidTarget { target, source }),
+    }
+}
+
+/// The access to a key-value path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KvAccess {
+    /// Get values and their metadata, and list keys.
+    Read,
+    /// Put and delete values.
+    Write,
+    /// Both read and write.
+    ReadWrite,
+}
+
+impl KvAccess {
+    fn abilities(self) -> &'static [AbilityStr<'static>] {
+        match self {
+            Self::Read => &[kv::GET, kv::LIST, kv::METADATA],
+            Self::Write => &[kv::PUT, kv::DELETE],
+            Self::ReadWrite => &[kv::GET, kv::LIST, kv::METADATA, kv::PUT, kv::DELETE],
+        }
+    }
+}
+
+/// The grants of a session with a Kepler orbit.
+#[derive(Clone, Debug)]
+pub struct KeplerGrants {
+    orbit: Orbit,
+    paths: Vec<(String, KvAccess)>,
+    host: bool,
+}
+
+impl KeplerGrants {
+    /// Create an empty set of grants for the orbit.
+    pub fn new(orbit: Orbit) -> Self {
+        Self {
+            orbit,
+            paths: Vec::new(),
+            host: false,
+        }
+    }
+
+    /// Grant access to a key-value path, or to the whole store for an empty path.
+    pub fn with_path(mut self, path: impl Into<String>, access: KvAccess) -> Self {
+        self.paths.push((path.into(), access));
+        self
+    }
+
+    /// Grant the ability to host the orbit.
+    pub fn with_host(mut self) -> Self {
+        self.host = true;
+        self
+    }
+
+    fn grants(&self) -> Result<Vec<(UriString, Ability)>, KeplerError> {
+        let mut grants = Vec::new();
+        if self.host {
+            grants.push((self.orbit.target()?, ability(orbit::HOST)));
+        }
+        for (path, access) in &self.paths {
+            let target = self.orbit.kv_target(path)?;
+            grants.extend(
+                access
+                    .abilities()
+                    .iter()
+                    .map(|action| (target.clone(), ability(*action))),
+            );
+        }
+        Ok(grants)
+    }
+
+    /// Produce the capabilities, without nota benes.
+    pub fn to_capability<NB>(&self) -> Result<Capability<NB>, KeplerError> {
+        let mut capability = Capability::new();
+        for (target, action) in self.grants()? {
+            capability.with_action(target, action, None::<BTreeMap<String, NB>>);
+        }
+        Ok(capability)
+    }
+}
+
+fn ability(ability: AbilityStr<'static>) -> Ability {
+    ability
+        .to_ability()
+        .expect("well-known abilities are valid")
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Clone,
+{
+    /// Add the grants of a Kepler session.
+    pub fn with_kepler_grants(self, grants: &KeplerGrants) -> Result<Self, KeplerError> {
+        grants
+            .grants()?
+            .into_iter()
+            .try_fold(self, |builder, (target, action)| {
+                Ok(builder.with_action(target, action, None)?)
+            })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeplerError {
+    #[error("invalid kepler target {target}: {source}")]
+    InvalidTarget {
+        target: String,
+        source: iri_string::validate::Error,
+    },
+    #[error(transparent)]
+    Rejected(#[from] crate::BuildError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn kepler_grants() {
+        let orbit = Orbit::new("ens:example.eth");
+        assert_eq!(
+            orbit.kv_target("/public/").unwrap().as_str(),
+            "kepler:ens:example.eth://default/kv/public"
+        );
+        assert_eq!(
+            orbit.clone().with_name("backup").target().unwrap().as_str(),
+            "kepler:ens:example.eth://backup"
+        );
+        assert_eq!(
+            ens_name(orbit.kv_target("public").unwrap().as_str()),
+            Some("example.eth")
+        );
+        assert_eq!(ens_name("kepler:pkh:eip155:1:0x0://default"), None);
+        assert!(matches!(
+            Orbit::new("ens:example eth").target(),
+            Err(KeplerError::InvalidTarget { .. })
+        ));
+
+        let grants = KeplerGrants::new(orbit)
+            .with_host()
+            .with_path("public", KvAccess::ReadWrite)
+            .with_path("", KvAccess::Read);
+        let capability = Builder::<Value>::new()
+            .with_kepler_grants(&grants)
+            .unwrap()
+            .into_capability();
+        assert_eq!(
+            serde_jcs::to_string(&capability).unwrap(),
+            serde_jcs::to_string(&grants.to_capability::<Value>().unwrap()).unwrap()
+        );
+        assert_eq!(
+            capability.to_statement(),
+            "I further authorize the stated URI to perform the following actions on my behalf: \
+             (1) 'orbit': 'host' for 'kepler:ens:example.eth://default'. \
+             (2) 'kv': 'get', 'list', 'metadata' for 'kepler:ens:example.eth://default/kv'. \
+             (3) 'kv': 'delete', 'get', 'list', 'metadata', 'put' for 'kepler:ens:example.eth://default/kv/public'."
+        );
+    }
+}
@@ -1,17 +1,180 @@
+mod ability;
+mod ability_set;
+#[cfg(feature = "actix-web")]
+mod actix;
+mod adapters;
+#[cfg(feature = "alloy")]
+mod alloy;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "axum")]
+mod axum;
+mod base64url;
+mod batch;
+mod builder;
+mod bundle;
+mod cacao;
+mod caip122;
 mod capability;
+mod capability_ref;
+mod compliance;
+mod credential;
+mod did;
+mod display;
+#[cfg(feature = "ethers")]
+mod ethers;
+mod farcaster;
+#[cfg(feature = "async-graphql")]
+mod graphql;
+mod hex;
+mod introspection;
+#[cfg(feature = "jwt")]
+mod jwt;
+pub mod kepler;
+mod macros;
+mod message_builder;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod nota_bene;
+#[cfg(feature = "utoipa")]
+mod openapi;
+mod ordering;
+mod pinning;
+mod plain_language;
+mod prepared;
+mod preset;
+mod proof_policy;
+mod proof_store;
+#[cfg(feature = "redis")]
+mod redis;
+mod registry;
+mod replay;
+mod scan;
+mod session;
+mod signer;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod statement_hash;
+mod store;
+mod target_index;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+#[cfg(feature = "tower")]
+mod tower;
+#[cfg(feature = "ts-rs")]
+mod typescript;
+mod vectors;
+mod verification;
+mod walletconnect;
+#[cfg(feature = "wasm")]
+mod wasm;
+pub mod well_known;
+mod zcap;
 
-pub use capability::{Capability, DecodingError, EncodingError, VerificationError};
+pub use ability::{
+    is_sub_namespace, parse_ability, parse_ability_with, parse_name, parse_namespace, AbilityError,
+    AbilityExt, AbilityPattern, AbilityPolicy, AbilityStr, CaseNormalization, Structured,
+    StructuredAbility, RESERVED_NAMESPACES,
+};
+pub use ability_set::AbilitySet;
+#[cfg(feature = "actix-web")]
+pub use actix::{RecapSession, RecapSessionMiddleware};
+pub use adapters::{AsAbilityString, AsB58Cid, AsUriStringMap};
+#[cfg(feature = "alloy")]
+pub use alloy::{verify_signature_alloy, AlloySigner};
+#[cfg(feature = "arbitrary")]
+pub use arbitrary::{arbitrary_ability, arbitrary_target};
+#[cfg(feature = "arena")]
+pub use arena::ArenaCapability;
+#[cfg(feature = "axum")]
+pub use axum::VerifiedRecap;
+pub use builder::{
+    BuildError, BuildReport, Builder, DuplicateGrantPolicy, GrantError, TargetValidator,
+};
+pub use bundle::{BundleError, SessionBundle};
+pub use cacao::{Cacao, CacaoError};
+pub use caip122::SignInMessage;
+pub use capability::{
+    Capability, DecodingError, DecodingLimits, DecodingProfile, EncodingError, VerificationError,
+};
+pub use capability_ref::CapabilityRef;
+pub use compliance::ComplianceProfile;
+pub use credential::DelegationCredential;
+pub use did::{
+    check_delegator_did, delegator_did, DelegeeError, DidDocument, DidError, DidKeyResolver,
+    DidResolver,
+};
+pub use display::{elide_middle, TreeDisplay};
+#[cfg(feature = "ethers")]
+pub use ethers::{verify_ens_targets, verify_signature, EnsError, EthersSigner};
+pub use farcaster::{FarcasterError, FarcasterMessage, FID_RESOURCE_PREFIX};
+#[cfg(feature = "async-graphql")]
+pub use graphql::RecapGuard;
+pub use introspection::inactive_introspection_response;
+#[cfg(feature = "jwt")]
+pub use jwt::SessionToken;
+pub use message_builder::MessageBuilder;
+pub use nota_bene::{TypedNotaBene, EXTRA_FIELDS_KEY};
+pub use ordering::AttOrdering;
+#[cfg(feature = "ipfs")]
+pub use pinning::{IpfsError, IpfsHttpBackend, PinningServiceBackend};
+pub use pinning::{PinError, PinningBackend};
+pub use plain_language::AbilityDescriptions;
+pub use prepared::{PreparedDelegation, Preview, SigningResponse, SigningResponseError};
+pub use preset::PresetRegistry;
+pub use proof_policy::{ProofPolicy, ProofPolicyError};
+#[cfg(feature = "ucan")]
+pub use proof_store::UcanProofStore;
+pub use proof_store::{Proof, ProofStore, ProofStoreError};
+#[cfg(feature = "redis")]
+pub use redis::RedisStore;
+pub use registry::{NamespaceRegistry, NamespaceSpec, RegistryError};
+pub use replay::{MemoryTtlStore, NonceStore, ReplayError, VerificationCache};
+pub use scan::{has_orphaned_statement, scan_recap};
+pub use session::{
+    RequiredCapabilities, SessionError, SessionHeader, VerifiedSession, SESSION_SCHEME,
+};
+pub use signer::{AsyncSigner, SignatureVerificationError, Signer, SigningError};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteCapabilityStore, SqliteStoreError};
+pub use statement_hash::StatementHash;
+pub use store::{signed_delegation_id, CapabilityStore, MemoryCapabilityStore, StoredDelegation};
+#[cfg(feature = "tower")]
+pub use tower::{RecapLayer, RecapService};
+#[cfg(feature = "ts-rs")]
+pub use typescript::typescript_definitions;
 pub use ucan_capabilities_object::{
     AbilityName, AbilityNameRef, AbilityNamespace, AbilityNamespaceRef, AbilityRef, CapsInner,
     ConvertError, NotaBeneCollection,
 };
+pub use vectors::{TestVector, VectorFailure, EIP5573_VECTORS};
+pub use verification::VerificationOptions;
+#[cfg(feature = "wasm")]
+pub use wasm::{extract_and_verify, WasmCapability};
+pub use zcap::{ZcapDelegation, ZcapError};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::ability::{
+        is_valid_ability, is_valid_namespace, is_valid_target, split_valid_ability,
+    };
+    pub use iri_string::types::UriString;
+}
 
 /// The prefix for a ReCap uri.
 pub const RESOURCE_PREFIX: &str = "urn:recap:";
 
+/// The opening sentence of a ReCap statement in final EIP-5573, see [`ComplianceProfile::preamble`].
+pub(crate) const STATEMENT_PREAMBLE: &str =
+    "I further authorize the stated URI to perform the following actions on my behalf:";
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use iri_string::types::UriString;
+    use proptest::prelude::*;
     use serde_json::Value;
     use siwe::Message;
 
@@ -192,4 +355,68 @@ mod test {
             "recap resource should come last"
         );
     }
+
+    fn arb_capability() -> impl Strategy<Value = Capability<Value>> {
+        let nb =
+            prop::collection::btree_map("[a-z]{1,5}", any::<i64>().prop_map(Value::from), 0..3);
+        let abilities = prop::collection::vec(
+            (
+                "[a-z][a-z0-9-]{0,7}/[a-z][a-z0-9-]{0,7}",
+                prop::collection::vec(nb, 0..3),
+            ),
+            1..4,
+        );
+        prop::collection::vec(("https://example\\.com/[a-z]{1,8}", abilities), 0..4).prop_map(
+            |grants| {
+                let mut capability = Capability::<Value>::default();
+                for (target, abilities) in grants {
+                    capability
+                        .with_actions_convert(
+                            target.as_str(),
+                            abilities.iter().map(|(a, nbs)| (a.as_str(), nbs.clone())),
+                        )
+                        .unwrap();
+                }
+                capability
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn encode_decode_roundtrip(capability in arb_capability()) {
+            let uri = UriString::try_from(&capability).unwrap();
+            let decoded = Capability::<Value>::try_from(&uri).unwrap();
+            prop_assert_eq!(
+                serde_jcs::to_string(&capability).unwrap(),
+                serde_jcs::to_string(&decoded).unwrap()
+            );
+        }
+
+        #[test]
+        fn statement_verify_roundtrip(capability in arb_capability(), statement in proptest::option::of("[A-Za-z ]{1,20}")) {
+            let msg = capability
+                .build_message(Message {
+                    domain: "example.com".parse().unwrap(),
+                    address: Default::default(),
+                    statement,
+                    uri: "did:key:example".parse().unwrap(),
+                    version: siwe::Version::V1,
+                    chain_id: 1,
+                    nonce: "mynonce1".into(),
+                    issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                    expiration_time: None,
+                    not_before: None,
+                    request_id: None,
+                    resources: vec![],
+                })
+                .unwrap();
+            let msg: Message = msg.to_string().parse().unwrap();
+            let verified = Capability::<Value>::extract_and_verify(&msg).unwrap();
+            prop_assert_eq!(
+                verified.map(|c| serde_jcs::to_string(&c).unwrap()),
+                (!capability.abilities().is_empty()).then(|| serde_jcs::to_string(&capability).unwrap())
+            );
+        }
+    }
 }
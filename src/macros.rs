@@ -0,0 +1,122 @@
+/// Create an [`AbilityStr`](crate::AbilityStr) constant from a string literal, validated at
+/// compile time like the constants of [`well_known`](crate::well_known).
+///
+/// ```
+/// const GET: siwe_recap::AbilityStr<'static> = siwe_recap::ability!("kv/get");
+/// assert_eq!(GET.to_string(), "kv/get");
+/// ```
+///
+/// ```compile_fail
+/// let ability = siwe_recap::ability!("kv/get put");
+/// ```
+#[macro_export]
+macro_rules! ability {
+    ($ability:literal) => {{
+        const ABILITY: $crate::AbilityStr<'static> = {
+            ::core::assert!(
+                $crate::__private::is_valid_ability($ability),
+                ::core::concat!("invalid ability: ", $ability)
+            );
+            $crate::__private::split_valid_ability($ability)
+        };
+        ABILITY
+    }};
+}
+
+/// Create an ability namespace constant from a string literal, validated at compile time like
+/// the namespaces of [`well_known`](crate::well_known).
+///
+/// ```
+/// assert_eq!(siwe_recap::namespace!("kv"), siwe_recap::well_known::kv::NAMESPACE);
+/// ```
+///
+/// ```compile_fail
+/// let namespace = siwe_recap::namespace!("kv/get");
+/// ```
+#[macro_export]
+macro_rules! namespace {
+    ($namespace:literal) => {{
+        const NAMESPACE: &str = {
+            ::core::assert!(
+                $crate::__private::is_valid_namespace($namespace),
+                ::core::concat!("invalid ability namespace: ", $namespace)
+            );
+            $namespace
+        };
+        NAMESPACE
+    }};
+}
+
+/// Create a target [`UriString`](iri_string::types::UriString) from a string literal, checked
+/// at compile time against the URI grammar of RFC 3986, so that parsing it cannot fail.
+///
+/// ```compile_fail
+/// let target = siwe_recap::target!("https://example.com/[x]");
+/// ```
+#[macro_export]
+macro_rules! target {
+    ($target:literal) => {{
+        const {
+            ::core::assert!(
+                $crate::__private::is_valid_target($target),
+                ::core::concat!("invalid target: ", $target)
+            )
+        };
+        <$crate::__private::UriString as ::core::str::FromStr>::from_str($target)
+            .expect("target was validated at compile time")
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    #[test]
+    fn literals() {
+        assert_eq!(crate::ability!("kv/get").to_string(), "kv/get");
+        assert_eq!(crate::namespace!("kv").to_string(), "kv");
+        assert_eq!(
+            crate::target!("kepler:ens:example.eth://default/kv").as_str(),
+            "kepler:ens:example.eth://default/kv"
+        );
+    }
+
+    #[test]
+    fn validation() {
+        use crate::ability::{is_valid_ability, is_valid_namespace, is_valid_target};
+        assert!(is_valid_ability("credential/*"));
+        assert!(!is_valid_ability("kv"));
+        assert!(!is_valid_ability("kv/"));
+        assert!(!is_valid_ability("kv/get put"));
+        assert!(is_valid_namespace("kv"));
+        assert!(!is_valid_namespace("kv/get"));
+        assert!(is_valid_target("https://example.com"));
+        assert!(!is_valid_target("example"));
+        assert!(!is_valid_target("https:"));
+        assert!(!is_valid_target("https://example.com/a b"));
+        assert!(is_valid_target("https://example.com/a%20b?q=1#f"));
+        assert!(!is_valid_target("https://example.com/%zz"));
+        assert!(!is_valid_target("https://example.com/é"));
+        assert!(!is_valid_target("https://example.com/<a>"));
+        assert!(!is_valid_target("https://example.com/[x]"));
+        assert!(!is_valid_target("https://example.com/a#b#c"));
+        assert!(!is_valid_target("https://exa mple.com"));
+        assert!(!is_valid_target("https://example.com:80a"));
+        assert!(!is_valid_target("https://[1:2]"));
+        assert!(!is_valid_target("https://[::1.2.3.256]"));
+        assert!(is_valid_target(
+            "https://user:pw@[::ffff:1.2.3.4]:8080/a?b/?#c?"
+        ));
+        assert!(is_valid_target("https://[v1.a:b]"));
+        assert!(is_valid_target("kepler:ens:example.eth://default/kv"));
+    }
+
+    proptest! {
+        #[test]
+        fn targets_parse(target in "[a-z]{1,3}:(//)?[a-z0-9:@\\[\\]/?#%.v]{0,12}") {
+            if crate::ability::is_valid_target(&target) {
+                prop_assert!(target.parse::<iri_string::types::UriString>().is_ok());
+            }
+        }
+    }
+}
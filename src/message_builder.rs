@@ -0,0 +1,351 @@
+use crate::{AsyncSigner, BuildError, BuildReport, Builder, GrantError, Signer, SigningError};
+use cid::Cid;
+use std::collections::BTreeMap;
+
+use http::uri::Authority;
+use iri_string::types::UriString;
+use serde::Serialize;
+use siwe::{Message, TimeStamp, Version};
+use time::OffsetDateTime;
+use ucan_capabilities_object::Ability;
+
+/// Fluent builder for a complete SIWE message carrying delegated capabilities.
+///
+/// The domain, address, URI and chain ID are required. A random nonce and the current issued-at
+/// time are used unless set explicitly.
+#[derive(Clone, Debug)]
+pub struct MessageBuilder<NB> {
+    capabilities: Builder<NB>,
+    domain: Option<Authority>,
+    address: Option<[u8; 20]>,
+    uri: Option<UriString>,
+    chain_id: Option<u64>,
+    statement: Option<String>,
+    nonce: Option<String>,
+    issued_at: Option<TimeStamp>,
+    expiration_time: Option<TimeStamp>,
+    not_before: Option<TimeStamp>,
+    request_id: Option<String>,
+    resources: Vec<UriString>,
+}
+
+impl<NB> MessageBuilder<NB> {
+    /// Create a new message builder with no capabilities.
+    pub fn new() -> Self {
+        Builder::new().into()
+    }
+
+    /// Set the capabilities to delegate in the message.
+    pub fn with_capabilities(mut self, capabilities: Builder<NB>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Add a supporting proof CID to the capabilities.
+    pub fn with_proof(mut self, proof: &Cid) -> Self {
+        self.capabilities = self.capabilities.with_proof(proof);
+        self
+    }
+
+    /// Set the domain requesting the signing.
+    pub fn domain(mut self, domain: Authority) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// Set the Ethereum address performing the signing.
+    pub fn address(mut self, address: [u8; 20]) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Set the URI which capabilities are delegated to.
+    pub fn uri(mut self, uri: UriString) -> Self {
+        self.uri = Some(uri);
+        self
+    }
+
+    /// Set the EIP-155 chain ID.
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Set the custom statement which precedes the ReCap statement, replacing any statement set
+    /// on the capabilities builder.
+    pub fn statement(mut self, statement: impl Into<String>) -> Self {
+        self.statement = Some(statement.into());
+        self
+    }
+
+    /// Set the nonce, instead of generating a random one.
+    pub fn nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Set the issued-at time, instead of using the current time.
+    pub fn issued_at(mut self, issued_at: TimeStamp) -> Self {
+        self.issued_at = Some(issued_at);
+        self
+    }
+
+    /// Set the expiration time.
+    pub fn expiration_time(mut self, expiration_time: TimeStamp) -> Self {
+        self.expiration_time = Some(expiration_time);
+        self
+    }
+
+    /// Set the time before which the message is not valid.
+    pub fn not_before(mut self, not_before: TimeStamp) -> Self {
+        self.not_before = Some(not_before);
+        self
+    }
+
+    /// Set the request ID.
+    pub fn request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Add a resource, which will precede the ReCap resource.
+    pub fn resource(mut self, resource: UriString) -> Self {
+        self.resources.push(resource);
+        self
+    }
+}
+
+impl<NB> MessageBuilder<NB>
+where
+    NB: Clone,
+{
+    /// Add an allowed action for the given target to the capabilities, as
+    /// [`Builder::with_action`] does.
+    pub fn with_action(
+        mut self,
+        target: UriString,
+        action: Ability,
+        nb: impl IntoIterator<Item = BTreeMap<String, NB>>,
+    ) -> Result<Self, BuildError> {
+        self.capabilities = self.capabilities.with_action(target, action, nb)?;
+        Ok(self)
+    }
+
+    /// Add an allowed action for the given target to the capabilities, as
+    /// [`Builder::with_action_convert`] does.
+    pub fn with_action_convert<T, A>(
+        mut self,
+        target: T,
+        action: A,
+        nb: impl IntoIterator<Item = BTreeMap<String, NB>>,
+    ) -> Result<Self, GrantError<T::Error, A::Error>>
+    where
+        T: TryInto<UriString>,
+        A: TryInto<Ability>,
+    {
+        self.capabilities = self.capabilities.with_action_convert(target, action, nb)?;
+        Ok(self)
+    }
+}
+
+impl<NB> MessageBuilder<NB>
+where
+    NB: Serialize,
+{
+    pub(crate) fn into_parts(self) -> Result<(Builder<NB>, Message), BuildReport> {
+        let mut problems: Vec<BuildError> = [
+            ("domain", self.domain.is_none()),
+            ("address", self.address.is_none()),
+            ("uri", self.uri.is_none()),
+            ("chain_id", self.chain_id.is_none()),
+        ]
+        .into_iter()
+        .filter(|(_, missing)| *missing)
+        .map(|(field, _)| BuildError::MissingField(field))
+        .collect();
+        if !problems.is_empty() {
+            problems.extend(self.capabilities.problems());
+            BuildReport::from_errors(problems)?;
+        }
+        let mut capabilities = self.capabilities;
+        if let Some(statement) = self.statement {
+            capabilities = capabilities.with_statement(statement);
+        }
+        let issued_at = match self.issued_at {
+            Some(issued_at) => issued_at,
+            None => {
+                capabilities = capabilities.with_issued_now();
+                OffsetDateTime::UNIX_EPOCH.into()
+            }
+        };
+        let message = Message {
+            domain: self.domain.ok_or(BuildError::MissingField("domain"))?,
+            address: self.address.ok_or(BuildError::MissingField("address"))?,
+            statement: None,
+            uri: self.uri.ok_or(BuildError::MissingField("uri"))?,
+            version: Version::V1,
+            chain_id: self.chain_id.ok_or(BuildError::MissingField("chain_id"))?,
+            nonce: self.nonce.unwrap_or_default(),
+            issued_at,
+            expiration_time: self.expiration_time,
+            not_before: self.not_before,
+            request_id: self.request_id,
+            resources: self.resources,
+        };
+        Ok((capabilities, message))
+    }
+
+    /// Build the SIWE message.
+    ///
+    /// All problems with the builder, including missing fields, are reported together.
+    pub fn build(self) -> Result<Message, BuildReport> {
+        let (capabilities, message) = self.into_parts()?;
+        capabilities.build(message)
+    }
+
+    /// Build the SIWE message and sign it, returning the message along with its signature.
+    pub fn build_and_sign<S>(self, signer: &S) -> Result<(Message, Vec<u8>), SigningError<S::Error>>
+    where
+        S: Signer,
+    {
+        let (capabilities, message) = self.into_parts()?;
+        capabilities.build_and_sign(message, signer)
+    }
+
+    /// Build the SIWE message and sign it with an asynchronous signer, returning the message along with its signature.
+    pub async fn build_and_sign_async<S>(
+        self,
+        signer: &S,
+    ) -> Result<(Message, Vec<u8>), SigningError<S::Error>>
+    where
+        S: AsyncSigner,
+    {
+        let (capabilities, message) = self.into_parts()?;
+        capabilities.build_and_sign_async(message, signer).await
+    }
+}
+
+impl<NB> Default for MessageBuilder<NB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NB> From<Builder<NB>> for MessageBuilder<NB> {
+    fn from(capabilities: Builder<NB>) -> Self {
+        Self {
+            capabilities,
+            domain: None,
+            address: None,
+            uri: None,
+            chain_id: None,
+            statement: None,
+            nonce: None,
+            issued_at: None,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn build_message() {
+        let msg = MessageBuilder::<Value>::new()
+            .with_capabilities(
+                Builder::new()
+                    .with_action_convert("credential:*", "credential/present", [])
+                    .unwrap(),
+            )
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .statement("Some custom statement.")
+            .nonce("mynonce1")
+            .issued_at("2022-06-21T12:00:00.000Z".parse().unwrap())
+            .resource("http://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            include_str!("../tests/siwe_with_statement.txt").trim(),
+            msg.to_string()
+        );
+
+        // the statement is kept when the capabilities are set afterwards
+        let reordered = MessageBuilder::<Value>::new()
+            .statement("Some custom statement.")
+            .with_capabilities(
+                Builder::new()
+                    .with_action_convert("credential:*", "credential/present", [])
+                    .unwrap(),
+            )
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .nonce("mynonce1")
+            .issued_at("2022-06-21T12:00:00.000Z".parse().unwrap())
+            .resource("http://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(reordered, msg);
+
+        // grants can be added on the message builder directly
+        let direct = MessageBuilder::<Value>::new()
+            .with_action_convert("credential:*", "credential/present", [])
+            .unwrap()
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .statement("Some custom statement.")
+            .nonce("mynonce1")
+            .issued_at("2022-06-21T12:00:00.000Z".parse().unwrap())
+            .resource("http://example.com".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(direct, msg);
+        let with_proof = MessageBuilder::<Value>::new()
+            .with_action(
+                "credential:*".parse().unwrap(),
+                "credential/present".parse().unwrap(),
+                [],
+            )
+            .unwrap()
+            .with_proof(&Cid::default())
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .build()
+            .unwrap();
+        assert_eq!(
+            crate::Capability::<Value>::extract_and_verify(&with_proof)
+                .unwrap()
+                .unwrap()
+                .proof(),
+            [Cid::default()]
+        );
+
+        assert!(matches!(
+            MessageBuilder::<Value>::new()
+                .domain("example.com".parse().unwrap())
+                .build()
+                .unwrap_err()
+                .errors(),
+            [
+                BuildError::MissingField("address"),
+                BuildError::MissingField("uri"),
+                BuildError::MissingField("chain_id")
+            ]
+        ));
+    }
+}
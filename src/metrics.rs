@@ -0,0 +1,99 @@
+//! Metrics emitted through the [`metrics`](::metrics) facade when sessions are verified, so that
+//! operators can dashboard the health of ReCap authentication by installing a recorder.
+
+use crate::{SessionError, VerifiedSession, RESOURCE_PREFIX};
+use std::time::Duration;
+
+use ::metrics::{counter, histogram};
+
+/// Counter of attempted session verifications.
+pub const VERIFICATIONS: &str = "recap_verifications_total";
+/// Counter of failed session verifications, labelled by the [code](SessionError::code) of the
+/// error.
+pub const VERIFICATION_FAILURES: &str = "recap_verification_failures_total";
+/// Histogram of the time taken to verify a session, in seconds.
+pub const VERIFICATION_DURATION: &str = "recap_verification_duration_seconds";
+/// Histogram of the number of abilities granted by verified sessions, counted once per target.
+pub const GRANTS: &str = "recap_grants";
+/// Histogram of the size in bytes of the ReCap resource URIs of verified sessions.
+pub const RESOURCE_SIZE: &str = "recap_resource_bytes";
+
+pub(crate) fn record_verification<NB>(
+    result: &Result<VerifiedSession<NB>, SessionError>,
+    elapsed: Duration,
+) {
+    counter!(VERIFICATIONS).increment(1);
+    histogram!(VERIFICATION_DURATION).record(elapsed.as_secs_f64());
+    match result {
+        Ok(session) => {
+            let grants: usize = session
+                .capability()
+                .abilities()
+                .values()
+                .map(|abilities| abilities.len())
+                .sum();
+            histogram!(GRANTS).record(grants as f64);
+            if let Some(recap) = session
+                .message()
+                .resources
+                .last()
+                .filter(|uri| uri.as_str().starts_with(RESOURCE_PREFIX))
+            {
+                histogram!(RESOURCE_SIZE).record(recap.as_str().len() as f64);
+            }
+        }
+        Err(error) => counter!(VERIFICATION_FAILURES, "code" => error.code()).increment(1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, sign, verification_options};
+    use crate::{Builder, SessionHeader};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use serde_json::Value;
+
+    #[test]
+    fn verification_metrics() {
+        let message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_action_convert("https://example.com/kv", "kv/put", [])
+            .unwrap()
+            .build(message())
+            .unwrap();
+        let (message, signature) = sign(message);
+        let options = verification_options();
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            SessionHeader::new(message.clone(), signature)
+                .verify::<Value>(&options)
+                .unwrap();
+            SessionHeader::new(message, vec![0; 65])
+                .verify::<Value>(&options)
+                .unwrap_err();
+        });
+
+        let metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels: Vec<_> = key.labels().map(|l| l.value().to_string()).collect();
+                (key.name().to_string(), labels, value)
+            })
+            .collect();
+        assert!(metrics.contains(&(VERIFICATIONS.into(), vec![], DebugValue::Counter(2))));
+        assert!(metrics.contains(&(
+            VERIFICATION_FAILURES.into(),
+            vec!["invalid_signature".into()],
+            DebugValue::Counter(1)
+        )));
+        assert!(metrics.iter().any(|(name, _, value)| name == GRANTS
+            && matches!(value, DebugValue::Histogram(v) if v.len() == 1 && v[0] == 2.0)));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{BuildError, Builder, Capability};
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+/// A typed nota bene for a specific ability, serialized into the nota bene map of its grant.
+///
+/// Namespaces can provide implementations of this trait (e.g. `kv_put().max_size(10_000_000)`)
+/// so that constrained grants don't have to be written as raw maps. Fields which serialize to
+/// `null` are omitted.
+pub trait TypedNotaBene: Serialize {
+    /// The ability this nota bene applies to, e.g. `kv/put`.
+    const ABILITY: &'static str;
+
+    /// Serialize into a nota bene map.
+    fn to_nota_bene<NB>(&self) -> Result<BTreeMap<String, NB>, serde_json::Error>
+    where
+        NB: DeserializeOwned,
+    {
+        match serde_json::to_value(self)? {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok((k, serde_json::from_value(v)?)))
+                .collect(),
+            _ => Err(serde::ser::Error::custom(
+                "nota bene must serialize to a map",
+            )),
+        }
+    }
+}
+
+impl<NB> Builder<NB>
+where
+    NB: DeserializeOwned + Clone,
+{
+    /// Add an allowed action for the given target, constrained by a typed nota bene.
+    pub fn with_nota_bene<N>(self, target: UriString, nota_bene: &N) -> Result<Self, BuildError>
+    where
+        N: TypedNotaBene,
+    {
+        let invalid = |reason: String| BuildError::InvalidNotaBene {
+            ability: N::ABILITY.to_string(),
+            reason,
+        };
+        let ability: Ability = N::ABILITY
+            .parse()
+            .map_err(|_| invalid("invalid ability".to_string()))?;
+        let nb = nota_bene
+            .to_nota_bene()
+            .map_err(|e| invalid(e.to_string()))?;
+        self.with_action(target, ability, [nb])
+    }
+}
+
+/// The reserved nota bene key under which extra fields are stored.
+pub const EXTRA_FIELDS_KEY: &str = "extra_fields";
+
+impl<NB> Builder<NB>
+where
+    NB: DeserializeOwned + Clone,
+{
+    /// Add an allowed action for the given target, carrying arbitrary application metadata.
+    ///
+    /// The fields are stored in the nota bene of the grant under [`EXTRA_FIELDS_KEY`], and can be
+    /// read back with [`Capability::extra_fields`].
+    pub fn with_extra_fields<E>(
+        self,
+        target: UriString,
+        ability: Ability,
+        fields: &E,
+    ) -> Result<Self, BuildError>
+    where
+        E: Serialize,
+    {
+        let fields = serde_json::to_value(fields)
+            .and_then(serde_json::from_value)
+            .map_err(|e| BuildError::InvalidNotaBene {
+                ability: ability.to_string(),
+                reason: e.to_string(),
+            })?;
+        self.with_action(
+            target,
+            ability,
+            [BTreeMap::from([(EXTRA_FIELDS_KEY.to_string(), fields)])],
+        )
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: Serialize,
+{
+    /// Read the extra fields attached to a grant, if any.
+    ///
+    /// The fields of the first nota bene carrying [`EXTRA_FIELDS_KEY`] are returned.
+    pub fn extra_fields<E>(
+        &self,
+        target: &UriString,
+        ability: &Ability,
+    ) -> Result<Option<E>, serde_json::Error>
+    where
+        E: DeserializeOwned,
+    {
+        self.can_do(target, ability)
+            .and_then(|nbs| nbs.iter().find_map(|nb| nb.get(EXTRA_FIELDS_KEY)))
+            .map(|fields| serde_json::to_value(fields).and_then(serde_json::from_value))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[derive(Serialize, Default)]
+    struct KvPut {
+        max_size: Option<u64>,
+    }
+
+    impl KvPut {
+        fn max_size(mut self, max_size: u64) -> Self {
+            self.max_size = Some(max_size);
+            self
+        }
+    }
+
+    impl TypedNotaBene for KvPut {
+        const ABILITY: &'static str = "kv/put";
+    }
+
+    fn kv_put() -> KvPut {
+        KvPut::default()
+    }
+
+    #[test]
+    fn typed_nota_bene() {
+        let target: UriString = "kepler:ens:example.eth://default/kv".parse().unwrap();
+        let builder = Builder::<Value>::new()
+            .with_nota_bene(target.clone(), &kv_put().max_size(10_000_000))
+            .unwrap();
+        let nbs = builder
+            .capability()
+            .can_do(&target, &"kv/put".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            nbs.first().and_then(|nb| nb.get("max_size")),
+            Some(&Value::from(10_000_000))
+        );
+
+        let nb: BTreeMap<String, Value> = kv_put().to_nota_bene().unwrap();
+        assert!(nb.is_empty());
+    }
+
+    #[test]
+    fn extra_fields() {
+        let target: UriString = "kepler:ens:example.eth://default/kv".parse().unwrap();
+        let ability: Ability = "kv/get".parse().unwrap();
+        let fields = BTreeMap::from([("app".to_string(), "example".to_string())]);
+        let capability = Builder::<Value>::new()
+            .with_extra_fields(target.clone(), ability.clone(), &fields)
+            .unwrap()
+            .into_capability();
+        assert_eq!(
+            capability.extra_fields(&target, &ability).unwrap(),
+            Some(fields)
+        );
+        assert_eq!(
+            capability
+                .extra_fields::<Value>(&target, &"kv/put".parse().unwrap())
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{Capability, SessionError, SessionHeader, SESSION_SCHEME};
+use std::borrow::Cow;
+
+use utoipa::openapi::schema::{
+    AdditionalProperties, ArrayBuilder, ObjectBuilder, Schema, SchemaType, Type,
+};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+
+fn map_of(values: impl Into<RefOr<Schema>>) -> ObjectBuilder {
+    ObjectBuilder::new()
+        .schema_type(SchemaType::new(Type::Object))
+        .additional_properties(Some(AdditionalProperties::RefOr(values.into())))
+}
+
+/// Capabilities are documented as the ReCap object: `att` maps targets to abilities to nota
+/// benes, and `prf` lists the CIDs of the proofs.
+impl<NB> PartialSchema for Capability<NB> {
+    fn schema() -> RefOr<Schema> {
+        let nota_benes = ArrayBuilder::new().items(
+            ObjectBuilder::new()
+                .schema_type(SchemaType::new(Type::Object))
+                .description(Some("A nota bene, restricting the ability.")),
+        );
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::Object))
+            .description(Some("A ReCap capability object."))
+            .property(
+                "att",
+                map_of(map_of(nota_benes)).description(Some(
+                    "The abilities granted on each target URI, along with their nota benes.",
+                )),
+            )
+            .property(
+                "prf",
+                ArrayBuilder::new()
+                    .items(ObjectBuilder::new().schema_type(SchemaType::new(Type::String)))
+                    .description(Some("The CIDs of the supporting proofs.")),
+            )
+            .required("att")
+            .required("prf")
+            .into()
+    }
+}
+
+impl<NB> ToSchema for Capability<NB> {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("Recap")
+    }
+}
+
+/// Session headers are documented as the `Authorization` header value.
+impl PartialSchema for SessionHeader {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::String))
+            .description(Some(
+                "A signed SIWE message: SIWE <base64url(message)>.<base64url(signature)>",
+            ))
+            .pattern(Some(format!(
+                "^{SESSION_SCHEME} [A-Za-z0-9_-]+\\.[A-Za-z0-9_-]+$"
+            )))
+            .into()
+    }
+}
+
+impl ToSchema for SessionHeader {}
+
+impl SessionHeader {
+    /// The OpenAPI security scheme of session headers, for use with
+    /// `Components::add_security_scheme`.
+    pub fn security_scheme() -> SecurityScheme {
+        SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+            "Authorization",
+            &format!("{SESSION_SCHEME} <base64url(message)>.<base64url(signature)>"),
+        )))
+    }
+}
+
+/// Session errors are documented as the JSON body of error responses.
+impl PartialSchema for SessionError {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::Object))
+            .property(
+                "error",
+                ObjectBuilder::new()
+                    .schema_type(SchemaType::new(Type::String))
+                    .enum_values(Some(Self::CODES.iter().copied())),
+            )
+            .property(
+                "message",
+                ObjectBuilder::new().schema_type(SchemaType::new(Type::String)),
+            )
+            .required("error")
+            .required("message")
+            .into()
+    }
+}
+
+impl ToSchema for SessionError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn schemas() {
+        let schema = serde_json::to_value(Capability::<Value>::schema()).unwrap();
+        assert_eq!(schema["required"], serde_json::json!(["att", "prf"]));
+        assert_eq!(Capability::<Value>::name(), "Recap");
+
+        let schema = serde_json::to_value(SessionError::schema()).unwrap();
+        assert_eq!(
+            schema["properties"]["error"]["enum"],
+            serde_json::json!(SessionError::CODES)
+        );
+        assert_eq!(
+            SessionError::CODES,
+            [
+                SessionError::Missing,
+                SessionError::Scheme,
+                SessionError::Format,
+                SessionError::Message(siwe::ParseError::Format("")),
+                SessionError::Siwe(siwe::VerificationError::Time),
+                SessionError::Siwe(siwe::VerificationError::DomainMismatch),
+                SessionError::Siwe(siwe::VerificationError::SignatureLength),
+                SessionError::Capability(crate::VerificationError::IncorrectStatement(
+                    String::new()
+                )),
+                SessionError::MissingCapability {
+                    target: "https://example.com".parse().unwrap(),
+                    action: "kv/get".parse().unwrap(),
+                },
+            ]
+            .map(|e| e.code())
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{base64url, DecodingError, RESOURCE_PREFIX};
+use std::cmp::Ordering;
+use std::fmt;
+
+use iri_string::types::UriString;
+use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Deserialize;
+
+/// How the target and ability keys of the `att` object of an encoded ReCap are ordered.
+///
+/// Targets must be in JCS order. Abilities may be in JCS order, as this crate produces, or ordered
+/// by length then bytes, as produced by implementations which sort them like DAG-CBOR map keys.
+/// Keys in any other order, e.g. insertion order, are a sign that the encoder will disagree with
+/// verifiers about the statement.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttOrdering {
+    /// All keys are in JCS order.
+    Canonical,
+    /// Targets are in JCS order and abilities are ordered by length then bytes.
+    LengthFirst,
+    /// The given target or ability key is out of order.
+    Unordered { key: String },
+}
+
+impl AttOrdering {
+    /// Check the order of the keys of a ReCap URI, as they appear in its encoded JSON.
+    pub fn of(uri: &UriString) -> Result<Self, DecodingError> {
+        uri.as_str()
+            .strip_prefix(RESOURCE_PREFIX)
+            .ok_or_else(|| DecodingError::InvalidResourcePrefix(uri.to_string()))
+            .and_then(|encoded| Self::of_json(&base64url::decode(encoded)?))
+    }
+
+    pub(crate) fn of_json(json: &[u8]) -> Result<Self, DecodingError> {
+        #[derive(Deserialize)]
+        struct Payload {
+            att: Keys<Keys<IgnoredAny>>,
+        }
+        let Payload { att } = serde_json::from_slice(json)?;
+        if let Some(target) = out_of_order(&att.0, jcs_order) {
+            return Ok(Self::Unordered {
+                key: target.0.clone(),
+            });
+        }
+        let abilities = || att.0.iter().map(|(_, abilities)| &abilities.0);
+        if abilities().all(|keys| out_of_order(keys, jcs_order).is_none()) {
+            return Ok(Self::Canonical);
+        }
+        Ok(abilities()
+            .find_map(|keys| out_of_order(keys, length_first_order))
+            .map_or(Self::LengthFirst, |ability| Self::Unordered {
+                key: ability.0.clone(),
+            }))
+    }
+
+    /// Reject keys which are in neither order.
+    pub(crate) fn check(json: &[u8]) -> Result<(), DecodingError> {
+        match Self::of_json(json)? {
+            Self::Unordered { key } => Err(DecodingError::UnorderedKey(key)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// JCS orders keys by their UTF-16 code units.
+fn jcs_order(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+fn length_first_order(a: &str, b: &str) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// The first key which does not follow its predecessor in the given order.
+fn out_of_order<V>(
+    keys: &[(String, V)],
+    order: fn(&str, &str) -> Ordering,
+) -> Option<&(String, V)> {
+    keys.windows(2)
+        .find(|pair| order(&pair[0].0, &pair[1].0) != Ordering::Less)
+        .map(|pair| &pair[1])
+}
+
+/// The entries of a JSON object, in the order in which they appear.
+struct Keys<V>(Vec<(String, V)>);
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for Keys<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor<V>(std::marker::PhantomData<V>);
+
+        impl<'de, V: Deserialize<'de>> Visitor<'de> for KeysVisitor<V> {
+            type Value = Keys<V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Keys(entries))
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor(std::marker::PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ordering(json: &str) -> AttOrdering {
+        AttOrdering::of(
+            &format!("{RESOURCE_PREFIX}{}", base64url::encode(json))
+                .parse()
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn att_ordering() {
+        let canonical = include_str!("../tests/serialized_cap.json");
+        assert_eq!(ordering(canonical), AttOrdering::Canonical);
+        assert_eq!(
+            ordering(
+                r#"{"att":{"https://a":{"kv/get":[{}],"kv/put":[{}],"kv/list":[{}]}},"prf":[]}"#
+            ),
+            AttOrdering::LengthFirst
+        );
+        assert_eq!(
+            ordering(
+                r#"{"att":{"https://b":{"kv/get":[{}]},"https://a":{"kv/get":[{}]}},"prf":[]}"#
+            ),
+            AttOrdering::Unordered {
+                key: "https://a".into()
+            }
+        );
+        assert_eq!(
+            ordering(
+                r#"{"att":{"https://a":{"kv/put":[{}],"kv/list":[{}],"kv/get":[{}]}},"prf":[]}"#
+            ),
+            AttOrdering::Unordered {
+                key: "kv/get".into()
+            }
+        );
+    }
+}
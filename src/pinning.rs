@@ -0,0 +1,238 @@
+use crate::{Capability, EncodingError, ProofStore};
+use cid::Cid;
+use std::future::Future;
+
+use serde::Serialize;
+
+/// A backend publishing and pinning blocks, e.g. an IPFS node or a pinning service, so that the
+/// proofs of a capability remain resolvable by re-delegation targets.
+pub trait PinningBackend {
+    type Error;
+
+    /// Publish and pin the block with the given CID.
+    fn pin(&self, cid: &Cid, block: &[u8]) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+impl<NB> Capability<NB>
+where
+    NB: Serialize,
+{
+    /// Pin each proof block, read from the proof store, followed by the block of the capabilities
+    /// themselves, returning the pinned CIDs in that order.
+    pub async fn pin<B, S>(
+        &self,
+        backend: &B,
+        proofs: &S,
+    ) -> Result<Vec<Cid>, PinError<B::Error, S::Error>>
+    where
+        B: PinningBackend,
+        S: ProofStore,
+    {
+        let mut pinned = Vec::with_capacity(self.proof().len() + 1);
+        for cid in self.proof() {
+            let block = proofs
+                .read(cid)
+                .await
+                .map_err(PinError::Store)?
+                .ok_or(PinError::MissingProof(*cid))?;
+            backend.pin(cid, &block).await.map_err(PinError::Backend)?;
+            pinned.push(*cid);
+        }
+        let (cid, block) = self.to_block().map_err(PinError::Encode)?;
+        backend.pin(&cid, &block).await.map_err(PinError::Backend)?;
+        pinned.push(cid);
+        Ok(pinned)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PinError<B, S> {
+    #[error("failed to pin block: {0}")]
+    Backend(B),
+    #[error("proof store error: {0}")]
+    Store(S),
+    #[error("proof {0} is not held by the proof store")]
+    MissingProof(Cid),
+    #[error(transparent)]
+    Encode(EncodingError),
+}
+
+/// Pins blocks by uploading them to the HTTP RPC API of an IPFS node, e.g. Kubo.
+#[cfg(feature = "ipfs")]
+#[derive(Clone, Debug)]
+pub struct IpfsHttpBackend {
+    client: reqwest::Client,
+    api: String,
+}
+
+#[cfg(feature = "ipfs")]
+impl IpfsHttpBackend {
+    /// Use the API at the given base URL, e.g. `http://127.0.0.1:5001`.
+    pub fn new(api: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api: api.into(),
+        }
+    }
+
+    /// Use the given HTTP client, e.g. one configured with authentication.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[cfg(feature = "ipfs")]
+impl PinningBackend for IpfsHttpBackend {
+    type Error = IpfsError;
+
+    async fn pin(&self, cid: &Cid, block: &[u8]) -> Result<(), Self::Error> {
+        #[derive(serde::Deserialize)]
+        struct BlockPut {
+            #[serde(rename = "Key")]
+            key: String,
+        }
+
+        let codec = match cid.codec() {
+            0x55 => "raw",
+            0x71 => "dag-cbor",
+            0x0129 => "dag-json",
+            _ => return Err(IpfsError::UnsupportedCid(*cid)),
+        };
+        let hash = match cid.hash().code() {
+            0x12 => "sha2-256",
+            0x1e => "blake3",
+            _ => return Err(IpfsError::UnsupportedCid(*cid)),
+        };
+        let form = reqwest::multipart::Form::new()
+            .part("file", reqwest::multipart::Part::bytes(block.to_vec()));
+        let response: BlockPut = self
+            .client
+            .post(format!(
+                "{}/api/v0/block/put",
+                self.api.trim_end_matches('/')
+            ))
+            .query(&[("cid-codec", codec), ("mhtype", hash), ("pin", "true")])
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match response.key.parse::<Cid>() {
+            Ok(found) if found == *cid => Ok(()),
+            _ => Err(IpfsError::CidMismatch {
+                expected: *cid,
+                found: response.key,
+            }),
+        }
+    }
+}
+
+/// Pins blocks through the IPFS Pinning Service API.
+///
+/// The service fetches the blocks from the IPFS network, so they must also be provided by a node,
+/// e.g. one of the given origins.
+#[cfg(feature = "ipfs")]
+#[derive(Clone, Debug)]
+pub struct PinningServiceBackend {
+    client: reqwest::Client,
+    endpoint: String,
+    token: String,
+    origins: Vec<String>,
+}
+
+#[cfg(feature = "ipfs")]
+impl PinningServiceBackend {
+    /// Use the service at the given endpoint, e.g. `https://api.pinata.cloud/psa`, with an access
+    /// token.
+    pub fn new(endpoint: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into(),
+            token: token.into(),
+            origins: Vec::new(),
+        }
+    }
+
+    /// Add the multiaddr of a node providing the blocks.
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+}
+
+#[cfg(feature = "ipfs")]
+impl PinningBackend for PinningServiceBackend {
+    type Error = IpfsError;
+
+    async fn pin(&self, cid: &Cid, _block: &[u8]) -> Result<(), Self::Error> {
+        self.client
+            .post(format!("{}/pins", self.endpoint.trim_end_matches('/')))
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({
+                "cid": cid.to_string(),
+                "origins": self.origins,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ipfs")]
+#[derive(thiserror::Error, Debug)]
+pub enum IpfsError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("unsupported codec or hash in {0}")]
+    UnsupportedCid(Cid),
+    #[error("pinned {found} rather than {expected}")]
+    CidMismatch { expected: Cid, found: String },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::MemoryProofStore;
+    use serde_json::Value;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Pins(RefCell<Vec<Cid>>);
+
+    impl PinningBackend for Pins {
+        type Error = std::convert::Infallible;
+
+        async fn pin(&self, cid: &Cid, _: &[u8]) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push(*cid);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn pin_chain() {
+        let mut parent = Capability::<Value>::new();
+        parent
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let parent_cid = parent.to_cid().unwrap();
+        let mut child = Capability::<Value>::new().with_proofs(&[parent_cid]);
+        child
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+
+        let pins = Pins::default();
+        assert!(matches!(
+            child.pin(&pins, &MemoryProofStore::default()).await,
+            Err(PinError::MissingProof(cid)) if cid == parent_cid
+        ));
+
+        let mut proofs = MemoryProofStore::default();
+        assert_eq!(parent.write_to(&mut proofs).await.unwrap(), parent_cid);
+        let pinned = child.pin(&pins, &proofs).await.unwrap();
+        assert_eq!(pinned, vec![parent_cid, child.to_cid().unwrap()]);
+        assert_eq!(*pins.0.borrow(), pinned);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::Capability;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use iri_string::types::UriString;
+use ucan_capabilities_object::Ability;
+
+/// Human-friendly descriptions of abilities and targets, used to render plain-language consent text.
+#[derive(Clone, Debug, Default)]
+pub struct AbilityDescriptions {
+    abilities: BTreeMap<Ability, String>,
+    targets: BTreeMap<UriString, String>,
+}
+
+impl AbilityDescriptions {
+    /// Create a new empty set of descriptions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describe an ability with a verb phrase, e.g. "read" for `kv/get`.
+    pub fn with_ability(mut self, ability: Ability, description: impl Into<String>) -> Self {
+        self.abilities.insert(ability, description.into());
+        self
+    }
+
+    /// Describe an ability with a verb phrase.
+    ///
+    /// This method automatically converts the provided ability into the correct type for convenience.
+    pub fn with_ability_convert<A>(
+        self,
+        ability: A,
+        description: impl Into<String>,
+    ) -> Result<Self, A::Error>
+    where
+        A: TryInto<Ability>,
+    {
+        Ok(self.with_ability(ability.try_into()?, description))
+    }
+
+    /// Describe a target with a noun phrase, e.g. "files in your public storage".
+    pub fn with_target(mut self, target: UriString, description: impl Into<String>) -> Self {
+        self.targets.insert(target, description.into());
+        self
+    }
+
+    /// Describe a target with a noun phrase.
+    ///
+    /// This method automatically converts the provided target into the correct type for convenience.
+    pub fn with_target_convert<T>(
+        self,
+        target: T,
+        description: impl Into<String>,
+    ) -> Result<Self, T::Error>
+    where
+        T: TryInto<UriString>,
+    {
+        Ok(self.with_target(target.try_into()?, description))
+    }
+
+    /// Describe an ability, keeping its namespace if the name alone is ambiguous among the
+    /// abilities being described together.
+    fn describe_ability(&self, ability: &Ability, ambiguous: bool) -> String {
+        if let Some(description) = self.abilities.get(ability) {
+            return description.clone();
+        }
+        match ability.name().to_string().as_str() {
+            "*" if ambiguous => format!("perform any {} action", humanize(&namespace_of(ability))),
+            "*" => "perform any action".to_string(),
+            name if ambiguous => {
+                format!("{} ({})", humanize(name), humanize(&namespace_of(ability)))
+            }
+            name => humanize(name),
+        }
+    }
+
+    fn describe_target(&self, target: &UriString) -> String {
+        if let Some(description) = self.targets.get(target) {
+            return description.clone();
+        }
+        let (location, path) = split_location(target);
+        let resource = match last_segment(path) {
+            "" | "*" => "any resource".to_string(),
+            segment => format!("the {} resource", humanize(segment)),
+        };
+        match location {
+            Some(location) => format!("{resource} on {location}"),
+            None => resource,
+        }
+    }
+}
+
+fn namespace_of(ability: &Ability) -> String {
+    ability.namespace().to_string()
+}
+
+/// Split a target into where it is hosted, i.e. its authority or the id of a Kepler-style orbit
+/// (`kepler:ens:example.eth://default/…`), and the path within it.
+fn split_location(target: &UriString) -> (Option<&str>, &str) {
+    if let Some(authority) = target.authority_str().filter(|a| !a.is_empty()) {
+        return (Some(authority), target.path_str());
+    }
+    match target.path_str().split_once("://") {
+        Some((orbit, path)) => {
+            let id = orbit.rsplit(':').next().unwrap_or(orbit);
+            (Some(id).filter(|id| !id.is_empty()), path)
+        }
+        None => (None, target.path_str()),
+    }
+}
+
+/// Take the most specific component of a path, dropping any URN/path prefix.
+fn last_segment(path: &str) -> &str {
+    let path = path.trim_end_matches('/');
+    let segment = path.rsplit('/').next().unwrap_or(path);
+    segment.rsplit(':').next().unwrap_or(segment)
+}
+
+fn humanize(s: &str) -> String {
+    s.replace(['-', '_', '.'], " ")
+}
+
+fn join_phrases(phrases: &[String]) -> String {
+    match phrases {
+        [] => String::new(),
+        [only] => only.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// Describe the granted capabilities as full sentences suitable for screen readers, using default descriptions.
+    pub fn to_plain_language(&self, delegee: &str) -> String {
+        self.to_plain_language_with(delegee, &AbilityDescriptions::default())
+    }
+
+    /// Describe the granted capabilities as full sentences suitable for screen readers.
+    ///
+    /// Abilities and targets without a registered description fall back to their most specific
+    /// component, so no quotes, URNs or slash notation appear in the output. Targets keep the host
+    /// or orbit they are on, and abilities keep their namespace if their names collide.
+    pub fn to_plain_language_with(
+        &self,
+        delegee: &str,
+        descriptions: &AbilityDescriptions,
+    ) -> String {
+        self.abilities()
+            .iter()
+            .map(|(target, abilities)| {
+                let mut namespaces = BTreeMap::<String, BTreeSet<String>>::new();
+                for ability in abilities.keys() {
+                    namespaces
+                        .entry(ability.name().to_string())
+                        .or_default()
+                        .insert(namespace_of(ability));
+                }
+                let mut seen = HashSet::new();
+                let verbs: Vec<String> = abilities
+                    .keys()
+                    .map(|ability| {
+                        let ambiguous = namespaces[&ability.name().to_string()].len() > 1;
+                        descriptions.describe_ability(ability, ambiguous)
+                    })
+                    .filter(|verb| seen.insert(verb.clone()))
+                    .collect();
+                format!(
+                    "Allow {delegee} to {} {}.",
+                    join_phrases(&verbs),
+                    descriptions.describe_target(target)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn plain_language() {
+        let mut cap = Capability::<Value>::default();
+        cap.with_actions_convert(
+            "kepler:ens:example.eth://default/kv/public",
+            [("kv/get", []), ("kv/list", [])],
+        )
+        .unwrap()
+        .with_action_convert("urn:credential:type:type1", "credential/present", [])
+        .unwrap();
+
+        assert_eq!(
+            cap.to_plain_language("example.com"),
+            "Allow example.com to get and list the public resource on example.eth. Allow example.com to present the type1 resource."
+        );
+
+        let descriptions = AbilityDescriptions::new()
+            .with_ability_convert("kv/get", "read")
+            .unwrap()
+            .with_target_convert(
+                "kepler:ens:example.eth://default/kv/public",
+                "files in your public storage",
+            )
+            .unwrap();
+        assert!(cap
+            .to_plain_language_with("example.com", &descriptions)
+            .starts_with("Allow example.com to read and list files in your public storage."));
+
+        let mut cap = Capability::<Value>::default();
+        cap.with_actions_convert(
+            "https://example.com/docs",
+            [("kv/get", []), ("credential/get", []), ("kv/put", [])],
+        )
+        .unwrap();
+        assert_eq!(
+            cap.to_plain_language("example.org"),
+            "Allow example.org to get (credential), get (kv) and put the docs resource on example.com."
+        );
+    }
+}
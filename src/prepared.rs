@@ -0,0 +1,352 @@
+use crate::{
+    BuildError, BuildReport, Builder, Capability, ComplianceProfile, EncodingError, MessageBuilder,
+    SessionHeader, VerificationOptions, RESOURCE_PREFIX,
+};
+use cid::Cid;
+
+use iri_string::types::UriString;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use siwe::{Message, TimeStamp};
+
+/// A built SIWE message along with the metadata a server needs to record the delegation session.
+#[derive(Clone, Debug)]
+pub struct PreparedDelegation {
+    /// The message to be signed.
+    pub message: Message,
+    /// The ReCap statement describing the grants, if any capabilities were delegated.
+    pub statement: Option<String>,
+    /// The ReCap resource URI, if any capabilities were delegated.
+    pub recap_uri: Option<UriString>,
+    /// The CID of the delegated capabilities, if any.
+    pub capability_cid: Option<Cid>,
+    /// The expiration time of the message, if any.
+    pub expires_at: Option<TimeStamp>,
+    /// The compliance profile whose wording the statement was built in.
+    pub compliance_profile: ComplianceProfile,
+}
+
+/// What the user will be asked to sign, computed without building a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preview {
+    /// The full statement of the message.
+    pub statement: Option<String>,
+    /// The ReCap resource URI, if any capabilities are delegated.
+    pub recap_uri: Option<UriString>,
+    /// The estimated size of the message in bytes.
+    pub estimated_size: usize,
+}
+
+/// The request to sign a prepared delegation, as handed by a backend to a frontend.
+#[derive(Serialize)]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SigningRequest<'a> {
+    message: String,
+    #[cfg_attr(feature = "ts-rs", ts(as = "crate::typescript::StatementLines"))]
+    statement_lines: Vec<String>,
+    resources: Vec<&'a str>,
+    expires_at: Option<String>,
+    capability_cid: Option<String>,
+}
+
+/// The response of a frontend to a [signing request](PreparedDelegation::to_signing_request_json).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
+pub struct SigningResponse {
+    /// The `0x`-prefixed hex signature.
+    pub signature: String,
+    /// The message which was signed, if echoed back by the frontend.
+    #[serde(default)]
+    #[cfg_attr(feature = "ts-rs", ts(optional))]
+    pub message: Option<String>,
+}
+
+impl PreparedDelegation {
+    /// Produce the JSON object to hand to a frontend for wallet signing, holding the `message`
+    /// to be signed, the `statementLines` of the ReCap statement to display, the `resources`, and
+    /// the `expiresAt` time and `capabilityCid` if any.
+    pub fn to_signing_request_json(&self) -> Value {
+        let options = VerificationOptions::new().with_compliance_profile(self.compliance_profile);
+        let statement_lines =
+            match Capability::<Value>::extract_and_verify_with(&self.message, &options) {
+                Ok(Some(capability)) if self.capability_cid.is_some() => {
+                    std::iter::once(self.compliance_profile.preamble().to_string())
+                        .chain(
+                            capability
+                                .to_statement_lines_with(self.compliance_profile)
+                                .into_iter()
+                                .enumerate()
+                                .map(|(n, line)| format!("({}) {line}", n + 1)),
+                        )
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+        serde_json::to_value(SigningRequest {
+            message: self.message.to_string(),
+            statement_lines,
+            resources: self.message.resources.iter().map(|r| r.as_str()).collect(),
+            expires_at: self.expires_at.as_ref().map(ToString::to_string),
+            capability_cid: self.capability_cid.as_ref().map(ToString::to_string),
+        })
+        .expect("signing requests serialize to JSON")
+    }
+
+    /// Ingest the JSON [response](SigningResponse) of a frontend to the signing request, checking
+    /// the signature against the prepared message and returning the session to present.
+    pub fn ingest_signing_response_json(
+        self,
+        response: &str,
+    ) -> Result<SessionHeader, SigningResponseError> {
+        let response: SigningResponse =
+            serde_json::from_str(response).map_err(SigningResponseError::Json)?;
+        self.ingest_signing_response(&response)
+    }
+
+    /// Ingest the [response](SigningResponse) of a frontend to the signing request, checking the
+    /// signature against the prepared message and returning the session to present.
+    pub fn ingest_signing_response(
+        self,
+        response: &SigningResponse,
+    ) -> Result<SessionHeader, SigningResponseError> {
+        if let Some(message) = &response.message {
+            if *message != self.message.to_string() {
+                return Err(SigningResponseError::MessageMismatch);
+            }
+        }
+        let signature = parse_signature(&response.signature)
+            .ok_or_else(|| SigningResponseError::Signature(response.signature.clone()))?;
+        self.message
+            .verify_eip191(&signature)
+            .map_err(SigningResponseError::Verification)?;
+        Ok(SessionHeader::new(self.message, signature.to_vec()))
+    }
+}
+
+fn parse_signature(signature: &str) -> Option<[u8; 65]> {
+    crate::hex::decode_array(signature.strip_prefix("0x").unwrap_or(signature))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SigningResponseError {
+    #[error("invalid signing response: {0}")]
+    Json(serde_json::Error),
+    #[error("the signed message does not match the prepared message")]
+    MessageMismatch,
+    #[error("invalid signature {0}")]
+    Signature(String),
+    #[error(transparent)]
+    Verification(siwe::VerificationError),
+    #[error("the wallet rejected the request ({code}): {message}")]
+    Rejected { code: i64, message: String },
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Serialize,
+{
+    /// Preview the statement and ReCap resource which building would add to a message.
+    ///
+    /// As the rest of the message is not known, the estimated size only counts the statement and
+    /// ReCap resource line; use [`MessageBuilder::preview`] for the size of a complete message.
+    pub fn preview(&self) -> Result<Preview, EncodingError> {
+        let capability = self.capability();
+        if capability.abilities().is_empty() {
+            let statement = self.statement().map(String::from);
+            return Ok(Preview {
+                estimated_size: statement.as_ref().map_or(0, String::len),
+                statement,
+                recap_uri: None,
+            });
+        }
+        let recap = capability.to_statement_with(self.compliance_profile());
+        let statement = match self.statement() {
+            Some(s) if !s.is_empty() => format!("{s} {recap}"),
+            _ => recap,
+        };
+        let recap_uri = UriString::try_from(capability)?;
+        Ok(Preview {
+            estimated_size: statement.len() + "\n- ".len() + recap_uri.as_str().len(),
+            statement: Some(statement),
+            recap_uri: Some(recap_uri),
+        })
+    }
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Serialize,
+{
+    /// Build the message, returning it along with the session metadata.
+    pub fn prepare(self, message: Message) -> Result<PreparedDelegation, BuildReport> {
+        let capability = self.capability();
+        let delegated = !capability.abilities().is_empty();
+        let statement = delegated.then(|| capability.to_statement_with(self.compliance_profile()));
+        let capability_cid = delegated
+            .then(|| capability.to_cid())
+            .transpose()
+            .map_err(BuildError::from)?;
+        let compliance_profile = self.compliance_profile();
+        let message = self.build(message)?;
+        Ok(PreparedDelegation {
+            compliance_profile,
+            statement,
+            recap_uri: capability_cid.and(message.resources.last().cloned()),
+            capability_cid,
+            expires_at: message.expiration_time.clone(),
+            message,
+        })
+    }
+}
+
+impl<NB> MessageBuilder<NB>
+where
+    NB: Serialize + Clone,
+{
+    /// Preview the complete message without consuming the builder.
+    pub fn preview(&self) -> Result<Preview, BuildReport> {
+        let message = self.clone().build()?;
+        Ok(Preview {
+            estimated_size: message.to_string().len(),
+            recap_uri: message
+                .resources
+                .last()
+                .filter(|uri| uri.as_str().starts_with(RESOURCE_PREFIX))
+                .cloned(),
+            statement: message.statement,
+        })
+    }
+}
+
+impl<NB> MessageBuilder<NB>
+where
+    NB: Serialize,
+{
+    /// Build the message, returning it along with the session metadata.
+    pub fn prepare(self) -> Result<PreparedDelegation, BuildReport> {
+        let (capabilities, message) = self.into_parts()?;
+        capabilities.prepare(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sign;
+
+    #[test]
+    fn preview() {
+        let builder = Builder::<Value>::new()
+            .with_action_convert("credential:*", "credential/present", [])
+            .unwrap()
+            .with_statement("Some custom statement.");
+        let preview = builder.preview().unwrap();
+
+        let message_builder = MessageBuilder::new()
+            .with_capabilities(builder)
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .nonce("mynonce1")
+            .issued_at("2022-06-21T12:00:00.000Z".parse().unwrap())
+            .resource("http://example.com".parse().unwrap());
+        let full_preview = message_builder.preview().unwrap();
+        let message = message_builder.build().unwrap();
+
+        assert_eq!(preview.statement, message.statement);
+        assert_eq!(preview.recap_uri.as_ref(), message.resources.last());
+        assert_eq!(full_preview.statement, message.statement);
+        assert_eq!(full_preview.estimated_size, message.to_string().len());
+    }
+
+    #[test]
+    fn prepare() {
+        let prepared = MessageBuilder::<Value>::new()
+            .with_capabilities(
+                Builder::new()
+                    .with_action_convert("credential:*", "credential/present", [])
+                    .unwrap(),
+            )
+            .domain("example.com".parse().unwrap())
+            .address(Default::default())
+            .uri("did:key:example".parse().unwrap())
+            .chain_id(1)
+            .expiration_time("2022-06-22T12:00:00.000Z".parse().unwrap())
+            .prepare()
+            .unwrap();
+
+        let capability = Capability::<Value>::extract_and_verify(&prepared.message)
+            .unwrap()
+            .unwrap();
+        assert_eq!(prepared.statement, Some(capability.to_statement()));
+        assert_eq!(
+            prepared.recap_uri.as_ref(),
+            prepared.message.resources.last()
+        );
+        assert_eq!(prepared.capability_cid, Some(capability.to_cid().unwrap()));
+        assert_eq!(
+            prepared.expires_at.map(|t| t.to_string()).as_deref(),
+            Some("2022-06-22T12:00:00.000Z")
+        );
+    }
+
+    #[test]
+    fn signing_request() {
+        let mut message = crate::test_utils::message();
+        message.address = sign(message.clone()).0.address;
+        let prepared = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .prepare(message)
+            .unwrap();
+
+        let request = prepared.to_signing_request_json();
+        assert_eq!(request["message"], prepared.message.to_string());
+        assert_eq!(
+            request["statementLines"],
+            serde_json::json!([
+                "I further authorize the stated URI to perform the following actions on my behalf:",
+                "(1) 'kv': 'get' for 'https://example.com/kv'."
+            ])
+        );
+        assert_eq!(
+            request["resources"][0],
+            prepared.recap_uri.as_ref().unwrap().as_str()
+        );
+        assert_eq!(request["expiresAt"], "2022-06-21T13:00:00.000Z");
+        for profile in ComplianceProfile::ALL {
+            let request = Builder::<Value>::new()
+                .with_action_convert("https://example.com/kv", "kv/get", [])
+                .unwrap()
+                .with_compliance_profile(profile)
+                .prepare(crate::test_utils::message())
+                .unwrap()
+                .to_signing_request_json();
+            assert_eq!(request["statementLines"][0], profile.preamble());
+            assert_eq!(request["statementLines"].as_array().unwrap().len(), 2);
+        }
+
+        let (_, signature) = sign(prepared.message.clone());
+        let signature: String = signature.iter().map(|b| format!("{b:02x}")).collect();
+        assert!(matches!(
+            prepared
+                .clone()
+                .ingest_signing_response_json(r#"{"signature": "0x00"}"#),
+            Err(SigningResponseError::Signature(_))
+        ));
+        assert!(matches!(
+            prepared.clone().ingest_signing_response(&SigningResponse {
+                signature: format!("0x{signature}"),
+                message: Some("another message".into()),
+            }),
+            Err(SigningResponseError::MessageMismatch)
+        ));
+        let header = prepared
+            .clone()
+            .ingest_signing_response_json(&format!(r#"{{"signature": "0x{signature}"}}"#))
+            .unwrap();
+        assert_eq!(header.message(), &prepared.message);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{BuildError, Builder};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+type PresetFn<NB> =
+    dyn Fn(Builder<NB>) -> Result<Builder<NB>, Box<dyn Error + Send + Sync>> + Send + Sync;
+
+/// A registry of named, reusable permission bundles (e.g. "basic session", "admin session").
+pub struct PresetRegistry<NB> {
+    presets: BTreeMap<String, Arc<PresetFn<NB>>>,
+}
+
+impl<NB> PresetRegistry<NB> {
+    /// Create a new empty registry.
+    pub fn new() -> Self {
+        Self {
+            presets: BTreeMap::new(),
+        }
+    }
+
+    /// Register a preset which adds its grants to a builder, replacing any preset with the same name.
+    pub fn register<F, E>(mut self, name: impl Into<String>, preset: F) -> Self
+    where
+        F: Fn(Builder<NB>) -> Result<Builder<NB>, E> + Send + Sync + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.presets.insert(
+            name.into(),
+            Arc::new(move |builder| preset(builder).map_err(Into::into)),
+        );
+        self
+    }
+
+    /// List the names of the registered presets.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+
+    fn apply(&self, name: &str, builder: Builder<NB>) -> Result<Builder<NB>, BuildError> {
+        let preset = self
+            .presets
+            .get(name)
+            .ok_or_else(|| BuildError::UnknownPreset(name.to_string()))?;
+        preset(builder).map_err(|source| BuildError::Preset {
+            name: name.to_string(),
+            source,
+        })
+    }
+}
+
+impl<NB> Builder<NB> {
+    /// Add the grants of a named preset from the registry.
+    pub fn apply_preset(
+        self,
+        presets: &PresetRegistry<NB>,
+        name: &str,
+    ) -> Result<Self, BuildError> {
+        presets.apply(name, self)
+    }
+}
+
+impl<NB> Default for PresetRegistry<NB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NB> Clone for PresetRegistry<NB> {
+    fn clone(&self) -> Self {
+        Self {
+            presets: self.presets.clone(),
+        }
+    }
+}
+
+impl<NB> fmt::Debug for PresetRegistry<NB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.presets.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn apply_preset() {
+        let presets = PresetRegistry::<Value>::new().register("read-only-storage", |b| {
+            b.with_actions_convert(
+                "kepler:ens:example.eth://default/kv",
+                [("kv/get", []), ("kv/list", [])],
+            )
+        });
+
+        let builder = Builder::new()
+            .apply_preset(&presets, "read-only-storage")
+            .unwrap();
+        assert!(builder
+            .capability()
+            .can("kepler:ens:example.eth://default/kv", "kv/list")
+            .unwrap()
+            .is_some());
+
+        assert!(matches!(
+            Builder::new().apply_preset(&presets, "admin"),
+            Err(BuildError::UnknownPreset(name)) if name == "admin"
+        ));
+    }
+}
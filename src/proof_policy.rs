@@ -0,0 +1,150 @@
+use crate::Capability;
+use cid::{Cid, Version};
+use std::collections::BTreeSet;
+
+/// Which proof CIDs are acceptable, e.g. only those a proof resolver is able to fetch and verify.
+///
+/// Codecs and hash functions are given by their multicodec codes, e.g. `0x55` for raw, `0x71` for
+/// dag-cbor and `0x12` for SHA2-256. By default, every CID is accepted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ProofPolicy {
+    require_v1: bool,
+    codecs: Option<BTreeSet<u64>>,
+    hashes: Option<BTreeSet<u64>>,
+}
+
+impl ProofPolicy {
+    /// Create a policy accepting every CID.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject CIDv0 proofs.
+    pub fn require_v1(mut self) -> Self {
+        self.require_v1 = true;
+        self
+    }
+
+    /// Only accept proofs with the given codecs.
+    pub fn with_codecs(mut self, codecs: impl IntoIterator<Item = u64>) -> Self {
+        self.codecs = Some(codecs.into_iter().collect());
+        self
+    }
+
+    /// Only accept proofs with the given hash functions.
+    pub fn with_hashes(mut self, hashes: impl IntoIterator<Item = u64>) -> Self {
+        self.hashes = Some(hashes.into_iter().collect());
+        self
+    }
+
+    /// Check a proof CID against the policy.
+    pub fn check(&self, cid: &Cid) -> Result<(), ProofPolicyError> {
+        if self.require_v1 && cid.version() == Version::V0 {
+            return Err(ProofPolicyError::Version(*cid));
+        }
+        if let Some(codecs) = &self.codecs {
+            if !codecs.contains(&cid.codec()) {
+                return Err(ProofPolicyError::Codec(*cid));
+            }
+        }
+        if let Some(hashes) = &self.hashes {
+            if !hashes.contains(&cid.hash().code()) {
+                return Err(ProofPolicyError::Hash(*cid));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<NB> Capability<NB> {
+    /// Add a supporting proof CID, if it is accepted by the policy.
+    pub fn try_with_proof(
+        self,
+        proof: &Cid,
+        policy: &ProofPolicy,
+    ) -> Result<Self, ProofPolicyError> {
+        policy.check(proof)?;
+        Ok(self.with_proof(proof))
+    }
+
+    /// Check every proof CID against the policy.
+    pub fn check_proofs(&self, policy: &ProofPolicy) -> Result<(), ProofPolicyError> {
+        self.proof().iter().try_for_each(|cid| policy.check(cid))
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProofPolicyError {
+    #[error("proof {0} is not a CIDv1")]
+    Version(Cid),
+    #[error("proof {0} uses a codec which is not allowed")]
+    Codec(Cid),
+    #[error("proof {0} uses a hash function which is not allowed")]
+    Hash(Cid),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Builder, VerificationOptions};
+    use cid::multihash::{Code, MultihashDigest};
+    use serde_json::Value;
+
+    #[test]
+    fn proof_policy() {
+        let policy = ProofPolicy::new()
+            .require_v1()
+            .with_codecs([0x55, 0x71])
+            .with_hashes([0x12]);
+        let raw = Cid::new_v1(0x55, Code::Sha2_256.digest(b"proof"));
+        let json = Cid::new_v1(0x0200, Code::Sha2_256.digest(b"proof"));
+        let blake = Cid::new_v1(0x55, Code::Blake3_256.digest(b"proof"));
+        let v0 = Cid::new_v0(Code::Sha2_256.digest(b"proof")).unwrap();
+        assert!(policy.check(&raw).is_ok());
+        assert!(matches!(
+            policy.check(&json),
+            Err(ProofPolicyError::Codec(_))
+        ));
+        assert!(matches!(
+            policy.check(&blake),
+            Err(ProofPolicyError::Hash(_))
+        ));
+        assert!(matches!(
+            policy.check(&v0),
+            Err(ProofPolicyError::Version(_))
+        ));
+        assert!(ProofPolicy::new().check(&v0).is_ok());
+
+        let cap = Capability::<Value>::new()
+            .try_with_proof(&raw, &policy)
+            .unwrap();
+        assert!(cap.clone().try_with_proof(&v0, &policy).is_err());
+        assert!(cap.with_proof(&json).check_proofs(&policy).is_err());
+
+        let builder = Builder::<Value>::new()
+            .with_proof_policy(policy.clone())
+            .with_proof(&json)
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        assert!(builder.validate().is_err());
+
+        let message = Builder::<Value>::new()
+            .with_proof(&json)
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build(crate::test_utils::message())
+            .unwrap();
+        assert!(Capability::<Value>::extract_and_verify_with(
+            &message,
+            &VerificationOptions::new()
+        )
+        .is_ok());
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify_with(
+                &message,
+                &VerificationOptions::new().with_proof_policy(policy)
+            ),
+            Err(crate::VerificationError::Proof(ProofPolicyError::Codec(_)))
+        ));
+    }
+}
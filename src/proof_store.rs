@@ -0,0 +1,158 @@
+use crate::Capability;
+use cid::Cid;
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+/// A content-addressed store of delegation blocks, such as a UCAN store, from which the proofs
+/// referenced in `prf` can be looked up and to which ReCap delegations can be written.
+pub trait ProofStore {
+    type Error;
+
+    /// Read the block with the given CID, if held by the store.
+    fn read(&self, cid: &Cid) -> impl Future<Output = Result<Option<Vec<u8>>, Self::Error>>;
+
+    /// Write a block, returning its CID as computed by the store.
+    fn write(&mut self, block: Vec<u8>) -> impl Future<Output = Result<Cid, Self::Error>>;
+}
+
+/// A proof looked up in a [`ProofStore`], which is either a ReCap delegation or an encoded UCAN,
+/// so that SIWE and UCAN delegations can be mixed in one chain.
+#[derive(Clone, Debug)]
+pub enum Proof<NB> {
+    Recap(Capability<NB>),
+    Ucan(String),
+}
+
+impl<NB> Proof<NB>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    /// Decode a block holding either the JSON of a ReCap delegation or a JWT-encoded UCAN.
+    pub fn decode(block: &[u8]) -> Result<Self, serde_json::Error> {
+        match std::str::from_utf8(block) {
+            Ok(token) if is_jwt(token) => Ok(Self::Ucan(token.to_string())),
+            _ => serde_json::from_slice(block).map(Self::Recap),
+        }
+    }
+}
+
+fn is_jwt(token: &str) -> bool {
+    let segments: Vec<&str> = token.split('.').collect();
+    segments.len() == 3
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+impl<NB> Capability<NB>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    /// Look up each proof of the capabilities in the store, in order, returning `None` for those
+    /// not held by the store.
+    pub async fn resolve_proofs<S>(
+        &self,
+        store: &S,
+    ) -> Result<Vec<(Cid, Option<Proof<NB>>)>, ProofStoreError<S::Error>>
+    where
+        S: ProofStore,
+    {
+        let mut proofs = Vec::with_capacity(self.proof().len());
+        for cid in self.proof() {
+            let proof = match store.read(cid).await.map_err(ProofStoreError::Store)? {
+                Some(block) => Some(
+                    Proof::decode(&block)
+                        .map_err(|source| ProofStoreError::Decode { cid: *cid, source })?,
+                ),
+                None => None,
+            };
+            proofs.push((*cid, proof));
+        }
+        Ok(proofs)
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: Serialize,
+{
+    /// Write the capabilities to the store as a proof for re-delegation, returning the CID under
+    /// which the store holds them.
+    ///
+    /// The CID is computed by the store, so it may use a different hash than [`Capability::to_cid`].
+    pub async fn write_to<S>(&self, store: &mut S) -> Result<Cid, ProofStoreError<S::Error>>
+    where
+        S: ProofStore,
+    {
+        let block = serde_jcs::to_vec(self).map_err(ProofStoreError::Encode)?;
+        store.write(block).await.map_err(ProofStoreError::Store)
+    }
+}
+
+/// Adapts a store of the `ucan` crate, which holds both UCANs and ReCap delegations as raw blocks.
+#[cfg(feature = "ucan")]
+#[derive(Clone, Debug)]
+pub struct UcanProofStore<S>(pub S);
+
+#[cfg(feature = "ucan")]
+impl<S> ProofStore for UcanProofStore<S>
+where
+    S: ucan::store::UcanStore<ucan::store::RawCodec>,
+{
+    type Error = anyhow::Error;
+
+    async fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.0.read::<Vec<u8>>(cid).await
+    }
+
+    async fn write(&mut self, block: Vec<u8>) -> Result<Cid, Self::Error> {
+        self.0.write(block).await
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProofStoreError<E> {
+    #[error("proof store error: {0}")]
+    Store(E),
+    #[error("failed to decode proof {cid}: {source}")]
+    Decode { cid: Cid, source: serde_json::Error },
+    #[error("failed to encode capability: {0}")]
+    Encode(serde_json::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::MemoryProofStore;
+    use cid::multihash::{Code, MultihashDigest};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn mixed_chain() {
+        let mut store = MemoryProofStore::default();
+        let mut parent = Capability::<Value>::new();
+        parent
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let recap = parent.write_to(&mut store).await.unwrap();
+        assert_eq!(recap, parent.to_cid().unwrap());
+        let ucan = store
+            .write(b"eyJhbGciOiJFZERTQSJ9.eyJpc3MiOiJkaWQ6a2V5In0.c2ln".to_vec())
+            .await
+            .unwrap();
+        let missing = Cid::new_v1(0x55, Code::Sha2_256.digest(b"missing"));
+
+        let mut child = Capability::<Value>::new().with_proofs(&[recap, ucan, missing]);
+        child
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let proofs = child.resolve_proofs(&store).await.unwrap();
+        assert!(matches!(&proofs[0], (cid, Some(Proof::Recap(_))) if *cid == recap));
+        assert!(matches!(&proofs[1], (cid, Some(Proof::Ucan(_))) if *cid == ucan));
+        assert!(matches!(&proofs[2], (cid, None) if *cid == missing));
+    }
+}
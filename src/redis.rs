@@ -0,0 +1,73 @@
+use crate::{NonceStore, VerificationCache};
+use std::time::Duration;
+
+use redis::aio::ConnectionManager;
+
+/// A [`NonceStore`] and [`VerificationCache`] backed by Redis, shared by every instance of a
+/// service, with entries expiring after their time-to-live.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisStore {
+    /// Use the given connection, with keys prefixed by `recap:`.
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self {
+            connection,
+            prefix: "recap:".to_string(),
+        }
+    }
+
+    /// Prefix keys with the given string rather than `recap:`.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, kind: &str, key: &str) -> String {
+        format!("{}{kind}:{key}", self.prefix)
+    }
+}
+
+fn millis(ttl: Duration) -> u64 {
+    u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1)
+}
+
+impl NonceStore for RedisStore {
+    type Error = redis::RedisError;
+
+    async fn use_nonce(&self, nonce: &str, ttl: Duration) -> Result<bool, Self::Error> {
+        let set: Option<String> = redis::cmd("SET")
+            .arg(self.key("nonce", nonce))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(set.is_some())
+    }
+}
+
+impl VerificationCache for RedisStore {
+    type Error = redis::RedisError;
+
+    async fn is_verified(&self, key: &str) -> Result<bool, Self::Error> {
+        redis::cmd("EXISTS")
+            .arg(self.key("verified", key))
+            .query_async(&mut self.connection.clone())
+            .await
+    }
+
+    async fn set_verified(&self, key: &str, ttl: Duration) -> Result<(), Self::Error> {
+        redis::cmd("SET")
+            .arg(self.key("verified", key))
+            .arg(1)
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut self.connection.clone())
+            .await
+    }
+}
@@ -0,0 +1,172 @@
+use crate::Capability;
+use std::collections::{BTreeMap, BTreeSet};
+
+use iri_string::types::UriString;
+use ucan_capabilities_object::{Ability, NotaBeneCollection};
+
+/// The abilities declared for a namespace in a [`NamespaceRegistry`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NamespaceSpec {
+    abilities: Option<BTreeMap<String, Option<BTreeSet<String>>>>,
+}
+
+impl NamespaceSpec {
+    /// Create a spec which allows any ability name in the namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare an allowed ability name with any nota bene keys.
+    ///
+    /// Once any ability is declared, only declared abilities are allowed.
+    pub fn with_ability(mut self, name: impl Into<String>) -> Self {
+        self.abilities
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.into(), None);
+        self
+    }
+
+    /// Declare an allowed ability name whose nota benes may only use the given keys.
+    pub fn with_ability_keys<K>(mut self, name: impl Into<String>, keys: K) -> Self
+    where
+        K: IntoIterator,
+        K::Item: Into<String>,
+    {
+        self.abilities.get_or_insert_with(BTreeMap::new).insert(
+            name.into(),
+            Some(keys.into_iter().map(Into::into).collect()),
+        );
+        self
+    }
+}
+
+/// A registry of the ability namespaces known to an application, used to reject grants with
+/// unknown namespaces, abilities or nota bene keys, e.g. a typo like `credentail/present`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NamespaceRegistry {
+    namespaces: BTreeMap<String, NamespaceSpec>,
+}
+
+impl NamespaceRegistry {
+    /// Create a new empty registry, which rejects every namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a known namespace, replacing any existing declaration.
+    pub fn with_namespace(mut self, namespace: impl Into<String>, spec: NamespaceSpec) -> Self {
+        self.namespaces.insert(namespace.into(), spec);
+        self
+    }
+
+    /// Check a single grant against the registry.
+    pub fn check_grant<NB>(
+        &self,
+        ability: &Ability,
+        nota_benes: &NotaBeneCollection<NB>,
+    ) -> Result<(), RegistryError> {
+        let spec = self
+            .namespaces
+            .get(&ability.namespace().to_string())
+            .ok_or_else(|| RegistryError::UnknownNamespace(ability.clone()))?;
+        let keys = match &spec.abilities {
+            None => return Ok(()),
+            Some(abilities) => abilities
+                .get(&ability.name().to_string())
+                .ok_or_else(|| RegistryError::UnknownAbility(ability.clone()))?,
+        };
+        match keys.as_ref().and_then(|keys| {
+            nota_benes
+                .iter()
+                .flat_map(|nb| nb.keys())
+                .find(|key| !keys.contains(*key))
+        }) {
+            Some(key) => Err(RegistryError::UnexpectedNotaBeneKey {
+                ability: ability.clone(),
+                key: key.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check every grant of the capabilities against the registry, returning all violations.
+    pub fn violations<NB>(&self, capability: &Capability<NB>) -> Vec<(UriString, RegistryError)> {
+        capability
+            .abilities()
+            .iter()
+            .flat_map(|(target, abilities)| {
+                abilities.iter().filter_map(move |(ability, nbs)| {
+                    self.check_grant(ability, nbs)
+                        .err()
+                        .map(|e| (target.clone(), e))
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegistryError {
+    #[error("unknown namespace for ability {0}")]
+    UnknownNamespace(Ability),
+    #[error("unknown ability {0}")]
+    UnknownAbility(Ability),
+    #[error("unexpected nota bene key {key:?} for ability {ability}")]
+    UnexpectedNotaBeneKey { ability: Ability, key: String },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn registry() {
+        let registry = NamespaceRegistry::new()
+            .with_namespace("credential", NamespaceSpec::new().with_ability("present"))
+            .with_namespace(
+                "kv",
+                NamespaceSpec::new().with_ability_keys("put", ["max_size"]),
+            );
+
+        let mut capability = Capability::<Value>::default();
+        capability
+            .with_action_convert("credential:*", "credential/present", [])
+            .unwrap()
+            .with_action_convert("credential:*", "credentail/present", [])
+            .unwrap()
+            .with_action_convert("credential:*", "credential/revoke", [])
+            .unwrap()
+            .with_action_convert(
+                "kepler:ens:example.eth://default/kv",
+                "kv/put",
+                [BTreeMap::from([("max_size".to_string(), Value::from(10))])],
+            )
+            .unwrap();
+        let violations: Vec<_> = registry
+            .violations(&capability)
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect();
+        assert!(matches!(
+            violations.as_slice(),
+            [
+                RegistryError::UnknownNamespace(_),
+                RegistryError::UnknownAbility(_)
+            ]
+        ));
+
+        let mut capability = Capability::<Value>::default();
+        capability
+            .with_action_convert(
+                "kepler:ens:example.eth://default/kv",
+                "kv/put",
+                [BTreeMap::from([("max_sise".to_string(), Value::from(10))])],
+            )
+            .unwrap();
+        assert!(matches!(
+            registry.violations(&capability).as_slice(),
+            [(_, RegistryError::UnexpectedNotaBeneKey { key, .. })] if key == "max_sise"
+        ));
+    }
+}
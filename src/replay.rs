@@ -0,0 +1,231 @@
+use crate::{SessionError, SessionHeader, VerificationOptions, VerifiedSession};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cid::multihash::{Code, MultihashDigest};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+/// A store of used SIWE nonces, for replay protection at sign-in.
+pub trait NonceStore {
+    type Error;
+
+    /// Mark the nonce as used for the given time-to-live, returning whether it was unused.
+    fn use_nonce(
+        &self,
+        nonce: &str,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<bool, Self::Error>>;
+}
+
+/// A cache of session headers whose signatures have been verified, so that repeated requests
+/// with the same session skip signature recovery.
+pub trait VerificationCache {
+    type Error;
+
+    /// Check whether the key has been marked as verified.
+    fn is_verified(&self, key: &str) -> impl Future<Output = Result<bool, Self::Error>>;
+
+    /// Mark the key as verified for the given time-to-live.
+    fn set_verified(
+        &self,
+        key: &str,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// An in-memory [`NonceStore`] and [`VerificationCache`], for single-instance services.
+#[derive(Debug, Default)]
+pub struct MemoryTtlStore {
+    nonces: Mutex<HashMap<String, Instant>>,
+    verified: Mutex<HashMap<String, Instant>>,
+}
+
+impl MemoryTtlStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Insert the key if absent or expired, dropping expired entries, and return whether it was inserted.
+fn insert_fresh(entries: &Mutex<HashMap<String, Instant>>, key: &str, ttl: Duration) -> bool {
+    let now = Instant::now();
+    let mut entries = entries.lock().unwrap_or_else(|e| e.into_inner());
+    entries.retain(|_, expiry| *expiry > now);
+    if entries.contains_key(key) {
+        return false;
+    }
+    entries.insert(key.to_string(), now + ttl);
+    true
+}
+
+impl NonceStore for MemoryTtlStore {
+    type Error = std::convert::Infallible;
+
+    async fn use_nonce(&self, nonce: &str, ttl: Duration) -> Result<bool, Self::Error> {
+        Ok(insert_fresh(&self.nonces, nonce, ttl))
+    }
+}
+
+impl VerificationCache for MemoryTtlStore {
+    type Error = std::convert::Infallible;
+
+    async fn is_verified(&self, key: &str) -> Result<bool, Self::Error> {
+        let verified = self.verified.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(verified
+            .get(key)
+            .is_some_and(|expiry| *expiry > Instant::now()))
+    }
+
+    async fn set_verified(&self, key: &str, ttl: Duration) -> Result<(), Self::Error> {
+        insert_fresh(&self.verified, key, ttl);
+        Ok(())
+    }
+}
+
+/// The time-to-live of an entry for the message: until it expires, but at most `max`.
+fn ttl_for(expiration: Option<&siwe::TimeStamp>, max: Duration) -> Duration {
+    expiration
+        .map(|exp| *AsRef::<OffsetDateTime>::as_ref(exp) - OffsetDateTime::now_utc())
+        .and_then(|remaining| Duration::try_from(remaining).ok())
+        .map_or(max, |remaining| remaining.min(max))
+}
+
+impl SessionHeader {
+    /// Verify the session, skipping signature recovery if the cache holds it as verified and
+    /// otherwise caching it for up to `max_ttl`, bounded by the expiration of the message.
+    ///
+    /// The validity period and capabilities are always checked.
+    pub async fn verify_cached<NB, C>(
+        self,
+        options: &VerificationOptions,
+        cache: &C,
+        max_ttl: Duration,
+    ) -> Result<VerifiedSession<NB>, ReplayError<C::Error>>
+    where
+        NB: for<'a> Deserialize<'a> + Clone,
+        C: VerificationCache,
+    {
+        let digest = Code::Sha2_256.digest(self.to_string().as_bytes());
+        let key: String = digest.digest().iter().map(|b| format!("{b:02x}")).collect();
+        if cache.is_verified(&key).await.map_err(ReplayError::Store)? {
+            #[cfg(feature = "metrics")]
+            let start = std::time::Instant::now();
+            let result = self.verify_unsigned(options);
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_verification(&result, start.elapsed());
+            return Ok(result?);
+        }
+        let ttl = ttl_for(self.message().expiration_time.as_ref(), max_ttl);
+        let session = self.verify(options)?;
+        cache
+            .set_verified(&key, ttl)
+            .await
+            .map_err(ReplayError::Store)?;
+        Ok(session)
+    }
+}
+
+impl<NB> VerifiedSession<NB> {
+    /// Mark the nonce of the message as used until the message expires, rejecting the session if
+    /// it was already used.
+    ///
+    /// The remaining validity period is measured at the time of `options`, as it was verified.
+    /// Messages without an expiration time are rejected, as their nonces would have to be kept forever.
+    pub async fn use_nonce<S>(
+        &self,
+        store: &S,
+        options: &VerificationOptions,
+    ) -> Result<(), ReplayError<S::Error>>
+    where
+        S: NonceStore,
+    {
+        let message = self.message();
+        let expiration = message
+            .expiration_time
+            .as_ref()
+            .ok_or(ReplayError::NoExpiration)?;
+        let remaining = *AsRef::<OffsetDateTime>::as_ref(expiration) - options.now();
+        let ttl = Duration::try_from(remaining).unwrap_or(Duration::ZERO);
+        if store
+            .use_nonce(&message.nonce, ttl)
+            .await
+            .map_err(ReplayError::Store)?
+        {
+            Ok(())
+        } else {
+            Err(ReplayError::NonceReused(message.nonce.clone()))
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError<E> {
+    #[error("store error: {0}")]
+    Store(E),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error("nonce {0} has already been used")]
+    NonceReused(String),
+    #[error("message has no expiration time, so its nonce cannot be tracked")]
+    NoExpiration,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, sign, verification_options};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn replay() {
+        let (message, signature) = sign(message());
+        let options = verification_options();
+        let store = MemoryTtlStore::new();
+        let ttl = Duration::from_secs(60);
+
+        let session = SessionHeader::new(message.clone(), signature.clone())
+            .verify_cached::<Value, _>(&options, &store, ttl)
+            .await
+            .unwrap();
+        // a cached session is accepted without recovering the signature again
+        assert!(SessionHeader::new(message.clone(), signature)
+            .verify_cached::<Value, _>(&options, &store, ttl)
+            .await
+            .is_ok());
+        assert!(matches!(
+            SessionHeader::new(message, vec![0; 65])
+                .verify_cached::<Value, _>(&options, &store, ttl)
+                .await,
+            Err(ReplayError::Session(_))
+        ));
+
+        session.use_nonce(&store, &options).await.unwrap();
+        assert!(matches!(
+            session.use_nonce(&store, &options).await,
+            Err(ReplayError::NonceReused(_))
+        ));
+        // the nonce is kept until the message expires, not just for the cache ttl
+        assert_eq!(
+            store.nonces.lock().unwrap()[&session.message().nonce]
+                .saturating_duration_since(Instant::now())
+                .as_secs()
+                / 60,
+            29
+        );
+
+        let mut unexpiring = crate::test_utils::message();
+        unexpiring.expiration_time = None;
+        let (unexpiring, signature) = sign(unexpiring);
+        let session = SessionHeader::new(unexpiring, signature)
+            .verify::<Value>(&options)
+            .unwrap();
+        assert!(matches!(
+            session.use_nonce(&store, &options).await,
+            Err(ReplayError::NoExpiration)
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{ComplianceProfile, SignInMessage, RESOURCE_PREFIX};
+
+/// Find the ReCap resource URI of a SIWE message without parsing the message, e.g. to filter or
+/// scrub logs of messages.
+///
+/// As for [`Capability::extract_and_verify`](crate::Capability::extract_and_verify), the ReCap
+/// must be the last resource. The message and URI are not validated.
+pub fn scan_recap(message: &str) -> Option<&str> {
+    let (_, resources) = message.rsplit_once("\nResources:")?;
+    let last = resources.trim_end().rsplit('\n').next()?;
+    let uri = last.trim_end_matches('\r').strip_prefix("- ")?;
+    uri.starts_with(RESOURCE_PREFIX).then_some(uri)
+}
+
+/// Whether the statement of a message contains the ReCap preamble although the message has no
+/// ReCap resource to extract, which suggests a spoofed or mangled message that would otherwise
+/// verify as delegating no capabilities.
+///
+/// Strict [verification](crate::DecodingProfile::Strict) rejects such messages.
+pub fn has_orphaned_statement<M: SignInMessage + ?Sized>(message: &M) -> bool {
+    message.statement().is_some_and(|statement| {
+        ComplianceProfile::ALL
+            .iter()
+            .any(|profile| statement.contains(profile.preamble()))
+    }) && !message
+        .resources()
+        .last()
+        .is_some_and(|uri| uri.starts_with(RESOURCE_PREFIX))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::message;
+    use crate::{Builder, Capability, DecodingProfile, VerificationError, VerificationOptions};
+    use serde_json::Value;
+
+    #[test]
+    fn scan() {
+        let mut message = message();
+        assert_eq!(scan_recap(&message.to_string()), None);
+
+        message
+            .resources
+            .push("https://example.com/a".parse().unwrap());
+        assert_eq!(scan_recap(&message.to_string()), None);
+
+        let message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build(message)
+            .unwrap();
+        let text = message.to_string();
+        assert_eq!(scan_recap(&text), Some(message.resources[1].as_str()));
+        assert_eq!(
+            scan_recap(&format!("{}\r\n", text.replace('\n', "\r\n"))),
+            Some(message.resources[1].as_str())
+        );
+    }
+
+    #[test]
+    fn orphaned_statement() {
+        let mut message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build(message())
+            .unwrap();
+        assert!(!has_orphaned_statement(&message));
+
+        message.resources.pop();
+        assert!(has_orphaned_statement(&message));
+        assert!(Capability::<Value>::extract_and_verify(&message)
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify_with(
+                &message,
+                &VerificationOptions::new().with_decoding_profile(DecodingProfile::Strict)
+            ),
+            Err(VerificationError::OrphanedStatement)
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{base64url, AbilityExt, Capability, VerificationError, VerificationOptions};
+use std::fmt;
+use std::str::FromStr;
+
+use iri_string::types::UriString;
+use serde::Deserialize;
+use siwe::Message;
+use ucan_capabilities_object::Ability;
+
+/// The HTTP authorization scheme of a [`SessionHeader`].
+pub const SESSION_SCHEME: &str = "SIWE";
+
+/// A signed SIWE message in the form of an HTTP `Authorization` header value:
+/// `SIWE <base64url(message)>.<base64url(signature)>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionHeader {
+    message: Message,
+    signature: Vec<u8>,
+}
+
+impl SessionHeader {
+    /// Create a header from a message and its signature.
+    pub fn new(message: Message, signature: Vec<u8>) -> Self {
+        Self { message, signature }
+    }
+
+    /// Encode a message and its signature as an `Authorization` header value.
+    pub fn encode(message: &Message, signature: &[u8]) -> String {
+        format!(
+            "{SESSION_SCHEME} {}.{}",
+            base64url::encode(message.to_string()),
+            base64url::encode(signature)
+        )
+    }
+
+    /// Decode an `Authorization` header value, without verifying it.
+    pub fn decode(header: &str) -> Result<Self, SessionError> {
+        let credentials = header
+            .split_once(' ')
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SESSION_SCHEME))
+            .map(|(_, credentials)| credentials.trim())
+            .ok_or(SessionError::Scheme)?;
+        let (message, signature) = credentials.split_once('.').ok_or(SessionError::Format)?;
+        let message = base64url::decode(message)?;
+        let message = String::from_utf8(message)?.parse()?;
+        let signature = base64url::decode(signature)?;
+        Ok(Self { message, signature })
+    }
+
+    /// Decode an `Authorization` header value and verify it.
+    pub fn decode_and_verify<NB>(
+        header: &str,
+        options: &VerificationOptions,
+    ) -> Result<VerifiedSession<NB>, SessionError>
+    where
+        NB: for<'a> Deserialize<'a> + Clone,
+    {
+        Self::decode(header)?.verify(options)
+    }
+
+    /// Verify the EIP-191 signature, validity period and capabilities of the message.
+    pub fn verify<NB>(
+        self,
+        options: &VerificationOptions,
+    ) -> Result<VerifiedSession<NB>, SessionError>
+    where
+        NB: for<'a> Deserialize<'a> + Clone,
+    {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = self
+            .verify_signature()
+            .and_then(|()| self.verify_unsigned(options));
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_verification(&result, start.elapsed());
+        result
+    }
+
+    fn verify_signature(&self) -> Result<(), SessionError> {
+        let signature: &[u8; 65] = self
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| siwe::VerificationError::SignatureLength)?;
+        self.message.verify_eip191(signature)?;
+        Ok(())
+    }
+
+    /// Verify the validity period and capabilities of the message, assuming the signature has
+    /// already been verified.
+    pub(crate) fn verify_unsigned<NB>(
+        self,
+        options: &VerificationOptions,
+    ) -> Result<VerifiedSession<NB>, SessionError>
+    where
+        NB: for<'a> Deserialize<'a> + Clone,
+    {
+        options.check_message(&self.message)?;
+        let capability =
+            Capability::extract_and_verify_with(&self.message, options)?.unwrap_or_default();
+        Ok(VerifiedSession {
+            message: self.message,
+            signature: self.signature,
+            capability,
+        })
+    }
+
+    /// The signed message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// The signature over the message.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+/// Read the value of an `Authorization` header for verification by a framework integration.
+#[cfg(any(feature = "axum", feature = "actix-web", feature = "tower"))]
+pub(crate) fn header_str(value: Option<&[u8]>) -> Result<&str, SessionError> {
+    std::str::from_utf8(value.ok_or(SessionError::Missing)?).map_err(|_| SessionError::Format)
+}
+
+impl fmt::Display for SessionHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Self::encode(&self.message, &self.signature))
+    }
+}
+
+impl FromStr for SessionHeader {
+    type Err = SessionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s)
+    }
+}
+
+/// A session whose signature, validity period and capabilities have been verified.
+#[derive(Clone, Debug)]
+pub struct VerifiedSession<NB> {
+    message: Message,
+    signature: Vec<u8>,
+    capability: Capability<NB>,
+}
+
+impl<NB> VerifiedSession<NB> {
+    /// The signed message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// The verified signature over the message.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The address of the signer.
+    pub fn address(&self) -> &[u8; 20] {
+        &self.message.address
+    }
+
+    /// The delegated capabilities, which are empty if the message contains no ReCap.
+    pub fn capability(&self) -> &Capability<NB> {
+        &self.capability
+    }
+
+    /// Take the delegated capabilities.
+    pub fn into_capability(self) -> Capability<NB> {
+        self.capability
+    }
+
+    /// Check that the session has been delegated the required capabilities.
+    pub fn require(&self, required: &RequiredCapabilities) -> Result<(), SessionError> {
+        required.check(&self.capability)
+    }
+}
+
+/// Capabilities which a session must have been delegated, e.g. to access a route.
+///
+/// A required action is satisfied by a grant on the same target whose ability
+/// [covers](AbilityExt::covers) it.
+#[derive(Clone, Debug, Default)]
+pub struct RequiredCapabilities {
+    grants: Vec<(UriString, Ability)>,
+}
+
+impl RequiredCapabilities {
+    /// Create an empty set of requirements, which every session satisfies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require an action on the given target.
+    pub fn with_action(mut self, target: UriString, action: Ability) -> Self {
+        self.grants.push((target, action));
+        self
+    }
+
+    /// Check that the capabilities grant every required action.
+    pub fn check<NB>(&self, capability: &Capability<NB>) -> Result<(), SessionError> {
+        match self.grants.iter().find(|(target, action)| {
+            !capability
+                .abilities_for_str(target.as_str())
+                .is_some_and(|abilities| abilities.keys().any(|ability| ability.covers(action)))
+        }) {
+            Some((target, action)) => Err(SessionError::MissingCapability {
+                target: target.clone(),
+                action: action.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionError {
+    #[error("missing authorization header")]
+    Missing,
+    #[error("expected the {SESSION_SCHEME} authorization scheme")]
+    Scheme,
+    #[error("expected credentials of the form <message>.<signature>")]
+    Format,
+    #[error("failed to decode base64 credentials: {0}")]
+    Base64Decode(#[from] base64::DecodeError),
+    #[error("message is not valid UTF-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("failed to parse siwe message: {0}")]
+    Message(#[from] siwe::ParseError),
+    #[error("siwe verification failed: {0}")]
+    Siwe(#[from] siwe::VerificationError),
+    #[error(transparent)]
+    Capability(#[from] VerificationError),
+    #[error("session is not authorized to perform {action} on {target}")]
+    MissingCapability { target: UriString, action: Ability },
+}
+
+impl SessionError {
+    /// Every [code](Self::code) an error may have.
+    pub const CODES: &'static [&'static str] = &[
+        "missing_session",
+        "invalid_scheme",
+        "malformed_session",
+        "invalid_message",
+        "invalid_time",
+        "domain_mismatch",
+        "invalid_signature",
+        "invalid_capability",
+        "insufficient_capability",
+    ];
+
+    /// A stable, machine-readable code for the error, for use in API responses.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing_session",
+            Self::Scheme => "invalid_scheme",
+            Self::Format | Self::Base64Decode(_) | Self::Utf8(_) => "malformed_session",
+            Self::Message(_) => "invalid_message",
+            Self::Siwe(siwe::VerificationError::Time) => "invalid_time",
+            Self::Siwe(siwe::VerificationError::DomainMismatch) => "domain_mismatch",
+            Self::Siwe(_) => "invalid_signature",
+            Self::Capability(_) => "invalid_capability",
+            Self::MissingCapability { .. } => "insufficient_capability",
+        }
+    }
+
+    /// The JSON body of an error response, holding the code and description of the error.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.code(),
+            "message": self.to_string(),
+        })
+    }
+
+    /// The HTTP status code for the error: 401 if the session could not be authenticated, or 403 if
+    /// its capabilities were rejected or insufficient.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Capability(_) | Self::MissingCapability { .. } => 403,
+            _ => 401,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{message, sign, verification_options};
+    use crate::Builder;
+    use serde_json::Value;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn session_header() {
+        let message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .build(message())
+            .unwrap();
+        let (message, signature) = sign(message);
+        let header = SessionHeader::encode(&message, &signature);
+        assert!(header.starts_with("SIWE "));
+        assert_eq!(
+            header.parse::<SessionHeader>().unwrap(),
+            SessionHeader::new(message.clone(), signature.clone())
+        );
+
+        let options = verification_options().with_domain("example.com".parse().unwrap());
+        let session = SessionHeader::decode_and_verify::<Value>(&header, &options).unwrap();
+        assert_eq!(session.address(), &message.address);
+        assert!(session
+            .capability()
+            .can_do_str(
+                "https://example.com/kv",
+                crate::AbilityStr::parse("kv/get").unwrap()
+            )
+            .is_some());
+
+        assert!(matches!(
+            SessionHeader::decode_and_verify::<Value>(
+                &header,
+                &options
+                    .clone()
+                    .with_timestamp(OffsetDateTime::from_unix_timestamp(1655820000).unwrap())
+            ),
+            Err(SessionError::Siwe(siwe::VerificationError::Time))
+        ));
+        let mut forged = signature.clone();
+        forged[0] ^= 1;
+        assert!(matches!(
+            SessionHeader::new(message.clone(), forged).verify::<Value>(&options),
+            Err(SessionError::Siwe(_))
+        ));
+        let required = |ability: &str| {
+            RequiredCapabilities::new().with_action(
+                "https://example.com/kv".parse().unwrap(),
+                ability.parse().unwrap(),
+            )
+        };
+        assert!(session.require(&required("kv/get")).is_ok());
+        assert!(matches!(
+            session.require(&required("kv/put")),
+            Err(SessionError::MissingCapability { .. })
+        ));
+        assert!(matches!(
+            SessionHeader::decode("Bearer abc"),
+            Err(SessionError::Scheme)
+        ));
+        assert!(matches!(
+            SessionHeader::decode("SIWE abc"),
+            Err(SessionError::Format)
+        ));
+    }
+}
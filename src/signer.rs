@@ -0,0 +1,138 @@
+use crate::{BuildReport, Builder};
+use std::future::Future;
+
+use serde::Serialize;
+use siwe::Message;
+
+/// A signer producing EIP-191 personal signatures over SIWE messages.
+pub trait Signer {
+    type Error;
+
+    /// Sign the given message text, returning the signature bytes.
+    fn sign(&self, message: &str) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// An asynchronous signer producing EIP-191 personal signatures over SIWE messages, e.g. a remote wallet.
+pub trait AsyncSigner {
+    type Error;
+
+    /// Sign the given message text, returning the signature bytes.
+    fn sign_async(&self, message: &str) -> impl Future<Output = Result<Vec<u8>, Self::Error>>;
+}
+
+impl<S> AsyncSigner for S
+where
+    S: Signer,
+{
+    type Error = S::Error;
+
+    fn sign_async(&self, message: &str) -> impl Future<Output = Result<Vec<u8>, Self::Error>> {
+        std::future::ready(self.sign(message))
+    }
+}
+
+#[cfg(feature = "ethers")]
+impl Signer for ethers::signers::LocalWallet {
+    type Error = ethers::signers::WalletError;
+
+    fn sign(&self, message: &str) -> Result<Vec<u8>, Self::Error> {
+        self.sign_hash(ethers::utils::hash_message(message))
+            .map(|signature| signature.to_vec())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SigningError<E> {
+    #[error(transparent)]
+    Build(#[from] BuildReport),
+    #[error("failed to sign message: {0}")]
+    Signer(E),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignatureVerificationError<E> {
+    #[error("failed to call the signer's contract: {0}")]
+    Provider(E),
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+impl<NB> Builder<NB>
+where
+    NB: Serialize,
+{
+    /// Build the message and sign it, returning the message along with its signature.
+    pub fn build_and_sign<S>(
+        self,
+        message: Message,
+        signer: &S,
+    ) -> Result<(Message, Vec<u8>), SigningError<S::Error>>
+    where
+        S: Signer,
+    {
+        let message = self.build(message)?;
+        let signature = signer
+            .sign(&message.to_string())
+            .map_err(SigningError::Signer)?;
+        Ok((message, signature))
+    }
+
+    /// Build the message and sign it with an asynchronous signer, returning the message along with its signature.
+    pub async fn build_and_sign_async<S>(
+        self,
+        message: Message,
+        signer: &S,
+    ) -> Result<(Message, Vec<u8>), SigningError<S::Error>>
+    where
+        S: AsyncSigner,
+    {
+        let message = self.build(message)?;
+        let signature = signer
+            .sign_async(&message.to_string())
+            .await
+            .map_err(SigningError::Signer)?;
+        Ok((message, signature))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    struct Echo;
+
+    impl Signer for Echo {
+        type Error = std::convert::Infallible;
+
+        fn sign(&self, message: &str) -> Result<Vec<u8>, Self::Error> {
+            Ok(message.as_bytes().to_vec())
+        }
+    }
+
+    #[test]
+    fn build_and_sign() {
+        let (message, signature) = Builder::<Value>::new()
+            .with_action_convert("credential:*", "credential/present", [])
+            .unwrap()
+            .build_and_sign(
+                Message {
+                    domain: "example.com".parse().unwrap(),
+                    address: Default::default(),
+                    statement: None,
+                    uri: "did:key:example".parse().unwrap(),
+                    version: siwe::Version::V1,
+                    chain_id: 1,
+                    nonce: "mynonce1".into(),
+                    issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                    expiration_time: None,
+                    not_before: None,
+                    request_id: None,
+                    resources: vec![],
+                },
+                &Echo,
+            )
+            .unwrap();
+        assert_eq!(signature, message.to_string().into_bytes());
+    }
+}
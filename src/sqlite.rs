@@ -0,0 +1,247 @@
+use crate::{Capability, CapabilityStore, StoredDelegation};
+use cid::Cid;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS recap_delegations (
+    id TEXT PRIMARY KEY NOT NULL,
+    cid TEXT NOT NULL,
+    capability TEXT NOT NULL,
+    expires_at TEXT,
+    expires_unix INTEGER,
+    delegator TEXT,
+    delegee TEXT
+);
+CREATE INDEX IF NOT EXISTS recap_delegations_cid ON recap_delegations (cid);
+CREATE INDEX IF NOT EXISTS recap_delegations_delegator ON recap_delegations (delegator);
+CREATE INDEX IF NOT EXISTS recap_delegations_delegee ON recap_delegations (delegee);
+CREATE INDEX IF NOT EXISTS recap_delegations_expires ON recap_delegations (expires_unix);";
+
+const COLUMNS: &str = "id, cid, capability, expires_at, delegator, delegee";
+
+/// A [`CapabilityStore`] persisting delegations in an SQLite database, in the
+/// `recap_delegations` table.
+#[derive(Debug)]
+pub struct SqliteCapabilityStore<NB> {
+    connection: Connection,
+    nota_bene: PhantomData<NB>,
+}
+
+impl<NB> SqliteCapabilityStore<NB> {
+    /// Use the database at the given path, creating the table if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteStoreError> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Use an in-memory database.
+    pub fn open_in_memory() -> Result<Self, SqliteStoreError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Use an existing connection, creating the table if needed.
+    pub fn new(connection: Connection) -> Result<Self, SqliteStoreError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection,
+            nota_bene: PhantomData,
+        })
+    }
+}
+
+impl<NB> SqliteCapabilityStore<NB>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    fn query(
+        &self,
+        filter: &str,
+        param: Option<&str>,
+    ) -> Result<Vec<StoredDelegation<NB>>, SqliteStoreError> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {COLUMNS} FROM recap_delegations {filter}"))?;
+        let rows = match param {
+            Some(param) => statement.query_map([param], columns)?,
+            None => statement.query_map([], columns)?,
+        };
+        rows.map(|row| from_columns(row?))
+            .collect::<Result<Vec<_>, _>>()
+    }
+}
+
+type Columns = (
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn columns(row: &Row<'_>) -> rusqlite::Result<Columns> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn from_columns<NB>(
+    (id, cid, capability, expires_at, delegator, delegee): Columns,
+) -> Result<StoredDelegation<NB>, SqliteStoreError>
+where
+    NB: for<'d> Deserialize<'d>,
+{
+    Ok(StoredDelegation {
+        id: id.parse().map_err(|_| SqliteStoreError::Cid(id))?,
+        cid: cid.parse().map_err(|_| SqliteStoreError::Cid(cid))?,
+        capability: Arc::new(serde_json::from_str::<Capability<NB>>(&capability)?),
+        expires_at: expires_at
+            .map(|t| t.parse().map_err(|_| SqliteStoreError::TimeStamp(t)))
+            .transpose()?,
+        delegator,
+        delegee,
+    })
+}
+
+impl<NB> CapabilityStore<NB> for SqliteCapabilityStore<NB>
+where
+    NB: Serialize + for<'d> Deserialize<'d>,
+{
+    type Error = SqliteStoreError;
+
+    fn delegations(&self) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        self.query("", None)
+    }
+
+    fn put(&mut self, delegation: StoredDelegation<NB>) -> Result<(), Self::Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO recap_delegations
+                (id, cid, capability, expires_at, expires_unix, delegator, delegee)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                delegation.id.to_string(),
+                delegation.cid.to_string(),
+                serde_jcs::to_string(&*delegation.capability)?,
+                delegation.expires_at.as_ref().map(ToString::to_string),
+                delegation
+                    .expires_at
+                    .as_ref()
+                    .map(|t| AsRef::<OffsetDateTime>::as_ref(t).unix_timestamp()),
+                delegation.delegator,
+                delegation.delegee,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &Cid) -> Result<bool, Self::Error> {
+        Ok(self.connection.execute(
+            "DELETE FROM recap_delegations WHERE id = ?1",
+            [id.to_string()],
+        )? > 0)
+    }
+
+    fn get(&self, id: &Cid) -> Result<Option<StoredDelegation<NB>>, Self::Error> {
+        self.connection
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM recap_delegations WHERE id = ?1"),
+                [id.to_string()],
+                columns,
+            )
+            .optional()?
+            .map(from_columns)
+            .transpose()
+    }
+
+    fn by_delegator(&self, delegator: &str) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        self.query("WHERE delegator = ?1", Some(delegator))
+    }
+
+    fn by_delegee(&self, delegee: &str) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        self.query("WHERE delegee = ?1", Some(delegee))
+    }
+
+    fn remove_expired_at(&mut self, t: &OffsetDateTime) -> Result<usize, Self::Error> {
+        Ok(self.connection.execute(
+            "DELETE FROM recap_delegations WHERE expires_unix <= ?1",
+            [t.unix_timestamp()],
+        )?)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SqliteStoreError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("failed to (de)serialize capability: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid stored CID {0}")]
+    Cid(String),
+    #[error("invalid stored expiration time {0}")]
+    TimeStamp(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signed_delegation_id;
+    use serde_json::Value;
+
+    #[test]
+    fn sqlite_store() {
+        let mut capability = Capability::<Value>::new();
+        capability
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap();
+        let delegation = StoredDelegation {
+            id: signed_delegation_id("message", &[0; 65]),
+            cid: capability.to_cid().unwrap(),
+            capability: Arc::new(capability),
+            expires_at: Some("2022-06-21T13:00:00.000Z".parse().unwrap()),
+            delegator: Some("did:pkh:eip155:1:0x0000000000000000000000000000000000000000".into()),
+            delegee: Some("did:key:example".into()),
+        };
+        let id = delegation.id;
+        let other = StoredDelegation {
+            id: signed_delegation_id("message", &[1; 65]),
+            delegator: Some("did:pkh:eip155:1:0x0000000000000000000000000000000000000001".into()),
+            ..delegation.clone()
+        };
+
+        let mut store = SqliteCapabilityStore::<Value>::open_in_memory().unwrap();
+        store.put(delegation.clone()).unwrap();
+        store.put(delegation).unwrap();
+        assert_eq!(store.delegations().unwrap().len(), 1);
+        store.put(other).unwrap();
+        assert_eq!(store.delegations().unwrap().len(), 2);
+        assert!(store
+            .delete(&signed_delegation_id("message", &[1; 65]))
+            .unwrap());
+        let stored = store.get(&id).unwrap().unwrap();
+        assert_eq!(
+            stored.expires_at.unwrap().to_string(),
+            "2022-06-21T13:00:00.000Z"
+        );
+        assert_eq!(store.by_delegee("did:key:example").unwrap().len(), 1);
+        assert!(store.by_delegator("did:key:example").unwrap().is_empty());
+
+        let t = OffsetDateTime::from_unix_timestamp(1655816400).unwrap();
+        assert_eq!(
+            store
+                .remove_expired_at(&(t - time::Duration::SECOND))
+                .unwrap(),
+            0
+        );
+        assert_eq!(store.remove_expired_at(&t).unwrap(), 1);
+        assert!(store.get(&id).unwrap().is_none());
+    }
+}
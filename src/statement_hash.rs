@@ -0,0 +1,118 @@
+use crate::{Capability, ComplianceProfile, SignInMessage, VerificationError};
+use cid::multihash::{Code, MultihashDigest};
+
+/// A generated ReCap statement along with its SHA2-256 hash, so that a message statement can be
+/// checked against it by comparing bytes without regenerating it, and identified by its digest.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StatementHash {
+    statement: Box<str>,
+    digest: [u8; 32],
+}
+
+impl StatementHash {
+    /// Hash a ReCap statement.
+    pub fn new(statement: impl Into<Box<str>>) -> Self {
+        let statement = statement.into();
+        Self {
+            digest: digest(statement.as_bytes()),
+            statement,
+        }
+    }
+
+    /// Check that a message statement ends with the hashed ReCap statement.
+    pub fn matches(&self, statement: &str) -> bool {
+        statement.ends_with(&*self.statement)
+    }
+
+    /// The hashed statement.
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+
+    /// The length of the hashed statement in bytes.
+    pub fn len(&self) -> usize {
+        self.statement.len()
+    }
+
+    /// Whether the hashed statement is empty.
+    pub fn is_empty(&self) -> bool {
+        self.statement.is_empty()
+    }
+
+    /// The SHA2-256 digest of the statement.
+    pub fn digest(&self) -> &[u8; 32] {
+        &self.digest
+    }
+}
+
+fn digest(bytes: &[u8]) -> [u8; 32] {
+    Code::Sha2_256
+        .digest(bytes)
+        .digest()
+        .try_into()
+        .expect("SHA2-256 digests are 32 bytes")
+}
+
+impl<NB> Capability<NB> {
+    /// Check that the statement of a message ends with the ReCap statement of the capabilities in
+    /// the wording of the compliance profile, comparing against the
+    /// [cached statement](Capability::statement_hash), e.g. when repeatedly verifying messages
+    /// delegating known capabilities.
+    pub fn verify_statement<M: SignInMessage + ?Sized>(
+        &self,
+        message: &M,
+        profile: ComplianceProfile,
+    ) -> Result<(), VerificationError> {
+        let expected = self.statement_hash(profile);
+        match message.statement() {
+            Some(s) if expected.matches(s) => Ok(()),
+            _ => Err(VerificationError::IncorrectStatement(
+                expected.statement().to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::message;
+    use crate::Builder;
+    use serde_json::Value;
+
+    #[test]
+    fn statement_hash() {
+        let mut message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .with_statement("Some custom statement.")
+            .build(message())
+            .unwrap();
+        let mut cap = Capability::<Value>::extract_and_verify(&message)
+            .unwrap()
+            .unwrap();
+        let profile = ComplianceProfile::Eip5573Final;
+        assert_eq!(
+            cap.statement_hash(profile),
+            &StatementHash::new(cap.to_statement())
+        );
+        assert!(cap.verify_statement(&message, profile).is_ok());
+        assert!(cap
+            .verify_statement(&message, ComplianceProfile::SpruceLegacy)
+            .is_err());
+
+        message.statement = Some(cap.to_statement_with(ComplianceProfile::SpruceLegacy));
+        assert!(cap
+            .verify_statement(&message, ComplianceProfile::SpruceLegacy)
+            .is_ok());
+        message.statement = Some("I".into());
+        assert!(cap.verify_statement(&message, profile).is_err());
+        message.statement = None;
+        assert!(cap.verify_statement(&message, profile).is_err());
+
+        let hash = cap.statement_hash(profile).clone();
+        cap.with_action_convert("https://example.com/kv", "kv/put", [])
+            .unwrap();
+        assert_ne!(cap.statement_hash(profile), &hash);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{delegator_did, Builder, Capability, EncodingError, VerifiedSession};
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use serde::Serialize;
+use siwe::TimeStamp;
+use time::OffsetDateTime;
+
+/// A previously issued delegation, as recorded in a [`CapabilityStore`].
+#[derive(Debug)]
+pub struct StoredDelegation<NB> {
+    /// The CID of the signed delegation, i.e. the message and its signature, which identifies it
+    /// in the store, as different signers can delegate the same capabilities.
+    pub id: Cid,
+    /// The CID of the delegated capabilities.
+    pub cid: Cid,
+    /// The delegated capabilities, shared so that listing delegations doesn't deep-copy every
+    /// target and ability.
+    pub capability: Arc<Capability<NB>>,
+    /// The expiration time of the delegation, if any.
+    pub expires_at: Option<TimeStamp>,
+    /// The `did:pkh` of the delegator, if known.
+    pub delegator: Option<String>,
+    /// The URI of the delegee, if known.
+    pub delegee: Option<String>,
+}
+
+impl<NB> Clone for StoredDelegation<NB> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            cid: self.cid,
+            capability: Arc::clone(&self.capability),
+            expires_at: self.expires_at.clone(),
+            delegator: self.delegator.clone(),
+            delegee: self.delegee.clone(),
+        }
+    }
+}
+
+impl<NB> StoredDelegation<NB>
+where
+    NB: Serialize + Clone,
+{
+    /// Record the delegation of a verified session.
+    pub fn from_session(session: &VerifiedSession<NB>) -> Result<Self, EncodingError> {
+        let message = session.message();
+        Ok(Self {
+            id: signed_delegation_id(&message.to_string(), session.signature()),
+            cid: session.capability().to_cid()?,
+            capability: Arc::new(session.capability().clone()),
+            expires_at: message.expiration_time.clone(),
+            delegator: Some(delegator_did(message)),
+            delegee: Some(message.uri.to_string()),
+        })
+    }
+}
+
+/// The CID of a signed message, as the raw bytes of the message followed by the signature.
+pub fn signed_delegation_id(message: &str, signature: &[u8]) -> Cid {
+    const RAW: u64 = 0x55;
+    Cid::new_v1(
+        RAW,
+        Code::Sha2_256.digest(&[message.as_bytes(), signature].concat()),
+    )
+}
+
+impl<NB> StoredDelegation<NB> {
+    /// Check whether the delegation has expired at the given time.
+    pub fn is_expired_at(&self, t: &OffsetDateTime) -> bool {
+        self.expires_at.as_ref().is_some_and(|exp| exp <= t)
+    }
+
+    /// Check whether the delegation has expired.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(&OffsetDateTime::now_utc())
+    }
+
+    /// Check whether the delegation grants anything on at least one of the targets of the given
+    /// capabilities.
+    pub fn covers_any_target(&self, requested: &Capability<NB>) -> bool {
+        let granted = self.capability.abilities();
+        requested
+            .abilities()
+            .keys()
+            .any(|target| granted.contains_key(target))
+    }
+}
+
+/// A store of previously issued delegations, which can be used as proofs and queried for audits.
+///
+/// Only listing, inserting and deleting are required; the lookups are implemented on top of
+/// listing, and may be overridden by stores which can query more efficiently.
+pub trait CapabilityStore<NB> {
+    type Error;
+
+    /// List the delegations held by the store.
+    fn delegations(&self) -> Result<Vec<StoredDelegation<NB>>, Self::Error>;
+
+    /// Insert a delegation, replacing any with the same [id](StoredDelegation::id).
+    fn put(&mut self, delegation: StoredDelegation<NB>) -> Result<(), Self::Error>;
+
+    /// Delete the delegation with the given id, returning whether it was held.
+    fn delete(&mut self, id: &Cid) -> Result<bool, Self::Error>;
+
+    /// Get the delegation with the given id.
+    fn get(&self, id: &Cid) -> Result<Option<StoredDelegation<NB>>, Self::Error> {
+        Ok(self
+            .delegations()?
+            .into_iter()
+            .find(|delegation| delegation.id == *id))
+    }
+
+    /// List the delegations issued by the given delegator.
+    fn by_delegator(&self, delegator: &str) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        Ok(self
+            .delegations()?
+            .into_iter()
+            .filter(|delegation| delegation.delegator.as_deref() == Some(delegator))
+            .collect())
+    }
+
+    /// List the delegations issued to the given delegee.
+    fn by_delegee(&self, delegee: &str) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        Ok(self
+            .delegations()?
+            .into_iter()
+            .filter(|delegation| delegation.delegee.as_deref() == Some(delegee))
+            .collect())
+    }
+
+    /// Delete the delegations expired at the given time, returning how many were deleted.
+    fn remove_expired_at(&mut self, t: &OffsetDateTime) -> Result<usize, Self::Error> {
+        let expired: Vec<Cid> = self
+            .delegations()?
+            .into_iter()
+            .filter(|delegation| delegation.is_expired_at(t))
+            .map(|delegation| delegation.id)
+            .collect();
+        for id in &expired {
+            self.delete(id)?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Delete the expired delegations, returning how many were deleted.
+    fn remove_expired(&mut self) -> Result<usize, Self::Error> {
+        self.remove_expired_at(&OffsetDateTime::now_utc())
+    }
+}
+
+/// An in-memory [`CapabilityStore`], e.g. for tests and single-instance services.
+#[derive(Debug)]
+pub struct MemoryCapabilityStore<NB> {
+    delegations: BTreeMap<Cid, StoredDelegation<NB>>,
+}
+
+impl<NB> MemoryCapabilityStore<NB> {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<NB> Default for MemoryCapabilityStore<NB> {
+    fn default() -> Self {
+        Self {
+            delegations: BTreeMap::new(),
+        }
+    }
+}
+
+impl<NB> CapabilityStore<NB> for MemoryCapabilityStore<NB> {
+    type Error = Infallible;
+
+    fn delegations(&self) -> Result<Vec<StoredDelegation<NB>>, Self::Error> {
+        Ok(self.delegations.values().cloned().collect())
+    }
+
+    fn put(&mut self, delegation: StoredDelegation<NB>) -> Result<(), Self::Error> {
+        self.delegations.insert(delegation.id, delegation);
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &Cid) -> Result<bool, Self::Error> {
+        Ok(self.delegations.remove(id).is_some())
+    }
+
+    fn get(&self, id: &Cid) -> Result<Option<StoredDelegation<NB>>, Self::Error> {
+        Ok(self.delegations.get(id).cloned())
+    }
+}
+
+impl<NB> Builder<NB> {
+    /// Add the CIDs of the stored delegations accepted by the filter as supporting proofs.
+    ///
+    /// The filter is given the capabilities requested so far along with each stored delegation,
+    /// e.g. `|requested, parent| !parent.is_expired() && parent.covers_any_target(requested)`.
+    pub fn with_proofs_from<S, F>(mut self, store: &S, mut filter: F) -> Result<Self, S::Error>
+    where
+        S: CapabilityStore<NB>,
+        F: FnMut(&Capability<NB>, &StoredDelegation<NB>) -> bool,
+    {
+        let proofs: Vec<Cid> = store
+            .delegations()?
+            .into_iter()
+            .filter(|parent| filter(self.capability(), parent))
+            .map(|parent| parent.cid)
+            .collect();
+        self = self.with_proofs(&proofs);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn proofs_from_store() {
+        let mut kv = Capability::<Value>::default();
+        kv.with_action_convert("kepler:ens:example.eth://default/kv", "kv/get", [])
+            .unwrap();
+        let kv = Arc::new(kv);
+        let mut store = MemoryCapabilityStore::new();
+        store
+            .put(StoredDelegation {
+                id: signed_delegation_id("unexpired", &[]),
+                cid: kv.to_cid().unwrap(),
+                capability: kv.clone(),
+                expires_at: None,
+                delegator: None,
+                delegee: None,
+            })
+            .unwrap();
+        store
+            .put(StoredDelegation {
+                id: signed_delegation_id("expired", &[]),
+                cid: "zdj7WWeQ43G6JJvLWQWZpyHuAMq6uYWRjkBXFad11vE2LHhQ7"
+                    .parse()
+                    .unwrap(),
+                capability: kv.clone(),
+                expires_at: Some("2022-06-21T12:00:00.000Z".parse().unwrap()),
+                delegator: None,
+                delegee: None,
+            })
+            .unwrap();
+
+        let builder = Builder::<Value>::new()
+            .with_action_convert("kepler:ens:example.eth://default/kv", "kv/get", [])
+            .unwrap()
+            .with_proofs_from(&store, |requested, parent| {
+                !parent.is_expired() && parent.covers_any_target(requested)
+            })
+            .unwrap();
+        assert_eq!(builder.capability().proof(), [kv.to_cid().unwrap()]);
+    }
+
+    #[test]
+    fn memory_store() {
+        let (message, signature) = crate::test_utils::sign(crate::test_utils::message());
+        let session = crate::SessionHeader::new(message.clone(), signature)
+            .verify::<Value>(&crate::test_utils::verification_options())
+            .unwrap();
+        let delegation = StoredDelegation::from_session(&session).unwrap();
+        let id = delegation.id;
+
+        let mut store = MemoryCapabilityStore::new();
+        store.put(delegation).unwrap();
+        assert!(store.get(&id).unwrap().is_some());
+        assert_eq!(
+            store.by_delegator(&delegator_did(&message)).unwrap().len(),
+            1
+        );
+        assert_eq!(store.by_delegee("did:key:example").unwrap().len(), 1);
+        assert!(store.by_delegee("did:key:other").unwrap().is_empty());
+
+        assert_eq!(
+            store
+                .remove_expired_at(
+                    &OffsetDateTime::from_unix_timestamp(crate::test_utils::FIXTURE_TIMESTAMP)
+                        .unwrap()
+                )
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .remove_expired_at(&OffsetDateTime::from_unix_timestamp(1655820000).unwrap())
+                .unwrap(),
+            1
+        );
+        assert!(!store.delete(&id).unwrap());
+    }
+
+    #[test]
+    fn same_grants_from_different_signers() {
+        let options = crate::test_utils::verification_options();
+        let mut store = MemoryCapabilityStore::new();
+        let mut delegators = Vec::new();
+        for seed in [1, 2] {
+            let (message, signature) = crate::test_utils::TestSigner::from_seed(seed)
+                .sign_message(crate::test_utils::message());
+            let session = crate::SessionHeader::new(message.clone(), signature)
+                .verify::<Value>(&options)
+                .unwrap();
+            store
+                .put(StoredDelegation::from_session(&session).unwrap())
+                .unwrap();
+            delegators.push(delegator_did(&message));
+        }
+        let delegations = store.delegations().unwrap();
+        assert_eq!(delegations.len(), 2);
+        assert_eq!(delegations[0].cid, delegations[1].cid);
+        for delegator in &delegators {
+            assert_eq!(store.by_delegator(delegator).unwrap().len(), 1);
+        }
+        assert_eq!(store.by_delegee("did:key:example").unwrap().len(), 2);
+    }
+}
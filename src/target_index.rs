@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use iri_string::types::UriString;
+
+/// An index of targets by their path segments, so that the targets above a given target in the
+/// path hierarchy can be found without scanning every target.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TargetIndex {
+    root: Node,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    target: Option<UriString>,
+    children: HashMap<Box<str>, Node>,
+}
+
+/// Split a target into `/`-separated segments, ignoring a trailing `/`.
+fn segments(target: &str) -> impl Iterator<Item = &str> {
+    target.strip_suffix('/').unwrap_or(target).split('/')
+}
+
+/// Whether a segment is `.` or `..`, including when its dots are percent-encoded, so that a path
+/// resolved by the caller could climb out of a granted target.
+fn is_dot_segment(segment: &str) -> bool {
+    let mut rest = segment;
+    let mut dots = 0;
+    while !rest.is_empty() {
+        rest = match rest.strip_prefix('.') {
+            Some(rest) => rest,
+            None if rest
+                .as_bytes()
+                .get(..3)
+                .is_some_and(|b| b.eq_ignore_ascii_case(b"%2e")) =>
+            {
+                &rest[3..]
+            }
+            None => return false,
+        };
+        dots += 1;
+    }
+    matches!(dots, 1 | 2)
+}
+
+impl TargetIndex {
+    pub(crate) fn new<'a>(targets: impl IntoIterator<Item = &'a UriString>) -> Self {
+        let mut index = Self::default();
+        for target in targets {
+            let node = segments(target.as_str()).fold(&mut index.root, |node, segment| {
+                node.children.entry(segment.into()).or_default()
+            });
+            // `a/b` and `a/b/` share a node, in which case the first is kept
+            node.target.get_or_insert_with(|| target.clone());
+        }
+        index
+    }
+
+    /// The indexed targets which are equal to or above the target, most specific first.
+    ///
+    /// Targets with dot-segments, e.g. `kv/../admin` or `kv/%2e%2e/admin`, are covered by nothing,
+    /// as they do not lie beneath the targets they are spelled under once resolved.
+    pub(crate) fn covering(&self, target: &str) -> Vec<&UriString> {
+        if segments(target).any(is_dot_segment) {
+            return Vec::new();
+        }
+        let mut covering = Vec::new();
+        let mut node = &self.root;
+        for segment in segments(target) {
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => break,
+            }
+            covering.extend(node.target.as_ref());
+        }
+        covering.reverse();
+        covering
+    }
+
+    /// The approximate number of heap bytes held by the index.
+    pub(crate) fn memory_footprint(&self) -> usize {
+        self.root.memory_footprint()
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.root.shrink_to_fit();
+    }
+}
+
+impl Node {
+    fn memory_footprint(&self) -> usize {
+        self.target.as_ref().map_or(0, |t| t.as_str().len())
+            + self.children.capacity() * std::mem::size_of::<(Box<str>, Node)>()
+            + self
+                .children
+                .iter()
+                .map(|(segment, child)| segment.len() + child.memory_footprint())
+                .sum::<usize>()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.children.shrink_to_fit();
+        self.children.values_mut().for_each(Node::shrink_to_fit);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn covering() {
+        let targets: Vec<UriString> = [
+            "kepler:ens:example.eth://default/kv",
+            "kepler:ens:example.eth://default/kv/public/",
+            "kepler:ens:example.eth://default/kvstore",
+            "https://example.com/",
+        ]
+        .iter()
+        .map(|t| t.parse().unwrap())
+        .collect();
+        let index = TargetIndex::new(&targets);
+        let covering = |target| {
+            index
+                .covering(target)
+                .into_iter()
+                .map(|t| t.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            covering("kepler:ens:example.eth://default/kv/public/photo.png"),
+            [
+                "kepler:ens:example.eth://default/kv/public/",
+                "kepler:ens:example.eth://default/kv"
+            ]
+        );
+        assert_eq!(
+            covering("kepler:ens:example.eth://default/kv"),
+            ["kepler:ens:example.eth://default/kv"]
+        );
+        assert!(covering("kepler:ens:example.eth://default").is_empty());
+        assert_eq!(
+            covering("https://example.com/a/b"),
+            ["https://example.com/"]
+        );
+        for escape in [
+            "kepler:ens:example.eth://default/kv/../admin",
+            "kepler:ens:example.eth://default/kv/./public",
+            "kepler:ens:example.eth://default/kv/%2e%2e/admin",
+            "kepler:ens:example.eth://default/kv/.%2E/admin",
+            "kepler:ens:example.eth://default/kv/public/..",
+        ] {
+            assert!(covering(escape).is_empty(), "{escape}");
+        }
+        for target in ["...hidden", "é%2e", "%2"] {
+            let target = format!("kepler:ens:example.eth://default/kv/{target}");
+            assert_eq!(index.covering(&target), [&targets[0]]);
+        }
+    }
+}
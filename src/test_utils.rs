@@ -0,0 +1,186 @@
+//! A deterministic signer, message factory and pre-signed fixtures, so that downstream crates can
+//! write integration tests for their ReCap-protected endpoints without an Ethereum signing stack.
+
+use crate::{
+    BuildReport, Builder, ProofStore, SessionHeader, Signer, VerificationOptions, VerifiedSession,
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+
+use k256::ecdsa::{recoverable, signature::Signer as _, SigningKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+use siwe::Message;
+use time::OffsetDateTime;
+
+/// The time at which the [fixtures](KV_GET_SESSION) are valid: 2022-06-21T12:30:00Z.
+pub const FIXTURE_TIMESTAMP: i64 = 1655814600;
+
+/// The target of the grant of the [`KV_GET_SESSION`] fixture.
+pub const FIXTURE_TARGET: &str = "https://example.com/kv";
+
+/// A session header delegating `kv/get` on [`FIXTURE_TARGET`] to `did:key:example`, signed by
+/// [`TestSigner`] over a [`message`] for `example.com`.
+pub const KV_GET_SESSION: &str = concat!(
+    "SIWE ",
+    "ZXhhbXBsZS5jb20gd2FudHMgeW91IHRvIHNpZ24gaW4gd2l0aCB5b3VyIEV0aGVyZXVtIGFjY291bnQ6",
+    "CjB4MWE2NDJmMEUzYzNhRjU0NUU3QWNCRDM4YjA3MjUxQjM5OTA5MTRGMQoKSSBmdXJ0aGVyIGF1dGhv",
+    "cml6ZSB0aGUgc3RhdGVkIFVSSSB0byBwZXJmb3JtIHRoZSBmb2xsb3dpbmcgYWN0aW9ucyBvbiBteSBi",
+    "ZWhhbGY6ICgxKSAna3YnOiAnZ2V0JyBmb3IgJ2h0dHBzOi8vZXhhbXBsZS5jb20va3YnLgoKVVJJOiBk",
+    "aWQ6a2V5OmV4YW1wbGUKVmVyc2lvbjogMQpDaGFpbiBJRDogMQpOb25jZTogbXlub25jZTEKSXNzdWVk",
+    "IEF0OiAyMDIyLTA2LTIxVDEyOjAwOjAwLjAwMFoKRXhwaXJhdGlvbiBUaW1lOiAyMDIyLTA2LTIxVDEz",
+    "OjAwOjAwLjAwMFoKUmVzb3VyY2VzOgotIHVybjpyZWNhcDpleUpoZEhRaU9uc2lhSFIwY0hNNkx5OWxl",
+    "R0Z0Y0d4bExtTnZiUzlyZGlJNmV5SnJkaTluWlhRaU9sdDdmVjE5ZlN3aWNISm1JanBiWFgw",
+    ".",
+    "0AxYM4xj_VMUrwN1u7RsGgNrA28dMUAplopcBtjtd7FBKJESuzhU0rWWMdUvFPwNH9qYRCMrDN-Qz-qa-rur4xw",
+);
+
+/// A signer with a fixed key, whose signatures are deterministic.
+#[derive(Clone, Debug)]
+pub struct TestSigner {
+    key: SigningKey,
+}
+
+impl TestSigner {
+    /// Use the fixed key.
+    pub fn new() -> Self {
+        Self::from_seed(1)
+    }
+
+    /// Use a key whose bytes all equal the seed, e.g. to sign as a second party.
+    pub fn from_seed(seed: u8) -> Self {
+        Self {
+            key: SigningKey::from_bytes(&[seed.max(1); 32]).expect("nonzero keys are valid"),
+        }
+    }
+
+    /// The Ethereum address of the signer.
+    pub fn address(&self) -> [u8; 20] {
+        let public = self.key.verifying_key().to_encoded_point(false);
+        Keccak256::digest(&public.as_bytes()[1..])[12..]
+            .try_into()
+            .expect("addresses are 20 bytes")
+    }
+
+    /// Set the address of the message to that of the signer and sign it.
+    pub fn sign_message(&self, mut message: Message) -> (Message, Vec<u8>) {
+        message.address = self.address();
+        let signature = self
+            .sign(&message.to_string())
+            .unwrap_or_else(|e| match e {});
+        (message, signature)
+    }
+}
+
+impl Default for TestSigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Signer for TestSigner {
+    type Error = Infallible;
+
+    fn sign(&self, message: &str) -> Result<Vec<u8>, Self::Error> {
+        let eip191 = format!("\x19Ethereum Signed Message:\n{}{message}", message.len());
+        let signature: recoverable::Signature = self.key.sign(eip191.as_bytes());
+        let mut bytes = signature.as_ref().to_vec();
+        bytes[64] += 27;
+        Ok(bytes)
+    }
+}
+
+/// Sign a message with the fixed key of [`TestSigner::new`], returning the message with the
+/// signer's address and the signature.
+pub fn sign(message: Message) -> (Message, Vec<u8>) {
+    TestSigner::new().sign_message(message)
+}
+
+/// A message for `example.com` to `did:key:example`, issued at 2022-06-21T12:00:00Z and expiring
+/// an hour later, with neither statement nor resources.
+pub fn message() -> Message {
+    Message {
+        domain: "example.com".parse().unwrap(),
+        address: Default::default(),
+        statement: None,
+        uri: "did:key:example".parse().unwrap(),
+        version: siwe::Version::V1,
+        chain_id: 1,
+        nonce: "mynonce1".into(),
+        issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+        expiration_time: Some("2022-06-21T13:00:00.000Z".parse().unwrap()),
+        not_before: None,
+        request_id: None,
+        resources: vec![],
+    }
+}
+
+/// Build the [`message`] with the given capabilities and sign it with [`TestSigner::new`],
+/// returning the encoded session header.
+pub fn session_header(capabilities: Builder<Value>) -> Result<String, BuildReport> {
+    let (message, signature) = sign(capabilities.build(message())?);
+    Ok(SessionHeader::encode(&message, &signature))
+}
+
+/// Build the [`message`] with the given capabilities, sign it with [`TestSigner::new`] and verify
+/// it with [`verification_options`].
+pub fn verified_session(
+    capabilities: Builder<Value>,
+) -> Result<VerifiedSession<Value>, BuildReport> {
+    let (message, signature) = sign(capabilities.build(message())?);
+    Ok(SessionHeader::new(message, signature)
+        .verify(&verification_options())
+        .expect("a fixture session verifies at the fixture timestamp"))
+}
+
+/// A [`ProofStore`] holding blocks in memory, keyed by the CID of their raw bytes.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryProofStore(HashMap<Cid, Vec<u8>>);
+
+impl ProofStore for MemoryProofStore {
+    type Error = Infallible;
+
+    async fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.0.get(cid).cloned())
+    }
+
+    async fn write(&mut self, block: Vec<u8>) -> Result<Cid, Self::Error> {
+        let cid = Cid::new_v1(0x55, Code::Sha2_256.digest(&block));
+        self.0.insert(cid, block);
+        Ok(cid)
+    }
+}
+
+/// Verification options at [`FIXTURE_TIMESTAMP`], within the validity period of the fixtures.
+pub fn verification_options() -> VerificationOptions {
+    VerificationOptions::new()
+        .with_timestamp(OffsetDateTime::from_unix_timestamp(FIXTURE_TIMESTAMP).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixtures() {
+        let header = session_header(
+            Builder::new()
+                .with_action_convert(FIXTURE_TARGET, "kv/get", [])
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(header, KV_GET_SESSION);
+        let session =
+            SessionHeader::decode_and_verify::<Value>(KV_GET_SESSION, &verification_options())
+                .unwrap();
+        assert_eq!(session.address(), &TestSigner::new().address());
+        assert_ne!(
+            TestSigner::from_seed(2).address(),
+            TestSigner::new().address()
+        );
+    }
+}
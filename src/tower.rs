@@ -0,0 +1,198 @@
+use crate::session::header_str;
+use crate::{
+    RequiredCapabilities, SessionError, SessionHeader, VerificationOptions, VerifiedSession,
+    SESSION_SCHEME,
+};
+use std::future::{ready, Future};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http1::{header, HeaderValue, Request, Response, StatusCode};
+use serde::Deserialize;
+use tower::{Layer, Service};
+
+/// A tower layer verifying the session in the `Authorization` header of each request, and
+/// inserting the [`VerifiedSession`] into the request extensions.
+///
+/// Requests are rejected with a 401 response if the session cannot be authenticated, or a 403
+/// response if its capabilities are rejected or do not satisfy the requirements, with a JSON body
+/// holding the [code](SessionError::code) and description of the error.
+pub struct RecapLayer<NB> {
+    options: Arc<VerificationOptions>,
+    required: Arc<RequiredCapabilities>,
+    _nota_bene: PhantomData<fn() -> NB>,
+}
+
+impl<NB> RecapLayer<NB> {
+    /// Create a layer verifying sessions with the given options.
+    pub fn new(options: impl Into<Arc<VerificationOptions>>) -> Self {
+        Self {
+            options: options.into(),
+            required: Default::default(),
+            _nota_bene: PhantomData,
+        }
+    }
+
+    /// Require verified sessions to have been delegated the given capabilities.
+    pub fn require(mut self, required: RequiredCapabilities) -> Self {
+        self.required = Arc::new(required);
+        self
+    }
+}
+
+impl<NB> Clone for RecapLayer<NB> {
+    fn clone(&self) -> Self {
+        Self {
+            options: self.options.clone(),
+            required: self.required.clone(),
+            _nota_bene: PhantomData,
+        }
+    }
+}
+
+impl<S, NB> Layer<S> for RecapLayer<NB> {
+    type Service = RecapService<S, NB>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecapService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service produced by a [`RecapLayer`].
+pub struct RecapService<S, NB> {
+    inner: S,
+    layer: RecapLayer<NB>,
+}
+
+impl<S: Clone, NB> Clone for RecapService<S, NB> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, NB> RecapService<S, NB>
+where
+    NB: for<'a> Deserialize<'a> + Clone,
+{
+    fn verify<B>(&self, request: &Request<B>) -> Result<VerifiedSession<NB>, SessionError> {
+        let header = header_str(
+            request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .map(HeaderValue::as_bytes),
+        )?;
+        let session = SessionHeader::decode_and_verify(header, &self.layer.options)?;
+        session.require(&self.layer.required)?;
+        Ok(session)
+    }
+}
+
+impl<S, NB, ReqBody, ResBody> Service<Request<ReqBody>> for RecapService<S, NB>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: From<String> + Send + 'static,
+    NB: for<'a> Deserialize<'a> + Clone + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        match self.verify(&request) {
+            Ok(session) => {
+                request.extensions_mut().insert(session);
+                Box::pin(self.inner.call(request))
+            }
+            Err(e) => Box::pin(ready(Ok(rejection(&e)))),
+        }
+    }
+}
+
+fn rejection<B: From<String>>(error: &SessionError) -> Response<B> {
+    let mut response = Response::new(B::from(error.to_json().to_string()));
+    *response.status_mut() =
+        StatusCode::from_u16(error.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    if error.status() == StatusCode::UNAUTHORIZED.as_u16() {
+        headers.insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static(SESSION_SCHEME),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{session_header, verification_options, TestSigner};
+    use crate::Builder;
+    use serde_json::Value;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn layer() {
+        let required = RequiredCapabilities::new().with_action(
+            "https://example.com/kv".parse().unwrap(),
+            "kv/get".parse().unwrap(),
+        );
+        let service = RecapLayer::<Value>::new(verification_options())
+            .require(required)
+            .layer(service_fn(|request: Request<()>| async move {
+                let session = request
+                    .extensions()
+                    .get::<VerifiedSession<Value>>()
+                    .unwrap();
+                Ok::<_, Infallible>(Response::new(format!("{:?}", session.address())))
+            }));
+
+        let call = |target: &str| {
+            let header = session_header(
+                Builder::new()
+                    .with_action_convert(target, "kv/*", [])
+                    .unwrap(),
+            )
+            .unwrap();
+            let request = Request::builder()
+                .header(header::AUTHORIZATION, header)
+                .body(())
+                .unwrap();
+            service.clone().oneshot(request)
+        };
+
+        let response = call("https://example.com/*");
+        assert_eq!(response.await.unwrap().status(), StatusCode::FORBIDDEN);
+        let response = call("https://example.com/kv").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.into_body(),
+            format!("{:?}", TestSigner::new().address())
+        );
+
+        let response = service
+            .oneshot(Request::builder().body(()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], SESSION_SCHEME);
+    }
+}
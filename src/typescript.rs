@@ -0,0 +1,53 @@
+use crate::prepared::SigningRequest;
+use crate::SigningResponse;
+use std::collections::HashMap;
+
+use serde_json::Value;
+use ts_rs::TS;
+
+/// The JSON of a ReCap capability object, as encoded in the resource URI.
+#[derive(TS)]
+#[ts(rename = "Recap")]
+#[allow(dead_code)]
+struct RecapObject {
+    /// The abilities granted on each target URI, along with their nota benes.
+    att: HashMap<String, HashMap<String, Vec<HashMap<String, Value>>>>,
+    /// The CIDs of the supporting proofs.
+    prf: Vec<String>,
+}
+
+/// The lines of a ReCap statement: the preamble, followed by one numbered line per target
+/// and namespace.
+#[derive(TS)]
+#[allow(dead_code)]
+pub(crate) struct StatementLines(Vec<String>);
+
+/// TypeScript declarations of the JSON structures exchanged with frontends: the capability
+/// object, the statement lines, and the signing request and response, along with the
+/// `JsonValue` type of nota benes.
+pub fn typescript_definitions() -> String {
+    [
+        Value::decl(),
+        RecapObject::decl(),
+        StatementLines::decl(),
+        SigningRequest::decl(),
+        SigningResponse::decl(),
+    ]
+    .map(|decl| format!("export {decl}\n"))
+    .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn definitions() {
+        let definitions = typescript_definitions();
+        assert!(definitions.contains("export type JsonValue ="));
+        assert!(definitions.contains("export type Recap = {"));
+        assert!(definitions.contains("export type StatementLines = Array<string>;"));
+        assert!(definitions.contains("statementLines: StatementLines"));
+        assert!(definitions.contains("capabilityCid: string | null"));
+    }
+}
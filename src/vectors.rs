@@ -0,0 +1,124 @@
+use crate::Capability;
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use siwe::Message;
+
+/// The EIP-5573 test vectors shipped with this crate.
+pub const EIP5573_VECTORS: &str = include_str!("../tests/vectors/eip5573.json");
+
+/// An EIP-5573 test vector: a SIWE message, and whether it is valid along with the ReCap statement
+/// and decoded capability object expected of it, so that implementations can be certified
+/// against the same corpus.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// A description of the case.
+    pub name: String,
+    /// The text of the SIWE message.
+    pub message: String,
+    /// Whether the ReCap of the message, if any, is valid.
+    pub valid: bool,
+    /// The expected ReCap statement, if the message is valid and has a ReCap.
+    #[serde(default)]
+    pub statement: Option<String>,
+    /// The expected decoded capability object, if the message is valid and has a ReCap.
+    #[serde(default)]
+    pub recap: Option<Value>,
+}
+
+impl TestVector {
+    /// Load a JSON array of test vectors.
+    pub fn load(json: &str) -> Result<Vec<Self>, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Read a JSON file of test vectors.
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Vec<Self>> {
+        Ok(Self::load(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Check the outcome of an implementation processing the message: either the decoded
+    /// capability object and ReCap statement, `None` if the message has no ReCap, or an error if
+    /// the message was rejected.
+    pub fn check<E>(&self, outcome: Result<Option<(Value, String)>, E>) -> Result<(), VectorFailure>
+    where
+        E: Display,
+    {
+        let fail = |reason: String| {
+            Err(VectorFailure {
+                name: self.name.clone(),
+                reason,
+            })
+        };
+        match (outcome, self.valid) {
+            (Err(e), true) => fail(format!("rejected a valid message: {e}")),
+            (Ok(_), false) => fail("accepted an invalid message".into()),
+            (Err(_), false) => Ok(()),
+            (Ok(decoded), true) => {
+                let (recap, statement) = decoded.unzip();
+                if recap != self.recap {
+                    fail(format!(
+                        "decoded {}, expected {}",
+                        recap.unwrap_or_default(),
+                        self.recap.clone().unwrap_or_default()
+                    ))
+                } else if statement != self.statement {
+                    fail(format!(
+                        "stated {statement:?}, expected {:?}",
+                        self.statement
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Run the vector against this crate.
+    pub fn run(&self) -> Result<(), VectorFailure> {
+        self.check(
+            self.message
+                .parse::<Message>()
+                .map_err(|e| e.to_string())
+                .and_then(|message| {
+                    Capability::<Value>::extract_and_verify(&message).map_err(|e| e.to_string())
+                })
+                .and_then(|capability| {
+                    capability
+                        .map(|capability| {
+                            serde_json::to_value(&capability)
+                                .map(|recap| (recap, capability.to_statement()))
+                                .map_err(|e| e.to_string())
+                        })
+                        .transpose()
+                }),
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{name}: {reason}")]
+pub struct VectorFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn eip5573_vectors() {
+        let vectors = TestVector::load(EIP5573_VECTORS).unwrap();
+        assert!(vectors.iter().any(|vector| !vector.valid));
+        for vector in &vectors {
+            vector.run().unwrap();
+        }
+
+        let vector = &vectors[0];
+        assert!(vector.check(Ok::<_, String>(None)).is_err());
+        assert!(vector.check(Err("unsupported")).is_err());
+    }
+}
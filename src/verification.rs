@@ -0,0 +1,337 @@
+use crate::{
+    base64url, AbilityPolicy, AbilitySet, AttOrdering, Capability, CaseNormalization,
+    ComplianceProfile, DecodingError, DecodingLimits, DecodingProfile, NamespaceRegistry,
+    ProofPolicy, SignInMessage, VerificationError, RESOURCE_PREFIX,
+};
+
+use std::collections::BTreeSet;
+
+use http::uri::Authority;
+use serde::Deserialize;
+use siwe::Message;
+use time::OffsetDateTime;
+
+/// Optional policies applied when extracting capabilities from a SIWE message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationOptions {
+    registry: Option<NamespaceRegistry>,
+    case_normalization: CaseNormalization,
+    decoding_profile: DecodingProfile,
+    decoding_limits: DecodingLimits,
+    allow_degenerate: bool,
+    require_ordered_keys: bool,
+    compliance_profile: ComplianceProfile,
+    allowed_reserved: Option<BTreeSet<String>>,
+    ability_policy: Option<AbilityPolicy>,
+    proof_policy: Option<ProofPolicy>,
+    allowed_schemes: Option<BTreeSet<String>>,
+    allowed_abilities: Option<AbilitySet>,
+    domain: Option<Authority>,
+    timestamp: Option<OffsetDateTime>,
+}
+
+impl VerificationOptions {
+    /// Create options which apply no additional policies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject grants which are not declared in the given registry.
+    pub fn with_namespace_registry(mut self, registry: NamespaceRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Normalize the case of the abilities of extracted capabilities, before any other policy is applied.
+    pub fn with_case_normalization(mut self, normalization: CaseNormalization) -> Self {
+        self.case_normalization = normalization;
+        self
+    }
+
+    /// Decode ReCaps with the given profile, e.g. to reject payloads which are not canonical.
+    pub fn with_decoding_profile(mut self, profile: DecodingProfile) -> Self {
+        self.decoding_profile = profile;
+        self
+    }
+
+    /// Reject ReCaps whose note-benes exceed the given limits, rather than the default limits.
+    pub fn with_decoding_limits(mut self, limits: DecodingLimits) -> Self {
+        self.decoding_limits = limits;
+        self
+    }
+
+    /// Accept ReCaps with degenerate grants, i.e. targets with no abilities or abilities with an
+    /// empty namespace or name, which are otherwise rejected.
+    pub fn allow_degenerate_grants(mut self) -> Self {
+        self.allow_degenerate = true;
+        self
+    }
+
+    /// Expect ReCap statements in the wording of the given compliance profile, e.g. to consume
+    /// messages produced by early SpruceID releases.
+    pub fn with_compliance_profile(mut self, profile: ComplianceProfile) -> Self {
+        self.compliance_profile = profile;
+        self
+    }
+
+    /// Reject ReCaps whose keys are in neither JCS nor length-first [order](AttOrdering), which the
+    /// strict decoding profile always rejects.
+    pub fn require_ordered_keys(mut self) -> Self {
+        self.require_ordered_keys = true;
+        self
+    }
+
+    /// Reject grants in reserved namespaces such as `ucan`, except for the given allowed namespaces.
+    pub fn reject_reserved_namespaces<A>(mut self, allowed: A) -> Self
+    where
+        A: IntoIterator,
+        A::Item: Into<String>,
+    {
+        self.allowed_reserved = Some(allowed.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Reject grants whose abilities violate the given policy.
+    pub fn with_ability_policy(mut self, policy: AbilityPolicy) -> Self {
+        self.ability_policy = Some(policy);
+        self
+    }
+
+    /// Reject capabilities whose proofs are not accepted by the given policy.
+    pub fn with_proof_policy(mut self, policy: ProofPolicy) -> Self {
+        self.proof_policy = Some(policy);
+        self
+    }
+
+    /// Reject grants for targets whose URI scheme is not one of the given schemes, e.g. `kepler`
+    /// and `https`.
+    pub fn with_allowed_schemes<S>(mut self, schemes: S) -> Self
+    where
+        S: IntoIterator,
+        S::Item: AsRef<str>,
+    {
+        self.allowed_schemes = Some(
+            schemes
+                .into_iter()
+                .map(|scheme| scheme.as_ref().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Reject grants whose ability is not contained in the given set, where wildcards such as
+    /// `kv/*` allow every ability they cover.
+    pub fn with_allowed_abilities(mut self, abilities: AbilitySet) -> Self {
+        self.allowed_abilities = Some(abilities);
+        self
+    }
+
+    /// Require signed messages to be issued for the given domain.
+    pub fn with_domain(mut self, domain: Authority) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// Check the validity period of signed messages at the given time, rather than the current time.
+    pub fn with_timestamp(mut self, timestamp: OffsetDateTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub(crate) fn decoding_profile(&self) -> DecodingProfile {
+        self.decoding_profile
+    }
+
+    pub(crate) fn decoding_limits(&self) -> &DecodingLimits {
+        &self.decoding_limits
+    }
+
+    pub(crate) fn allows_degenerate_grants(&self) -> bool {
+        self.allow_degenerate
+    }
+
+    pub(crate) fn requires_ordered_keys(&self) -> bool {
+        self.require_ordered_keys
+    }
+
+    pub(crate) fn compliance_profile(&self) -> ComplianceProfile {
+        self.compliance_profile
+    }
+
+    /// The time at which validity periods are checked.
+    pub(crate) fn now(&self) -> OffsetDateTime {
+        self.timestamp.unwrap_or_else(OffsetDateTime::now_utc)
+    }
+
+    /// Check the validity period and domain of a signed message.
+    pub(crate) fn check_message(&self, message: &Message) -> Result<(), siwe::VerificationError> {
+        let valid = match &self.timestamp {
+            Some(timestamp) => message.valid_at(timestamp),
+            None => message.valid_now(),
+        };
+        if !valid {
+            return Err(siwe::VerificationError::Time);
+        }
+        match &self.domain {
+            Some(domain) if *domain != message.domain => {
+                Err(siwe::VerificationError::DomainMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check<NB>(&self, capability: &Capability<NB>) -> Result<(), VerificationError> {
+        if let Some(policy) = &self.ability_policy {
+            if let Some((target, source)) = capability.policy_violations(policy).into_iter().next()
+            {
+                return Err(VerificationError::InvalidAbility { target, source });
+            }
+        }
+        if let Some(allowed) = &self.allowed_reserved {
+            if let Some((target, ability)) = capability.reserved_grants(allowed).next() {
+                return Err(VerificationError::ReservedNamespace {
+                    target: target.clone(),
+                    ability: ability.clone(),
+                });
+            }
+        }
+        if let Some(schemes) = &self.allowed_schemes {
+            if let Some(target) = capability.disallowed_targets(schemes).next() {
+                return Err(VerificationError::DisallowedScheme(target.clone()));
+            }
+        }
+        if let Some(allowed) = &self.allowed_abilities {
+            if let Some((target, ability)) = capability.disallowed_grants(allowed).next() {
+                return Err(VerificationError::DisallowedAbility {
+                    target: target.clone(),
+                    ability: ability.clone(),
+                });
+            }
+        }
+        if let Some(policy) = &self.proof_policy {
+            capability.check_proofs(policy)?;
+        }
+        if let Some(registry) = &self.registry {
+            if let Some((target, source)) = registry.violations(capability).into_iter().next() {
+                return Err(VerificationError::Registry { target, source });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: for<'a> Deserialize<'a> + Clone,
+{
+    /// Extract the encoded capabilities from a SIWE message, or any other
+    /// [CAIP-122](crate::SignInMessage) message, ensuring the correctness of the statement and
+    /// applying the given policies.
+    pub fn extract_and_verify_with<M: SignInMessage + ?Sized>(
+        message: &M,
+        options: &VerificationOptions,
+    ) -> Result<Option<Self>, VerificationError> {
+        let capability = Self::extract_and_verify_as(
+            message,
+            options.decoding_profile,
+            &options.decoding_limits,
+            options.allow_degenerate,
+            options.compliance_profile,
+        )?
+        .map(|capability| match options.case_normalization {
+            CaseNormalization::Preserve => capability,
+            normalization => capability.normalized(normalization),
+        });
+        if let Some(capability) = &capability {
+            if options.require_ordered_keys {
+                if let Some(encoded) = message
+                    .resources()
+                    .last()
+                    .and_then(|uri| uri.strip_prefix(RESOURCE_PREFIX))
+                {
+                    AttOrdering::check(&base64url::decode(encoded).map_err(DecodingError::from)?)?;
+                }
+            }
+            options.check(capability)?;
+        }
+        Ok(capability)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Builder, NamespaceSpec, RegistryError};
+    use serde_json::Value;
+
+    #[test]
+    fn registry_on_decode() {
+        let message = Builder::<Value>::new()
+            .with_action_convert("credential:*", "credentail/present", [])
+            .unwrap()
+            .build(Message {
+                domain: "example.com".parse().unwrap(),
+                address: Default::default(),
+                statement: None,
+                uri: "did:key:example".parse().unwrap(),
+                version: siwe::Version::V1,
+                chain_id: 1,
+                nonce: "mynonce1".into(),
+                issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                expiration_time: None,
+                not_before: None,
+                request_id: None,
+                resources: vec![],
+            })
+            .unwrap();
+
+        assert!(Capability::<Value>::extract_and_verify_with(
+            &message,
+            &VerificationOptions::new()
+        )
+        .unwrap()
+        .is_some());
+        let options = VerificationOptions::new().with_namespace_registry(
+            NamespaceRegistry::new()
+                .with_namespace("credential", NamespaceSpec::new().with_ability("present")),
+        );
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify_with(&message, &options),
+            Err(VerificationError::Registry {
+                source: RegistryError::UnknownNamespace(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reserved_on_decode() {
+        let message = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "ucan/*", [])
+            .unwrap()
+            .allow_reserved_namespace("ucan")
+            .build(Message {
+                domain: "example.com".parse().unwrap(),
+                address: Default::default(),
+                statement: None,
+                uri: "did:key:example".parse().unwrap(),
+                version: siwe::Version::V1,
+                chain_id: 1,
+                nonce: "mynonce1".into(),
+                issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+                expiration_time: None,
+                not_before: None,
+                request_id: None,
+                resources: vec![],
+            })
+            .unwrap();
+
+        let reject = VerificationOptions::new().reject_reserved_namespaces(None::<String>);
+        assert!(matches!(
+            Capability::<Value>::extract_and_verify_with(&message, &reject),
+            Err(VerificationError::ReservedNamespace { .. })
+        ));
+        let allow = VerificationOptions::new().reject_reserved_namespaces(["ucan"]);
+        assert!(Capability::<Value>::extract_and_verify_with(&message, &allow).is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{PreparedDelegation, SessionHeader, SigningResponse, SigningResponseError};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use siwe::eip55;
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    #[serde(default)]
+    result: Option<String>,
+    #[serde(default)]
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+impl PreparedDelegation {
+    /// Produce the `personal_sign` request for WalletConnect or any EIP-1193 provider, with the
+    /// hex-encoded message and the address of the signer as params.
+    pub fn to_personal_sign_request(&self) -> Value {
+        let message: String = self
+            .message
+            .to_string()
+            .bytes()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        json!({
+            "method": "personal_sign",
+            "params": [format!("0x{message}"), eip55(&self.message.address)],
+        })
+    }
+
+    /// Ingest the JSON-RPC response of the wallet to the
+    /// [`personal_sign` request](Self::to_personal_sign_request), checking the signature against
+    /// the prepared message and returning the session to present.
+    pub fn ingest_personal_sign_response(
+        self,
+        response: &str,
+    ) -> Result<SessionHeader, SigningResponseError> {
+        let response: JsonRpcResponse =
+            serde_json::from_str(response).map_err(SigningResponseError::Json)?;
+        match (response.result, response.error) {
+            (_, Some(JsonRpcError { code, message })) => {
+                Err(SigningResponseError::Rejected { code, message })
+            }
+            (Some(signature), None) => self.ingest_signing_response(&SigningResponse {
+                signature,
+                message: None,
+            }),
+            (None, None) => Err(SigningResponseError::Signature(String::new())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sign;
+    use crate::Builder;
+
+    #[test]
+    fn personal_sign() {
+        let mut message = crate::test_utils::message();
+        message.address = sign(message.clone()).0.address;
+        let prepared = Builder::<Value>::new()
+            .with_action_convert("https://example.com/kv", "kv/get", [])
+            .unwrap()
+            .prepare(message)
+            .unwrap();
+
+        let request = prepared.to_personal_sign_request();
+        assert_eq!(request["method"], "personal_sign");
+        let hex = request["params"][0]
+            .as_str()
+            .unwrap()
+            .strip_prefix("0x")
+            .unwrap();
+        assert_eq!(hex.len(), prepared.message.to_string().len() * 2);
+        assert_eq!(request["params"][1], eip55(&prepared.message.address));
+
+        assert!(matches!(
+            prepared.clone().ingest_personal_sign_response(
+                r#"{"id": 1, "jsonrpc": "2.0", "error": {"code": 4001, "message": "User rejected"}}"#
+            ),
+            Err(SigningResponseError::Rejected { code: 4001, .. })
+        ));
+
+        let (_, signature) = sign(prepared.message.clone());
+        let signature: String = signature.iter().map(|b| format!("{b:02x}")).collect();
+        let header = prepared
+            .clone()
+            .ingest_personal_sign_response(&format!(
+                r#"{{"id": 1, "jsonrpc": "2.0", "result": "0x{signature}"}}"#
+            ))
+            .unwrap();
+        assert_eq!(header.message(), &prepared.message);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::Capability;
+use cid::Cid;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use iri_string::types::UriString;
+use serde_json::Value;
+use siwe::Message;
+use wasm_bindgen::prelude::*;
+
+/// A set of capabilities with JSON nota benes, exported to JavaScript as `Capability`.
+#[wasm_bindgen(js_name = Capability)]
+#[derive(Clone, Debug, Default)]
+pub struct WasmCapability {
+    inner: Capability<Value>,
+}
+
+#[wasm_bindgen(js_class = Capability)]
+impl WasmCapability {
+    /// Create a new empty set of capabilities.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode capabilities from a `urn:recap:` URI.
+    #[wasm_bindgen(js_name = fromUri)]
+    pub fn from_uri(uri: &str) -> Result<WasmCapability, JsError> {
+        let uri = UriString::try_from(uri)?;
+        Ok(Capability::try_from(&uri)?.into())
+    }
+
+    /// Allow an action for the given target, with an optional JSON array of nota bene objects.
+    #[wasm_bindgen(js_name = withAction)]
+    pub fn with_action(
+        &mut self,
+        target: &str,
+        ability: &str,
+        nota_benes: Option<String>,
+    ) -> Result<(), JsError> {
+        let nota_benes: Vec<BTreeMap<String, Value>> = nota_benes
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?
+            .unwrap_or_default();
+        let target = UriString::try_from(target)?;
+        let ability = crate::parse_ability(ability)?;
+        self.inner.with_action(target, ability, nota_benes);
+        Ok(())
+    }
+
+    /// Add a proof, given as a CID string.
+    #[wasm_bindgen(js_name = withProof)]
+    pub fn with_proof(&mut self, cid: &str) -> Result<(), JsError> {
+        let cid = Cid::from_str(cid)?;
+        self.inner = std::mem::take(&mut self.inner).with_proof(&cid);
+        Ok(())
+    }
+
+    /// The ReCap statement for these capabilities.
+    #[wasm_bindgen(js_name = toStatement)]
+    pub fn to_statement(&self) -> String {
+        self.inner.to_statement()
+    }
+
+    /// Encode the capabilities as a `urn:recap:` URI.
+    pub fn encode(&self) -> Result<String, JsError> {
+        Ok(UriString::try_from(&self.inner)?.to_string())
+    }
+
+    /// The canonical JSON form of the capabilities.
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        Ok(serde_jcs::to_string(&self.inner)?)
+    }
+
+    /// Apply the capabilities to a SIWE message string, returning the message string to be signed.
+    #[wasm_bindgen(js_name = buildMessage)]
+    pub fn build_message(&self, message: &str) -> Result<String, JsError> {
+        let message = Message::from_str(message)?;
+        Ok(self.inner.build_message(message)?.to_string())
+    }
+}
+
+/// Extract the capabilities from a SIWE message string, ensuring the correctness of the statement.
+#[wasm_bindgen(js_name = extractAndVerify)]
+pub fn extract_and_verify(message: &str) -> Result<Option<WasmCapability>, JsError> {
+    let message = Message::from_str(message)?;
+    Ok(Capability::extract_and_verify(&message)?.map(Into::into))
+}
+
+impl From<Capability<Value>> for WasmCapability {
+    fn from(inner: Capability<Value>) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<WasmCapability> for Capability<Value> {
+    fn from(capability: WasmCapability) -> Self {
+        capability.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message() -> String {
+        Message {
+            domain: "example.com".parse().unwrap(),
+            address: Default::default(),
+            statement: None,
+            uri: "did:key:example".parse().unwrap(),
+            version: siwe::Version::V1,
+            chain_id: 1,
+            nonce: "mynonce1".into(),
+            issued_at: "2022-06-21T12:00:00.000Z".parse().unwrap(),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: vec![],
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn wasm_roundtrip() {
+        let mut capability = WasmCapability::new();
+        capability
+            .with_action("https://example.com/kv", "kv/get", None)
+            .unwrap();
+        capability
+            .with_action(
+                "https://example.com/kv",
+                "kv/put",
+                Some(r#"[{"max_size":10}]"#.into()),
+            )
+            .unwrap();
+
+        let signed = capability.build_message(&message()).unwrap();
+        let extracted = extract_and_verify(&signed).unwrap().unwrap();
+        assert_eq!(extracted.to_json().unwrap(), capability.to_json().unwrap());
+        assert_eq!(
+            WasmCapability::from_uri(&capability.encode().unwrap())
+                .unwrap()
+                .to_statement(),
+            capability.to_statement()
+        );
+        assert!(extract_and_verify(&message()).unwrap().is_none());
+    }
+}
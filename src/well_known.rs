@@ -0,0 +1,85 @@
+//! Abilities used across the ReCap ecosystem, to avoid misspelling them as strings.
+//!
+//! ```
+//! use siwe_recap::{well_known, Builder};
+//!
+//! let builder = Builder::<serde_json::Value>::new()
+//!     .with_action_convert("kepler:ens:example.eth://default/kv", well_known::kv::GET, [])
+//!     .unwrap();
+//! assert!(builder
+//!     .capability()
+//!     .can_do_str("kepler:ens:example.eth://default/kv", well_known::kv::GET)
+//!     .is_some());
+//! ```
+
+use crate::AbilityStr;
+
+/// Key-value storage abilities.
+pub mod kv {
+    use super::AbilityStr;
+
+    /// The `kv` namespace.
+    pub const NAMESPACE: &str = "kv";
+    /// Read a value.
+    pub const GET: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "get");
+    /// Write a value.
+    pub const PUT: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "put");
+    /// List the keys.
+    pub const LIST: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "list");
+    /// Delete a value.
+    pub const DELETE: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "delete");
+    /// Read the metadata of a value.
+    pub const METADATA: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "metadata");
+}
+
+/// Verifiable credential abilities.
+pub mod credential {
+    use super::AbilityStr;
+
+    /// The `credential` namespace.
+    pub const NAMESPACE: &str = "credential";
+    /// Present a credential.
+    pub const PRESENT: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "present");
+    /// Issue a credential.
+    pub const ISSUE: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "issue");
+}
+
+/// Orbit management abilities.
+pub mod orbit {
+    use super::AbilityStr;
+
+    /// The `orbit` namespace.
+    pub const NAMESPACE: &str = "orbit";
+    /// Host an orbit.
+    pub const HOST: AbilityStr<'static> = AbilityStr::from_valid_parts(NAMESPACE, "host");
+}
+
+/// Every well-known ability.
+pub const ALL: &[AbilityStr<'static>] = &[
+    kv::GET,
+    kv::PUT,
+    kv::LIST,
+    kv::DELETE,
+    kv::METADATA,
+    credential::PRESENT,
+    credential::ISSUE,
+    orbit::HOST,
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ucan_capabilities_object::Ability;
+
+    #[test]
+    fn well_known() {
+        for ability in ALL {
+            assert_eq!(AbilityStr::parse(&ability.to_string()).unwrap(), *ability);
+            assert_eq!(ability.to_ability().unwrap(), *ability);
+        }
+        assert_eq!(
+            Ability::try_from(kv::GET).unwrap(),
+            "kv/get".parse::<Ability>().unwrap()
+        );
+    }
+}
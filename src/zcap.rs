@@ -0,0 +1,162 @@
+use crate::Capability;
+use cid::Cid;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use iri_string::types::UriString;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, OneOrMany};
+use ucan_capabilities_object::Ability;
+
+const ZCAP_CONTEXT: &str = "https://w3id.org/zcap/v1";
+const CID_PREFIX: &str = "urn:cid:";
+
+/// A ZCAP-LD delegation for a single invocation target, without a proof.
+///
+/// Each ability granted on the target becomes an `allowedAction`, and its nota benes become the
+/// `caveats` of that action. The proofs of the capabilities become `parentCapability` references
+/// of the form `urn:cid:<base58btc cid>`.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZcapDelegation<NB> {
+    #[serde(rename = "@context")]
+    context: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    controller: Option<String>,
+    invocation_target: UriString,
+    #[serde_as(as = "OneOrMany<_>")]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    parent_capability: Vec<String>,
+    allowed_action: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    caveats: BTreeMap<String, Vec<BTreeMap<String, NB>>>,
+}
+
+impl<NB> ZcapDelegation<NB> {
+    /// The delegee of the zcap, if set.
+    pub fn controller(&self) -> Option<&str> {
+        self.controller.as_deref()
+    }
+
+    /// Set the delegee of the zcap.
+    pub fn with_controller(mut self, controller: impl Into<String>) -> Self {
+        self.controller = Some(controller.into());
+        self
+    }
+
+    /// The target of the zcap.
+    pub fn invocation_target(&self) -> &UriString {
+        &self.invocation_target
+    }
+
+    /// The actions allowed on the target.
+    pub fn allowed_actions(&self) -> &[String] {
+        &self.allowed_action
+    }
+}
+
+impl<NB> Capability<NB>
+where
+    NB: Clone,
+{
+    /// Convert the capabilities to one ZCAP-LD delegation per target.
+    pub fn to_zcaps(&self) -> Vec<ZcapDelegation<NB>> {
+        let parents: Vec<String> = self
+            .proof()
+            .iter()
+            .map(|cid| {
+                let cid = cid
+                    .to_string_of_base(cid::multibase::Base::Base58Btc)
+                    .unwrap_or_else(|_| cid.to_string());
+                format!("{CID_PREFIX}{cid}")
+            })
+            .collect();
+        self.abilities()
+            .iter()
+            .map(|(target, abilities)| ZcapDelegation {
+                context: ZCAP_CONTEXT.to_string(),
+                controller: None,
+                invocation_target: target.clone(),
+                parent_capability: parents.clone(),
+                allowed_action: abilities.keys().map(ToString::to_string).collect(),
+                caveats: abilities
+                    .iter()
+                    .filter(|(_, nbs)| nbs.iter().any(|nb| !nb.is_empty()))
+                    .map(|(ability, nbs)| (ability.to_string(), nbs.to_vec()))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Convert ZCAP-LD delegations back to capabilities, collecting the `urn:cid:` parent references
+    /// as proofs.
+    pub fn from_zcaps<'a>(
+        zcaps: impl IntoIterator<Item = &'a ZcapDelegation<NB>>,
+    ) -> Result<Self, ZcapError>
+    where
+        NB: 'a,
+    {
+        let mut capability = Self::new();
+        let mut proofs: Vec<Cid> = Vec::new();
+        for zcap in zcaps {
+            for parent in &zcap.parent_capability {
+                let cid = parent
+                    .strip_prefix(CID_PREFIX)
+                    .and_then(|cid| Cid::from_str(cid).ok())
+                    .ok_or_else(|| ZcapError::ParentCapability(parent.clone()))?;
+                if !proofs.contains(&cid) {
+                    proofs.push(cid);
+                }
+            }
+            for action in &zcap.allowed_action {
+                let ability = Ability::from_str(action)
+                    .map_err(|_| ZcapError::AllowedAction(action.clone()))?;
+                let caveats = zcap.caveats.get(action).cloned().unwrap_or_default();
+                capability.with_action(zcap.invocation_target.clone(), ability, caveats);
+            }
+        }
+        Ok(capability.with_proofs(&proofs))
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ZcapError {
+    #[error("allowed action {0} is not an ability")]
+    AllowedAction(String),
+    #[error("parent capability {0} is not a urn:cid reference")]
+    ParentCapability(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    const JSON_CAP: &str = include_str!("../tests/serialized_cap.json");
+
+    #[test]
+    fn zcap_roundtrip() {
+        let capability: Capability<Value> = serde_json::from_str(JSON_CAP).unwrap();
+        let zcaps = capability.to_zcaps();
+        assert_eq!(zcaps.len(), capability.abilities().len());
+
+        let json = serde_json::to_value(&zcaps[1]).unwrap();
+        assert_eq!(json["@context"], ZCAP_CONTEXT);
+        assert_eq!(json["invocationTarget"], "mailto:username@example.com");
+        assert_eq!(
+            json["allowedAction"],
+            serde_json::json!(["msg/receive", "msg/send"])
+        );
+        assert!(json["parentCapability"]
+            .as_str()
+            .unwrap()
+            .starts_with("urn:cid:z"));
+
+        let decoded = Capability::from_zcaps(&zcaps).unwrap();
+        assert_eq!(
+            serde_jcs::to_string(&decoded).unwrap(),
+            serde_jcs::to_string(&capability).unwrap()
+        );
+    }
+}